@@ -1,29 +1,53 @@
-use std::borrow::Borrow;
 use std::fmt::{Display, Formatter};
-use std::io::Empty;
-use std::num::ParseIntError;
 use std::str::FromStr;
 use crate::board::generator::{Move, MoveGenerator};
 use crate::board::perft::{Perft, PerftResult};
 use crate::board::piece::Piece;
-use crate::board::piece::Piece::{BISHOP, KNIGHT, QUEEN, ROOK};
+use crate::board::piece::Piece::{BISHOP, KING, KNIGHT, PAWN, QUEEN, ROOK};
 use crate::{Color, DEFAULT_COLORS, DEFAULT_PIECES};
-use crate::board::color::inverse_color;
-use crate::board::fen::Fen;
-
+use crate::board::color::{color_index, inverse_color};
+use crate::board::defaults::{BLACK_KING_SIDE, BLACK_QUEEN_SIDE, DEFAULT_CASTLING, DEFAULT_CASTLING_FILES, WHITE_KING_SIDE, WHITE_QUEEN_SIDE};
+use crate::board::fen::{Fen, FenError};
+use crate::board::crazyhouse::{format_pockets, parse_pockets, pocket_index, Pockets, POCKET_PIECES};
+use crate::board::square::{parse_square, square_name};
+use crate::board::variant::{parse_checks, Outcome, Variant, HILL};
+
+#[cfg(test)]
 mod tests;
+pub(crate) mod chess960;
 pub(crate) mod color;
+pub(crate) mod crazyhouse;
 pub(crate) mod defaults;
 pub(crate) mod fen;
 pub(crate) mod generator;
 pub(crate) mod perft;
 pub(crate) mod piece;
+pub(crate) mod san;
+pub(crate) mod square;
+pub(crate) mod variant;
+pub(crate) mod zobrist;
+
+const KNIGHT_OFFSETS: [(isize, isize); 8] = [(2, 1), (1, 2), (-1, 2), (-2, 1), (-2, -1), (-1, -2), (1, -2), (2, -1)];
+const KING_OFFSETS: [(isize, isize); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const STRAIGHT_DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const DIAGONAL_DIRECTIONS: [(isize, isize); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Board {
     pub(crate) pieces: [Piece; 64],
     pub(crate) colors: [Color; 64],
     pub(crate) next: Color,
+    pub(crate) castling: [bool; 4],
+    // the file of the rook for each castling right
+    pub(crate) castling_files: [usize; 4],
+    // castling is written as the king taking its rook
+    pub(crate) chess960: bool,
+    pub(crate) variant: Variant,
+    // Crazyhouse pockets by color and `POCKET_PIECES`, and the fields of promoted pieces
+    pub(crate) pockets: Pockets,
+    pub(crate) promoted: u64,
+    // Three-check, the number of checks given by white and black
+    pub(crate) checks: [u8; 2],
     pub(crate) en_passant: isize,
     pub(crate) halfmove_clock: isize,
     pub(crate) fullmove_clock: isize,
@@ -35,6 +59,13 @@ impl Default for Board {
             pieces: DEFAULT_PIECES,
             colors: DEFAULT_COLORS,
             next: Color::WHITE,
+            castling: DEFAULT_CASTLING,
+            castling_files: DEFAULT_CASTLING_FILES,
+            chess960: false,
+            variant: Variant::STANDARD,
+            pockets: [[0; 5]; 2],
+            promoted: 0,
+            checks: [0; 2],
             en_passant: -1,
             halfmove_clock: 0,
             fullmove_clock: 1,
@@ -42,6 +73,84 @@ impl Default for Board {
     }
 }
 
+impl Board {
+    /// The castling rights of a color, king side first, and the index of its back rank's a-file.
+    pub(crate) fn castling_rights(color: Color) -> ([usize; 2], usize) {
+        match color {
+            Color::WHITE => ([WHITE_KING_SIDE, WHITE_QUEEN_SIDE], 56),
+            _ => ([BLACK_KING_SIDE, BLACK_QUEEN_SIDE], 0),
+        }
+    }
+
+    /// The field of the rook that castles with `m`.
+    pub(crate) fn castling_rook(&self, m: Move) -> usize {
+        self.castling_rook_of(self.colors[m.from], m.to > m.from)
+    }
+
+    fn castling_rook_of(&self, color: Color, king_side: bool) -> usize {
+        let (rights, rank) = Board::castling_rights(color);
+        rank + self.castling_files[if king_side { rights[0] } else { rights[1] }]
+    }
+
+    // the fields king and rook end up on when castling with `m`
+    fn castling_targets(m: Move) -> (usize, usize) {
+        let rank = m.from - m.from % 8;
+        if m.to > m.from { (rank + 6, rank + 5) } else { (rank + 2, rank + 3) }
+    }
+
+    // The race is over once a king reached the 8th rank, unless white got there first and black
+    // can still follow with the next move for a draw.
+    fn racing_kings_outcome(&self) -> Option<Outcome> {
+        let white = self.king_position(Color::WHITE);
+        let black = self.king_position(Color::BLACK);
+        let (white_home, black_home) = (white > -1 && white < 8, black > -1 && black < 8);
+        let result = match (white_home, black_home) {
+            (false, false) => return None,
+            (true, true) => return Some(Outcome::DRAW),
+            (true, false) => Color::WHITE,
+            (false, true) => Color::BLACK,
+        };
+
+        if result == Color::WHITE && self.next == Color::BLACK {
+            let catches_up = KING_OFFSETS.iter()
+                .map(|(r, f)| self.calc_relative_index(black as usize, *r, *f))
+                .any(|index| (0..8).contains(&index) && self.colors[index as usize] != Color::BLACK
+                    && !self.is_attacked(index as usize, Color::WHITE));
+            if catches_up {
+                return None;
+            }
+        }
+        Some(if result == self.next { Outcome::WIN } else { Outcome::LOSS })
+    }
+
+    // pushes a pawn move, expanded into all promotions when the pawn reaches the last rank,
+    // which includes the king in Antichess
+    fn push_pawn_move(&self, list: &mut Vec<Move>, m: Move, promotes: bool) {
+        if promotes {
+            for promote_to in [QUEEN, ROOK, BISHOP, KNIGHT, KING] {
+                if promote_to != KING || self.variant == Variant::ANTICHESS {
+                    list.push(Move { promote_to, ..m })
+                }
+            }
+        } else {
+            list.push(m)
+        }
+    }
+
+    // returns the piece found first when walking from `position` in the given direction
+    fn first_piece_in_direction(&self, position: usize, plus_ranks: isize, plus_files: isize) -> isize {
+        let mut index = self.calc_relative_index(position, plus_ranks, plus_files);
+        while index > -1 {
+            if !self.is_empty_field(index) {
+                return index;
+            }
+            index = self.calc_relative_index(index as usize, plus_ranks, plus_files);
+        }
+
+        -1
+    }
+}
+
 impl MoveGenerator for Board {
     fn in_bounds(&self, index: isize) -> bool {
         index > -1 && index < 64
@@ -90,28 +199,100 @@ impl MoveGenerator for Board {
         }
     }
 
+    fn is_attacked(&self, position: usize, by: Color) -> bool {
+        // in Atomic, capturing next to the own king would blow it up, and kings cannot capture
+        let atomic = self.variant == Variant::ATOMIC;
+        if atomic {
+            let king = self.king_position(by);
+            if king < 0 || KING_OFFSETS.iter().any(|(r, f)| self.calc_relative_index(position, *r, *f) == king) {
+                return false;
+            }
+        }
+
+        // pawns attack diagonally forward, so look one rank behind from the attacker's perspective
+        let direction = self.current_direction(by);
+        for file in [-1, 1] {
+            let index = self.calc_relative_index(position, -direction, file);
+            if index > -1 && self.pieces[index as usize] == PAWN && self.colors[index as usize] == by {
+                return true;
+            }
+        }
+
+        for (plus_ranks, plus_files) in KNIGHT_OFFSETS {
+            let index = self.calc_relative_index(position, plus_ranks, plus_files);
+            if index > -1 && self.pieces[index as usize] == KNIGHT && self.colors[index as usize] == by {
+                return true;
+            }
+        }
+
+        for (plus_ranks, plus_files) in KING_OFFSETS {
+            let index = self.calc_relative_index(position, plus_ranks, plus_files);
+            if !atomic && index > -1 && self.pieces[index as usize] == KING && self.colors[index as usize] == by {
+                return true;
+            }
+        }
+
+        for (plus_ranks, plus_files) in STRAIGHT_DIRECTIONS {
+            let index = self.first_piece_in_direction(position, plus_ranks, plus_files);
+            if index > -1 && self.colors[index as usize] == by && matches!(self.pieces[index as usize], ROOK | QUEEN) {
+                return true;
+            }
+        }
+
+        for (plus_ranks, plus_files) in DIAGONAL_DIRECTIONS {
+            let index = self.first_piece_in_direction(position, plus_ranks, plus_files);
+            if index > -1 && self.colors[index as usize] == by && matches!(self.pieces[index as usize], BISHOP | QUEEN) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn king_position(&self, color: Color) -> isize {
+        for index in 0..64 {
+            if self.pieces[index] == KING && self.colors[index] == color {
+                return index as isize;
+            }
+        }
+
+        -1
+    }
+
+    fn is_check(&self, color: Color) -> bool {
+        // the king is just another piece in Antichess
+        if self.variant == Variant::ANTICHESS {
+            return false;
+        }
+        let position = self.king_position(color);
+        position > -1 && self.is_attacked(position as usize, inverse_color(color))
+    }
+
+    /// Whether the side to move is in check.
+    fn in_check(&self) -> bool {
+        self.is_check(self.next)
+    }
+
     fn generate_pawn_moves(&self, position: usize, color: Color) -> Vec<Move> {
         let mut list: Vec<Move> = Vec::new();
         let direction = self.current_direction(color);
         let (rank, _) = self.calc_rank_and_file(position);
 
+        // pawns promote when moving from the 7th (white) or 2nd (black) rank
+        let promotes = (color == Color::WHITE && rank == 6) || (color == Color::BLACK && rank == 1);
+
         // check if pawn can move one field forward
-        let index = self.calc_relative_index(position, direction * 1, 0);
+        let index = self.calc_relative_index(position, direction, 0);
         if self.in_bounds(index) && self.is_empty_field(index) {
-            // check if we need to convert pawn
-            if (color == Color::WHITE && rank == 7) || (color == Color::BLACK && rank == 0) {
-                for promote_to in [ROOK, BISHOP, KNIGHT, QUEEN] {
-                    list.push(Move { from: position, to: index as usize, promote_to, ..Default::default() })
-                }
-            } else {
-                list.push(Move { from: position, to: index as usize, ..Default::default() })
-            }
+            self.push_pawn_move(&mut list, Move { from: position, to: index as usize, ..Default::default() }, promotes);
         }
 
         // check if pawn can move two fields forward (given that current rank = 1
-        // for white and rank = 6 for black) and next two fields are both empty
-        if (color == Color::WHITE && rank == 1) || (color == Color::BLACK && rank == 6) {
-            let index1 = self.calc_relative_index(position, direction * 1, 0);
+        // for white and rank = 6 for black) and next two fields are both empty, in Horde the
+        // white pawns on the first rank can do so as well
+        let horde_pawn = self.variant == Variant::HORDE && color == Color::WHITE && rank == 0;
+        if (color == Color::WHITE && rank == 1) || (color == Color::BLACK && rank == 6) || horde_pawn {
+            let index1 = self.calc_relative_index(position, direction, 0);
             if self.in_bounds(index1) && self.is_empty_field(index1) {
                 let index2 = self.calc_relative_index(position, direction * 2, 0);
                 if self.in_bounds(index2) && self.is_empty_field(index2) {
@@ -121,18 +302,17 @@ impl MoveGenerator for Board {
         }
 
         // check if pawn can take +1,+1, +1,-1
-        let index = self.calc_relative_index(position, direction * 1, 1);
-        if self.in_bounds(index) && self.can_take(index, color) {
-            list.push(Move { from: position, to: index as usize, capture: self.pieces[index as usize], ..Default::default() })
-        }
-
-        let index = self.calc_relative_index(position, direction * 1, -1);
-        if self.in_bounds(index) && self.can_take(index, color) {
-            list.push(Move { from: position, to: index as usize, capture: self.pieces[index as usize], ..Default::default() })
+        for file in [1, -1] {
+            let index = self.calc_relative_index(position, direction, file);
+            if self.in_bounds(index) && self.can_take(index, color) {
+                let m = Move { from: position, to: index as usize, capture: self.pieces[index as usize], ..Default::default() };
+                self.push_pawn_move(&mut list, m, promotes);
+            } else if self.in_bounds(index) && index == self.en_passant {
+                // the captured pawn is not on the target field, but right behind it
+                list.push(Move { from: position, to: index as usize, capture: PAWN, en_passant: true, ..Default::default() })
+            }
         }
 
-        // todo: check if pawn can move en passant
-
         // return all valid moves
         list
     }
@@ -295,7 +475,7 @@ impl MoveGenerator for Board {
         let mut list: Vec<Move> = Vec::new();
         let direction = self.current_direction(color);
 
-        // check if bishop can move +2-1
+        // check if knight can move +2-1
         let index = self.calc_relative_index(position, direction * 2, -1);
         if self.in_bounds(index) && (self.is_empty_field(index) || self.can_take(index, color)) {
             if self.can_take(index, color) {
@@ -305,8 +485,8 @@ impl MoveGenerator for Board {
             }
         }
 
-        // check if bishop can move +1-2
-        let index = self.calc_relative_index(position, direction * 1, -2);
+        // check if knight can move +1-2
+        let index = self.calc_relative_index(position, direction, -2);
         if self.in_bounds(index) && (self.is_empty_field(index) || self.can_take(index, color)) {
             if self.can_take(index, color) {
                 list.push(Move { from: position, to: index as usize, capture: self.pieces[index as usize], ..Default::default() })
@@ -315,7 +495,7 @@ impl MoveGenerator for Board {
             }
         }
 
-        // check if bishop can move +2+1
+        // check if knight can move +2+1
         let index = self.calc_relative_index(position, direction * 2, 1);
         if self.in_bounds(index) && (self.is_empty_field(index) || self.can_take(index, color)) {
             if self.can_take(index, color) {
@@ -325,8 +505,8 @@ impl MoveGenerator for Board {
             }
         }
 
-        // check if bishop can move +1+2
-        let index = self.calc_relative_index(position, direction * 1, 2);
+        // check if knight can move +1+2
+        let index = self.calc_relative_index(position, direction, 2);
         if self.in_bounds(index) && (self.is_empty_field(index) || self.can_take(index, color)) {
             if self.can_take(index, color) {
                 list.push(Move { from: position, to: index as usize, capture: self.pieces[index as usize], ..Default::default() })
@@ -335,7 +515,7 @@ impl MoveGenerator for Board {
             }
         }
 
-        // check if bishop can move -2-1
+        // check if knight can move -2-1
         let index = self.calc_relative_index(position, direction * -2, -1);
         if self.in_bounds(index) && (self.is_empty_field(index) || self.can_take(index, color)) {
             if self.can_take(index, color) {
@@ -345,8 +525,8 @@ impl MoveGenerator for Board {
             }
         }
 
-        // check if bishop can move -1-2
-        let index = self.calc_relative_index(position, direction * -1, -2);
+        // check if knight can move -1-2
+        let index = self.calc_relative_index(position, -direction, -2);
         if self.in_bounds(index) && (self.is_empty_field(index) || self.can_take(index, color)) {
             if self.can_take(index, color) {
                 list.push(Move { from: position, to: index as usize, capture: self.pieces[index as usize], ..Default::default() })
@@ -355,7 +535,7 @@ impl MoveGenerator for Board {
             }
         }
 
-        // check if bishop can move -2+1
+        // check if knight can move -2+1
         let index = self.calc_relative_index(position, direction * -2, 1);
         if self.in_bounds(index) && (self.is_empty_field(index) || self.can_take(index, color)) {
             if self.can_take(index, color) {
@@ -365,8 +545,8 @@ impl MoveGenerator for Board {
             }
         }
 
-        // check if bishop can move -1+2
-        let index = self.calc_relative_index(position, direction * -1, 2);
+        // check if knight can move -1+2
+        let index = self.calc_relative_index(position, -direction, 2);
         if self.in_bounds(index) && (self.is_empty_field(index) || self.can_take(index, color)) {
             if self.can_take(index, color) {
                 list.push(Move { from: position, to: index as usize, capture: self.pieces[index as usize], ..Default::default() })
@@ -533,9 +713,10 @@ impl MoveGenerator for Board {
             for file in -1..=1 {
                 let index = self.calc_relative_index(position, rank, file);
                 if self.in_bounds(index) {
-                    // todo: check if field is check
-
                     if self.can_take(index, color) {
+                        if self.variant == Variant::ATOMIC {
+                            continue;
+                        }
                         list.push(Move { from: position, to: index as usize, capture: self.pieces[index as usize], ..Default::default() })
                     } else if self.is_empty_field(index) {
                         list.push(Move { from: position, to: index as usize, ..Default::default() })
@@ -544,6 +725,68 @@ impl MoveGenerator for Board {
             }
         }
 
+        if self.variant != Variant::ANTICHESS {
+            list.extend(self.generate_castling_moves(position, color));
+        }
+        list
+    }
+
+    fn generate_castling_moves(&self, position: usize, color: Color) -> Vec<Move> {
+        let mut list: Vec<Move> = Vec::new();
+        let (rights, rank) = Board::castling_rights(color);
+        if !(rank..rank + 8).contains(&position) {
+            return list;
+        }
+
+        let opponent = inverse_color(color);
+        let span = |a: usize, b: usize| a.min(b)..=a.max(b);
+        for (side, right) in rights.into_iter().enumerate() {
+            let rook = rank + self.castling_files[right];
+            if !self.castling[right] || self.pieces[rook] != ROOK || self.colors[rook] != color || (rook > position) != (side == 0) {
+                continue;
+            }
+
+            // all fields king and rook pass or land on must be empty, apart from the two of them
+            let (king_to, rook_to) = if side == 0 { (rank + 6, rank + 5) } else { (rank + 2, rank + 3) };
+            let blocked = span(position, king_to)
+                .chain(span(rook, rook_to))
+                .any(|index| index != position && index != rook && self.pieces[index] != Piece::EMPTY);
+
+            // The king must neither stand on nor pass an attacked field, and it does not shield
+            // the fields behind it, which matters where it is safe next to the other king in
+            // Atomic. Landing in check is left to the legality check with the rook in place.
+            let mut without_king = *self;
+            without_king.pieces[position] = Piece::EMPTY;
+            without_king.colors[position] = Color::NONE;
+            let mut passed = span(position, king_to).filter(|index| *index != king_to || king_to == position);
+            if blocked || passed.any(|index| without_king.is_attacked(index, opponent)) {
+                continue;
+            }
+            let to = if self.chess960 { rook } else { king_to };
+            list.push(Move { from: position, to, castling: true, ..Default::default() });
+        }
+
+        list
+    }
+
+    fn generate_drop_moves(&self, color: Color) -> Vec<Move> {
+        let mut list: Vec<Move> = Vec::new();
+        let pocket = self.pockets[color_index(color)];
+
+        for (piece, count) in POCKET_PIECES.iter().zip(pocket) {
+            if count == 0 {
+                continue;
+            }
+            for to in (0..64).filter(|index| self.pieces[*index] == Piece::EMPTY) {
+                // pawns are never dropped on the first or last rank
+                let (rank, _) = self.calc_rank_and_file(to);
+                if *piece == PAWN && (rank == 0 || rank == 7) {
+                    continue;
+                }
+                list.push(Move { from: to, to, drop: *piece, ..Default::default() });
+            }
+        }
+
         list
     }
 
@@ -565,98 +808,440 @@ impl MoveGenerator for Board {
                 Piece::QUEEN => self.generate_queen_moves(position, to_move),
                 Piece::KING => self.generate_king_moves(position, to_move),
                 Piece::EMPTY => Vec::new(),
-                _ => Vec::new(),
             };
 
             // add to list
             all_moves.extend(moves)
         }
 
-        return all_moves;
+        if self.variant == Variant::CRAZYHOUSE {
+            all_moves.extend(self.generate_drop_moves(to_move));
+        }
+
+        all_moves
+    }
+
+    fn generate_legal(&self) -> Vec<Move> {
+        // there is no check in Antichess, but captures are compulsory
+        if self.variant == Variant::ANTICHESS {
+            let moves = self.generate(self.next);
+            if moves.iter().any(|m| m.capture != Piece::EMPTY) {
+                return moves.into_iter().filter(|m| m.capture != Piece::EMPTY).collect();
+            }
+            return moves;
+        }
+        if self.variant_outcome().is_some() {
+            return Vec::new();
+        }
+
+        // a move is legal if it does not leave the own king in check, or blow it up in Atomic,
+        // and in Racing Kings it must not give check either
+        self.generate(self.next)
+            .into_iter()
+            .filter(|m| {
+                let b = self.apply(*m);
+                !b.is_check(self.next)
+                    && (self.variant != Variant::ATOMIC || b.king_position(self.next) > -1)
+                    && (self.variant != Variant::RACINGKINGS || !b.in_check())
+            })
+            .collect()
+    }
+
+    fn variant_outcome(&self) -> Option<Outcome> {
+        let opponent = inverse_color(self.next);
+        let own_king = self.king_position(self.next);
+        let other_king = self.king_position(opponent);
+        match self.variant {
+            Variant::ATOMIC if own_king < 0 => Some(Outcome::LOSS),
+            Variant::ATOMIC if other_king < 0 => Some(Outcome::WIN),
+            Variant::THREECHECK if self.checks[color_index(opponent)] >= 3 => Some(Outcome::LOSS),
+            Variant::THREECHECK if self.checks[color_index(self.next)] >= 3 => Some(Outcome::WIN),
+            Variant::KINGOFTHEHILL if HILL.contains(&(other_king as usize)) => Some(Outcome::LOSS),
+            Variant::KINGOFTHEHILL if HILL.contains(&(own_king as usize)) => Some(Outcome::WIN),
+            Variant::RACINGKINGS => self.racing_kings_outcome(),
+            // losing all pieces or having no move left wins
+            Variant::ANTICHESS if self.generate(self.next).is_empty() => Some(Outcome::WIN),
+            // black wins by taking all of white's pieces
+            Variant::HORDE if !self.colors.contains(&Color::WHITE) => {
+                Some(if self.next == Color::WHITE { Outcome::LOSS } else { Outcome::WIN })
+            }
+            _ => None,
+        }
     }
 
     fn apply(&self, m: Move) -> Board {
-        let mut b = self.clone();
+        let mut b = *self;
+        let piece = self.pieces[m.from];
+        let color = self.colors[m.from];
 
         b.next = inverse_color(self.next);
         if self.next == Color::BLACK {
             b.fullmove_clock += 1;
         }
 
+        if m.drop != Piece::EMPTY {
+            b.pieces[m.to] = m.drop;
+            b.colors[m.to] = self.next;
+            b.pockets[color_index(self.next)][pocket_index(m.drop).unwrap()] -= 1;
+            b.halfmove_clock += 1;
+            b.en_passant = -1;
+            return b;
+        }
+
+        if self.variant == Variant::CRAZYHOUSE {
+            // captured pieces change sides, promoted ones turn back into pawns
+            let promoted = |index: usize| self.promoted & 1 << index != 0;
+            // a king is taken only by pseudo-legal moves in a position where it was left in check
+            let captured = if promoted(m.to) { PAWN } else { m.capture };
+            if let Some(pocket) = pocket_index(captured) {
+                b.pockets[color_index(color)][pocket] += 1;
+            }
+            b.promoted &= !(1 << m.from | 1 << m.to);
+            if m.promote_to != Piece::EMPTY || promoted(m.from) {
+                b.promoted |= 1 << m.to;
+            }
+        }
+
+        if piece == PAWN || m.capture != Piece::EMPTY {
+            b.halfmove_clock = 0;
+        } else {
+            b.halfmove_clock += 1;
+        }
+
         b.colors[m.from] = Color::NONE;
         b.pieces[m.from] = Piece::EMPTY;
-        b.colors[m.to] = self.colors[m.from];
-        b.pieces[m.to] = self.pieces[m.from];
+        b.colors[m.to] = color;
+        b.pieces[m.to] = if m.promote_to != Piece::EMPTY { m.promote_to } else { piece };
+
+        if m.en_passant {
+            let index = self.calc_relative_index(m.to, -self.current_direction(color), 0) as usize;
+            b.colors[index] = Color::NONE;
+            b.pieces[index] = Piece::EMPTY;
+        }
+
+        if m.castling {
+            // the king may land on the field of its rook in Chess960, so both are lifted first
+            let rook_from = self.castling_rook(m);
+            let (king_to, rook_to) = Board::castling_targets(m);
+            for index in [m.to, rook_from] {
+                b.colors[index] = Color::NONE;
+                b.pieces[index] = Piece::EMPTY;
+            }
+            b.colors[king_to] = color;
+            b.pieces[king_to] = KING;
+            b.colors[rook_to] = color;
+            b.pieces[rook_to] = ROOK;
+        }
+
+        // moving the king or a rook, or capturing a rook on its home field, loses castling rights
+        for right in 0..4 {
+            let index = if right < 2 { 56 } else { 0 } + self.castling_files[right];
+            if m.from == index || m.to == index {
+                b.castling[right] = false;
+            }
+        }
+        if piece == KING {
+            match color {
+                Color::WHITE => {
+                    b.castling[WHITE_KING_SIDE] = false;
+                    b.castling[WHITE_QUEEN_SIDE] = false;
+                }
+                _ => {
+                    b.castling[BLACK_KING_SIDE] = false;
+                    b.castling[BLACK_QUEEN_SIDE] = false;
+                }
+            }
+        }
+
+        if self.variant == Variant::ATOMIC && m.capture != Piece::EMPTY {
+            // the capturing piece explodes along with all pieces but pawns around it
+            let around = KING_OFFSETS.iter()
+                .map(|(r, f)| self.calc_relative_index(m.to, *r, *f))
+                .filter(|index| *index > -1 && !matches!(b.pieces[*index as usize], Piece::EMPTY | PAWN))
+                .map(|index| index as usize);
+            for index in around.chain([m.to]).collect::<Vec<usize>>() {
+                if b.pieces[index] == KING {
+                    for right in Board::castling_rights(b.colors[index]).0 {
+                        b.castling[right] = false;
+                    }
+                }
+                for right in 0..4 {
+                    if index == if right < 2 { 56 } else { 0 } + self.castling_files[right] {
+                        b.castling[right] = false;
+                    }
+                }
+                b.pieces[index] = Piece::EMPTY;
+                b.colors[index] = Color::NONE;
+            }
+        }
+
+        // a double pawn push makes the skipped field capturable en passant
+        b.en_passant = -1;
+        if piece == PAWN && m.from.abs_diff(m.to) == 16 && matches!(m.from / 8, 1 | 6) {
+            b.en_passant = ((m.from + m.to) / 2) as isize;
+        }
+
+        if self.variant == Variant::THREECHECK && b.in_check() {
+            b.checks[color_index(color)] += 1;
+        }
 
         b
     }
 
+    /// Reverts the piece placement of `m`. Castling rights, the en passant field and the halfmove
+    /// clock are not part of a `Move` and are therefore not restored, neither are Crazyhouse
+    /// pockets after captures.
     fn undo(&self, m: Move) -> Board {
-        let mut b = self.clone();
-
+        let mut b = *self;
         b.next = inverse_color(self.next);
         if self.next == Color::WHITE {
             b.fullmove_clock -= 1;
         }
 
-        b.colors[m.from] = self.colors[m.to];
-        b.pieces[m.from] = self.pieces[m.to];
+        if m.drop != Piece::EMPTY {
+            b.pockets[color_index(self.colors[m.to])][pocket_index(m.drop).unwrap()] += 1;
+            b.colors[m.to] = Color::NONE;
+            b.pieces[m.to] = Piece::EMPTY;
+            return b;
+        }
+
+        if m.castling {
+            let (king_to, rook_to) = Board::castling_targets(m);
+            let color = self.colors[king_to];
+            let rook_from = self.castling_rook_of(color, m.to > m.from);
+            for index in [king_to, rook_to] {
+                b.colors[index] = Color::NONE;
+                b.pieces[index] = Piece::EMPTY;
+            }
+            b.colors[m.from] = color;
+            b.pieces[m.from] = KING;
+            b.colors[rook_from] = color;
+            b.pieces[rook_from] = ROOK;
+            return b;
+        }
+
+        let color = self.colors[m.to];
+
+        b.colors[m.from] = color;
+        b.pieces[m.from] = if m.promote_to != Piece::EMPTY { PAWN } else { self.pieces[m.to] };
         b.colors[m.to] = Color::NONE;
         b.pieces[m.to] = Piece::EMPTY;
 
+        if m.en_passant {
+            let index = self.calc_relative_index(m.to, -self.current_direction(color), 0) as usize;
+            b.colors[index] = inverse_color(color);
+            b.pieces[index] = PAWN;
+        } else if m.capture != Piece::EMPTY {
+            b.colors[m.to] = inverse_color(color);
+            b.pieces[m.to] = m.capture;
+        }
+
         b
     }
 }
 
+// The file of the outermost rook of a color on its back rank, on one side of the king.
+fn outer_rook(pieces: &[Piece; 64], colors: &[Color; 64], color: Color, king_side: bool) -> Option<usize> {
+    let (_, rank) = Board::castling_rights(color);
+    let king = (0..8).find(|file| pieces[rank + file] == KING && colors[rank + file] == color)?;
+    let is_rook = |file: &usize| pieces[rank + file] == ROOK && colors[rank + file] == color;
+    if king_side {
+        (king + 1..8).rev().find(is_rook)
+    } else {
+        (0..king).find(is_rook)
+    }
+}
+
+// Castling rights in classical notation (`KQkq`), X-FEN, which names the file of the rook when
+// it is not the outermost one, or Shredder-FEN, which always names the file (`HAha`). Returns
+// the rights, the file of each right's rook and whether the position needs Chess960 castling.
+fn parse_castling(pieces: &[Piece; 64], colors: &[Color; 64], rights: &str) -> Option<([bool; 4], [usize; 4], bool)> {
+    let mut castling = [false; 4];
+    let mut files = DEFAULT_CASTLING_FILES;
+    let mut chess960 = false;
+    if rights == "-" {
+        return Some((castling, files, chess960));
+    }
+
+    for right in rights.chars() {
+        let color = if right.is_ascii_uppercase() { Color::WHITE } else { Color::BLACK };
+        let (indexes, rank) = Board::castling_rights(color);
+        let king = (0..8).find(|file| pieces[rank + file] == KING && colors[rank + file] == color);
+        let (king_side, file) = match right.to_ascii_lowercase() {
+            // without a rook, classical rights keep their classical file
+            'k' => (true, outer_rook(pieces, colors, color, true).unwrap_or(7)),
+            'q' => (false, outer_rook(pieces, colors, color, false).unwrap_or(0)),
+            letter @ 'a'..='h' => {
+                let file = letter as usize - 'a' as usize;
+                chess960 = true;
+                (file > king?, file)
+            }
+            _ => return None,
+        };
+
+        let index = if king_side { indexes[0] } else { indexes[1] };
+        castling[index] = true;
+        files[index] = file;
+        if king.is_some_and(|king| king != 4) || file != DEFAULT_CASTLING_FILES[index] {
+            chess960 = true;
+        }
+    }
+
+    Some((castling, files, chess960))
+}
+
+// The counterpart of `parse_castling`, in X-FEN for Chess960 positions.
+fn format_castling(board: &Board) -> String {
+    let mut rights = String::new();
+    for color in [Color::WHITE, Color::BLACK] {
+        let (indexes, _) = Board::castling_rights(color);
+        for (side, index) in indexes.into_iter().enumerate() {
+            if !board.castling[index] {
+                continue;
+            }
+            let king_side = side == 0;
+            let file = board.castling_files[index];
+            let outermost = outer_rook(&board.pieces, &board.colors, color, king_side) == Some(file);
+            let letter = if !board.chess960 || outermost {
+                if king_side { 'k' } else { 'q' }
+            } else {
+                (b'a' + file as u8) as char
+            };
+            rights.push(if color == Color::WHITE { letter.to_ascii_uppercase() } else { letter });
+        }
+    }
+    rights
+}
+
 impl Fen for Board {
     fn from_fen(fen: &str) -> Board {
+        match Board::try_from_fen(fen) {
+            Ok(board) => board,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_from_fen(fen: &str) -> Result<Board, FenError> {
+        let error = |message: String| FenError { message };
         let mut pieces: [Piece; 64] = [Piece::EMPTY; 64];
         let mut colors: [Color; 64] = [Color::NONE; 64];
-        // extract position from fen, ignore metadata
+
+        let mut promoted = 0;
+
         let mut split = fen.split_whitespace();
-        let ranks = split.next().unwrap_or("").split("/");
-        let color = split.next().unwrap_or("");
-        for (rank, data) in ranks.enumerate() {
+        let mut placement = split.next().unwrap_or("");
+        // Crazyhouse pockets follow the placement in brackets or as a ninth rank
+        let mut pockets = None;
+        if let Some((rest, pocket)) = placement.strip_suffix(']').and_then(|p| p.split_once('[')) {
+            placement = rest;
+            pockets = Some(pocket);
+        } else if placement.matches('/').count() == 8 {
+            let (rest, pocket) = placement.rsplit_once('/').unwrap();
+            placement = rest;
+            pockets = Some(pocket);
+        }
+        let pockets = match pockets {
+            Some(pockets) => Some(parse_pockets(pockets).ok_or_else(|| error(format!("invalid pockets `{}`", pockets)))?),
+            None => None,
+        };
+
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(error(format!("expected 8 ranks, got {}", ranks.len())));
+        }
+
+        for (rank, data) in ranks.iter().enumerate() {
             let mut file = 0;
-            for identifier in data.chars().map(|c| c.to_string()) {
+            for identifier in data.chars() {
+                // digits skip the given number of empty fields
+                if let Some(empty) = identifier.to_digit(10) {
+                    file += empty as usize;
+                    continue;
+                }
+                // a promoted piece in Crazyhouse
+                if identifier == '~' && file > 0 {
+                    promoted |= 1u64 << (rank * 8 + file - 1);
+                    continue;
+                }
+                if file > 7 {
+                    return Err(error(format!("too many fields in rank `{}`", data)));
+                }
+
                 let index = rank * 8 + file;
+                match Piece::from_str(identifier.to_string().as_str()) {
+                    Ok(piece) if piece != Piece::EMPTY => pieces[index] = piece,
+                    _ => return Err(error(format!("cannot parse piece `{}`", identifier))),
+                }
 
-                let is_empty_field = match identifier.parse::<isize>() {
-                    Ok(_ok) => true,
-                    Err(_e) => false,
-                };
-
-                if !is_empty_field {
-                    // determine color
-                    if identifier.to_lowercase() == identifier {
-                        colors[index] = Color::BLACK;
-                    } else {
-                        colors[index] = Color::WHITE;
-                    }
+                // determine color
+                colors[index] = if identifier.is_lowercase() { Color::BLACK } else { Color::WHITE };
+                file += 1;
 
-                    // determine piece
-                    let piece = Piece::from_str(identifier.as_str());
-                    match piece {
-                        Ok(ok) => {
-                            pieces[index] = ok;
-                        }
-                        Err(()) => panic!("cannot parse piece `{}` in fen notation", identifier)
-                    }
+                // no pawn stands on the top or bottom rank, except white ones in Horde
+                if pieces[index] == Piece::PAWN && (rank == 0 || (rank == 7 && colors[index] == Color::BLACK)) {
+                    return Err(error(format!("pawn on {}", square_name(index))));
                 }
+            }
 
-                file += 1;
+            if file != 8 {
+                return Err(error(format!("expected 8 fields in rank `{}`", data)));
             }
         }
 
         // determine next color to move
-        let next = Color::from_str(color).unwrap();
+        let next = match Color::from_str(split.next().unwrap_or("w")) {
+            Ok(Color::NONE) | Err(()) => return Err(error("invalid active color".to_string())),
+            Ok(color) => color,
+        };
+
+        let (castling, castling_files, chess960) = parse_castling(&pieces, &colors, split.next().unwrap_or("-"))
+            .ok_or_else(|| error("invalid castling rights".to_string()))?;
+
+        let en_passant = match split.next().unwrap_or("-") {
+            "-" => -1,
+            field => match parse_square(field) {
+                Some(index) => index as isize,
+                None => return Err(error(format!("invalid en passant field `{}`", field))),
+            },
+        };
+
+        // Three-check counters either follow the en passant field or come last
+        let mut checks = None;
+        let mut field = split.next();
+        if let Some(given) = field.and_then(parse_checks) {
+            checks = Some(given);
+            field = split.next();
+        }
 
-        Board {
+        let halfmove_clock = field.unwrap_or("0").parse::<isize>()
+            .map_err(|_| error("invalid halfmove clock".to_string()))?;
+        let fullmove_clock = split.next().unwrap_or("1").parse::<isize>()
+            .map_err(|_| error("invalid fullmove clock".to_string()))?;
+        if let Some(field) = split.next() {
+            checks = Some(parse_checks(field).ok_or_else(|| error(format!("invalid checks `{}`", field)))?);
+        }
+
+        let variant = match (pockets, checks) {
+            (Some(_), _) => Variant::CRAZYHOUSE,
+            (None, Some(_)) => Variant::THREECHECK,
+            (None, None) => Variant::STANDARD,
+        };
+
+        Ok(Board {
             pieces,
             colors,
             next,
-            ..Default::default()
-        }
+            castling,
+            castling_files,
+            chess960,
+            variant,
+            pockets: pockets.unwrap_or_default(),
+            promoted,
+            checks: checks.unwrap_or_default(),
+            en_passant,
+            halfmove_clock,
+            fullmove_clock,
+        })
     }
 
     fn to_fen(&self) -> String {
@@ -684,24 +1269,38 @@ impl Fen for Board {
                     last += 1;
                 }
             }
+            if self.promoted & 1 << index != 0 {
+                fen.push('~');
+            }
 
-            if (index + 1) % 8 == 0 && index > 0 && index < 63 {
+            if (index + 1).is_multiple_of(8) && index > 0 {
                 if last > 0 {
                     fen.push_str(last.to_string().as_str());
                     last = 0;
                 }
-                fen.push_str("/".to_string().as_str());
+                if index < 63 {
+                    fen.push('/');
+                }
             }
         }
 
+        if self.variant == Variant::CRAZYHOUSE {
+            fen += format!("[{}]", format_pockets(&self.pockets)).as_str();
+        }
+
         // active color
         fen += format!(" {}", self.next).as_str();
 
         // castling
-        fen += " KQkq";
+        let rights = format_castling(self);
+        fen += format!(" {}", if rights.is_empty() { "-" } else { rights.as_str() }).as_str();
 
         // en passant
-        fen += " -";
+        if self.en_passant > -1 {
+            fen += format!(" {}", square_name(self.en_passant as usize)).as_str();
+        } else {
+            fen += " -";
+        }
 
         // halfmove clock
         fen += format!(" {}", self.halfmove_clock).as_str();
@@ -709,48 +1308,68 @@ impl Fen for Board {
         // fullmove clock
         fen += format!(" {}", self.fullmove_clock).as_str();
 
+        // checks given, like lichess writes them
+        if self.variant == Variant::THREECHECK {
+            fen += format!(" +{}+{}", self.checks[0], self.checks[1]).as_str();
+        }
+
         fen
     }
 }
 
 impl Perft for Board {
     fn perft(&self, depth: usize) -> PerftResult {
-        let result = PerftResult { ..Default::default() };
-        let color = if depth % 2 == 0 { Color::WHITE } else { Color::BLACK };
-        self._perft(depth, self.clone(), color, result)
+        let result = PerftResult { depth, ..Default::default() };
+        self._perft(depth, result)
     }
 
-    fn _perft(&self, depth: usize, board: Board, color: Color, mut result: PerftResult) -> PerftResult {
+    fn _perft(&self, depth: usize, mut result: PerftResult) -> PerftResult {
         if depth < 1 {
             return result;
         }
 
-        let legal_moves = board.generate(color);
-        result.nodes += legal_moves.len();
+        for m in self.generate_legal() {
+            let b = self.apply(m);
+            if depth > 1 {
+                result = b._perft(depth - 1, result);
+                continue;
+            }
 
-        for m in legal_moves {
-            let b = board.apply(m);
-            // captures
+            // only leaf nodes are counted
+            result.nodes += 1;
             if m.capture != Piece::EMPTY {
                 result.captures += 1;
             }
-
-            result = b._perft(depth - 1, b, if color == Color::WHITE { Color::BLACK } else { Color::WHITE }, result);
+            if m.en_passant {
+                result.ep += 1;
+            }
+            if m.castling {
+                result.castles += 1;
+            }
+            if m.promote_to != Piece::EMPTY {
+                result.promotions += 1;
+            }
+            if b.in_check() {
+                result.checks += 1;
+                if b.generate_legal().is_empty() {
+                    result.checkmates += 1;
+                }
+            }
         }
 
         result
     }
 
     fn divide(&self, depth: usize) -> PerftResult {
-        let board = self.clone();
-        let mut result = PerftResult { ..Default::default() };
+        let mut result = PerftResult { depth, ..Default::default() };
 
-        let legal_moves = board.generate(board.next);
-        result.nodes += legal_moves.len();
-
-        for m in legal_moves {
-            let b = board.apply(m);
-            let node_result = b._perft(depth - 1, b, Color::BLACK, PerftResult { ..Default::default() });
+        for m in self.generate_legal() {
+            let b = self.apply(m);
+            let node_result = if depth > 1 {
+                b.perft(depth - 1)
+            } else {
+                PerftResult { nodes: 1, ..Default::default() }
+            };
             println!("{}: {}", m, node_result.nodes);
 
             result.captures += node_result.captures;
@@ -773,7 +1392,7 @@ impl Display for Board {
                 Color::NONE => str.push_str(self.pieces[index].to_string().as_str()),
             }
 
-            if (index + 1) % 8 == 0 && index > 0 {
+            if (index + 1).is_multiple_of(8) && index > 0 {
                 str.push_str("\n".to_string().as_str());
             }
         }
@@ -0,0 +1,138 @@
+use std::str::FromStr;
+use crate::board::defaults::{DEFAULT_COLORS, DEFAULT_PIECES};
+use crate::board::piece::Piece;
+use crate::board::piece::Piece::{BISHOP, EMPTY, KING, KNIGHT, PAWN, QUEEN, ROOK};
+use crate::{Board, Color};
+
+pub const POSITIONS: usize = 960;
+/// Double Fischer Random, where both colors get their own Chess960 back rank.
+pub const DFRC_POSITIONS: usize = POSITIONS * POSITIONS;
+
+// the fields of both knights among the five fields left after placing bishops and queen
+const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+/// The back rank of Chess960 start position `index`, from the a-file, numbered like Scharnagl
+/// does so the classical start position is 518.
+pub fn back_rank(index: usize) -> Option<[Piece; 8]> {
+    if index >= POSITIONS {
+        return None;
+    }
+
+    let mut rank = [EMPTY; 8];
+    // one bishop on a light and one on a dark field
+    rank[index % 4 * 2 + 1] = BISHOP;
+    rank[index / 4 % 4 * 2] = BISHOP;
+
+    let mut place = |piece: Piece, nth: usize| {
+        let file = (0..8).filter(|file| rank[*file] == EMPTY).nth(nth).unwrap();
+        rank[file] = piece;
+    };
+    place(QUEEN, index / 16 % 6);
+    let (first, second) = KNIGHTS[index / 96];
+    // the second knight is counted before the first one takes its field
+    place(KNIGHT, second);
+    place(KNIGHT, first);
+    // the king ends up between the rooks
+    place(ROOK, 0);
+    place(KING, 0);
+    place(ROOK, 0);
+
+    Some(rank)
+}
+
+/// A back rank like `RNBQKBNR`, from the a-file. Any setup with a single king is allowed.
+pub fn parse_back_rank(rank: &str) -> Option<[Piece; 8]> {
+    let pieces: Vec<Piece> = rank.chars().map(|letter| Piece::from_str(&letter.to_string())).collect::<Result<_, _>>().ok()?;
+    let kings = pieces.iter().filter(|piece| **piece == KING).count();
+    if kings != 1 || pieces.iter().any(|piece| *piece == PAWN || *piece == EMPTY) {
+        return None;
+    }
+    pieces.try_into().ok()
+}
+
+/// A family of start positions that can be listed by index.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartPositions {
+    CHESS960,
+    DFRC,
+    // white and black back rank
+    RANKS([Piece; 8], [Piece; 8]),
+}
+
+impl FromStr for StartPositions {
+    type Err = ();
+
+    /// `chess960`, `dfrc`, or back ranks like `RNBQKBNR`, which both colors share, or
+    /// `RNBQKBNR/rbnkqbnr` for different ones.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chess960" => Ok(StartPositions::CHESS960),
+            "dfrc" => Ok(StartPositions::DFRC),
+            _ => {
+                let (white, black) = s.split_once('/').unwrap_or((s, s));
+                Ok(StartPositions::RANKS(parse_back_rank(white).ok_or(())?, parse_back_rank(black).ok_or(())?))
+            }
+        }
+    }
+}
+
+impl StartPositions {
+    pub fn len(&self) -> usize {
+        match self {
+            StartPositions::CHESS960 => POSITIONS,
+            StartPositions::DFRC => DFRC_POSITIONS,
+            StartPositions::RANKS(_, _) => 1,
+        }
+    }
+
+    /// Position `index` of the family. Double Fischer Random numbers its positions by the white
+    /// back rank times 960 plus the black one.
+    pub fn board(&self, index: usize) -> Option<Board> {
+        match self {
+            StartPositions::CHESS960 => Board::from_chess960(index),
+            StartPositions::DFRC if index < DFRC_POSITIONS => {
+                Some(Board::from_back_ranks(back_rank(index / POSITIONS)?, back_rank(index % POSITIONS)?))
+            }
+            StartPositions::RANKS(white, black) if index == 0 => Some(Board::from_back_ranks(*white, *black)),
+            _ => None,
+        }
+    }
+}
+
+pub trait Chess960 {
+    /// Chess960 start position `index` with full castling rights, `None` if there is no such position.
+    fn from_chess960(index: usize) -> Option<Board>;
+    /// A start position with the given back ranks for both colors, read from the a-file.
+    fn from_back_ranks(white: [Piece; 8], black: [Piece; 8]) -> Board;
+}
+
+impl Chess960 for Board {
+    fn from_chess960(index: usize) -> Option<Board> {
+        let rank = back_rank(index)?;
+        Some(Board::from_back_ranks(rank, rank))
+    }
+
+    fn from_back_ranks(white: [Piece; 8], black: [Piece; 8]) -> Board {
+        let mut board = Board { chess960: true, ..Default::default() };
+        board.pieces = DEFAULT_PIECES;
+        board.colors = DEFAULT_COLORS;
+        board.pieces[..8].copy_from_slice(&black);
+        board.pieces[56..].copy_from_slice(&white);
+
+        // castling rights for the outermost rooks next to the king
+        board.castling = [false; 4];
+        for (color, pieces) in [(Color::WHITE, white), (Color::BLACK, black)] {
+            let (rights, _) = Board::castling_rights(color);
+            let Some(king) = pieces.iter().position(|piece| *piece == KING) else { continue };
+            let king_side = (king + 1..8).rev().find(|file| pieces[*file] == ROOK);
+            let queen_side = (0..king).find(|file| pieces[*file] == ROOK);
+            for (right, file) in rights.into_iter().zip([king_side, queen_side]) {
+                board.castling[right] = file.is_some();
+                board.castling_files[right] = file.unwrap_or(board.castling_files[right]);
+            }
+        }
+
+        board
+    }
+}
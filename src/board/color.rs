@@ -1,7 +1,8 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-#[derive(PartialEq, Eq, Copy, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum Color {
     WHITE,
     BLACK,
@@ -16,6 +17,11 @@ pub fn inverse_color(color: Color) -> Color {
     }
 }
 
+/// Index of a color in per-color arrays, white first.
+pub fn color_index(color: Color) -> usize {
+    if color == Color::WHITE { 0 } else { 1 }
+}
+
 impl Display for Color {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -0,0 +1,36 @@
+use std::str::FromStr;
+use crate::board::color::color_index;
+use crate::board::piece::Piece;
+use crate::Color;
+
+/// The pieces a pocket can hold, in the order of `Board::pockets`.
+pub const POCKET_PIECES: [Piece; 5] = [Piece::PAWN, Piece::KNIGHT, Piece::BISHOP, Piece::ROOK, Piece::QUEEN];
+
+pub type Pockets = [[u8; 5]; 2];
+
+pub fn pocket_index(piece: Piece) -> Option<usize> {
+    POCKET_PIECES.iter().position(|p| *p == piece)
+}
+
+/// Pockets in FEN, like `QNnp` with white's pieces in upper case.
+pub fn parse_pockets(pockets: &str) -> Option<Pockets> {
+    let mut result = [[0; 5]; 2];
+    for letter in pockets.chars() {
+        let piece = Piece::from_str(&letter.to_string()).ok()?;
+        let color = if letter.is_ascii_uppercase() { Color::WHITE } else { Color::BLACK };
+        result[color_index(color)][pocket_index(piece)?] += 1;
+    }
+    Some(result)
+}
+
+/// The counterpart of `parse_pockets`, strongest pieces first.
+pub fn format_pockets(pockets: &Pockets) -> String {
+    let mut result = String::new();
+    for color in [Color::WHITE, Color::BLACK] {
+        for (index, piece) in POCKET_PIECES.iter().enumerate().rev() {
+            let letter = if color == Color::WHITE { piece.to_string().to_uppercase() } else { piece.to_string() };
+            result.push_str(&letter.repeat(pockets[color_index(color)][index] as usize));
+        }
+    }
+    result
+}
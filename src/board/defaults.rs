@@ -26,3 +26,17 @@ pub const DEFAULT_COLORS: [Color; 64] = [
     WHITE, WHITE, WHITE, WHITE, WHITE, WHITE, WHITE, WHITE,
     WHITE, WHITE, WHITE, WHITE, WHITE, WHITE, WHITE, WHITE,
 ];
+
+// indexes into `Board::castling`
+pub const WHITE_KING_SIDE: usize = 0;
+pub const WHITE_QUEEN_SIDE: usize = 1;
+pub const BLACK_KING_SIDE: usize = 2;
+pub const BLACK_QUEEN_SIDE: usize = 3;
+
+pub const DEFAULT_CASTLING: [bool; 4] = [true, true, true, true];
+pub const DEFAULT_CASTLING_FILES: [usize; 4] = [7, 0, 7, 0];
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+pub const RACING_KINGS_FEN: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+pub const ANTICHESS_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1";
+pub const HORDE_FEN: &str = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
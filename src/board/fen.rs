@@ -1,6 +1,20 @@
+use std::fmt::{Display, Formatter};
 use crate::Board;
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct FenError {
+    pub message: String,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid fen: {}", self.message)
+    }
+}
+
 pub trait Fen {
+    #[allow(dead_code)]
     fn from_fen(fen: &str) -> Board;
+    fn try_from_fen(fen: &str) -> Result<Board, FenError>;
     fn to_fen(&self) -> String;
 }
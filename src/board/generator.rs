@@ -1,13 +1,19 @@
 use std::fmt::{Display, Formatter};
 use crate::board::piece::Piece;
+use crate::board::square::square_name;
+use crate::board::variant::Outcome;
 use crate::{Board, Color};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Move {
     pub from: usize,
     pub to: usize,
     pub promote_to: Piece,
     pub capture: Piece,
+    pub en_passant: bool,
+    pub castling: bool,
+    // the piece put on `to` from the pocket in Crazyhouse
+    pub drop: Piece,
 }
 
 impl Default for Move {
@@ -16,23 +22,25 @@ impl Default for Move {
             from: 0,
             to: 0,
             promote_to: Piece::EMPTY,
-            capture: Piece::EMPTY
+            capture: Piece::EMPTY,
+            en_passant: false,
+            castling: false,
+            drop: Piece::EMPTY,
         }
     }
 }
 
 impl Display for Move {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let from_rank = 7 - self.from / 8;
-        let from_file = self.from % 8;
-        let to_rank = 7 - self.to / 8;
-        let to_file = self.to % 8;
-
-        let files = "abcdefgh";
-        let human_from_file = files.chars().nth(from_file).unwrap();
-        let human_to_file = files.chars().nth(to_file).unwrap();
+        if self.drop != Piece::EMPTY {
+            return write!(f, "{}@{}", self.drop.to_string().to_uppercase(), square_name(self.to));
+        }
+        write!(f, "{}{}", square_name(self.from), square_name(self.to))?;
+        if self.promote_to != Piece::EMPTY {
+            write!(f, "{}", self.promote_to)?;
+        }
 
-        write!(f, "{}{}{}{}", human_from_file, from_rank + 1, human_to_file, to_rank + 1)
+        Ok(())
     }
 }
 
@@ -48,15 +56,25 @@ pub trait MoveGenerator {
 
     fn current_direction(&self, color: Color) -> isize;
 
+    fn is_attacked(&self, position: usize, by: Color) -> bool;
+    fn king_position(&self, color: Color) -> isize;
+    fn is_check(&self, color: Color) -> bool;
+    fn in_check(&self) -> bool;
+
     fn generate_pawn_moves(&self, position: usize, color: Color) -> Vec<Move>;
     fn generate_rook_moves(&self, position: usize, color: Color) -> Vec<Move>;
     fn generate_bishop_moves(&self, position: usize, color: Color) -> Vec<Move>;
     fn generate_knight_moves(&self, position: usize, color: Color) -> Vec<Move>;
     fn generate_queen_moves(&self, position: usize, color: Color) -> Vec<Move>;
     fn generate_king_moves(&self, position: usize, color: Color) -> Vec<Move>;
+    fn generate_castling_moves(&self, position: usize, color: Color) -> Vec<Move>;
+    fn generate_drop_moves(&self, color: Color) -> Vec<Move>;
 
     fn generate(&self, next: Color) -> Vec<Move>;
+    fn generate_legal(&self) -> Vec<Move>;
+    fn variant_outcome(&self) -> Option<Outcome>;
 
     fn apply(&self, m: Move) -> Board;
+    #[allow(dead_code)]
     fn undo(&self, m: Move) -> Board;
 }
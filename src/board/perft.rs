@@ -1,7 +1,6 @@
 use std::fmt::{Display, Formatter};
-use crate::{Board, Color};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default)]
 pub struct PerftResult {
     pub(crate) depth: usize,
     pub(crate) nodes: usize,
@@ -15,26 +14,9 @@ pub struct PerftResult {
     pub(crate) checkmates: usize,
 }
 
-impl Default for PerftResult {
-    fn default() -> Self {
-        PerftResult {
-            depth: 0,
-            nodes: 0,
-            captures: 0,
-            ep: 0,
-            castles: 0,
-            promotions: 0,
-            checks: 0,
-            discovery_checks: 0,
-            double_checks: 0,
-            checkmates: 0,
-        }
-    }
-}
-
 pub trait Perft {
     fn perft(&self, depth: usize) -> PerftResult;
-    fn _perft(&self, depth: usize, board: Board, color: Color, result: PerftResult) -> PerftResult;
+    fn _perft(&self, depth: usize, result: PerftResult) -> PerftResult;
 
     fn divide(&self, max_depth: usize) -> PerftResult;
 }
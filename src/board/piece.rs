@@ -1,8 +1,8 @@
 use std::fmt::{Display, Formatter};
-use std::pin::Pin;
 use std::str::FromStr;
 
-#[derive(PartialEq, Eq, Copy, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum Piece {
     PAWN,
     ROOK,
@@ -35,7 +35,7 @@ impl FromStr for Piece {
             "p" => Ok(Piece::PAWN),
             "r" => Ok(Piece::ROOK),
             "b" => Ok(Piece::BISHOP),
-            "n" => Ok(Piece::KING),
+            "n" => Ok(Piece::KNIGHT),
             "q" => Ok(Piece::QUEEN),
             "k" => Ok(Piece::KING),
             "." => Ok(Piece::EMPTY),
@@ -0,0 +1,184 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::piece::Piece;
+use crate::board::square::{parse_square, square_name};
+use crate::board::variant::Outcome;
+use crate::Board;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// The token is not a move in standard algebraic notation.
+    Invalid(String),
+    /// The move is well-formed, but not legal in the current position.
+    Illegal(String),
+    /// More than one legal move matches the token.
+    Ambiguous(String),
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "invalid move `{}`", san),
+            SanError::Illegal(san) => write!(f, "illegal move `{}`", san),
+            SanError::Ambiguous(san) => write!(f, "ambiguous move `{}`", san),
+        }
+    }
+}
+
+/// Standard algebraic notation (`Nbd7`, `exd5`, `O-O`, `e8=Q+`, `N@f3`) for moves of the side to move.
+pub trait San {
+    fn to_san(&self, m: Move) -> String;
+    fn parse_san(&self, san: &str) -> Result<Move, SanError>;
+}
+
+impl San for Board {
+    fn to_san(&self, m: Move) -> String {
+        let piece = self.pieces[m.from];
+        let mut san = String::new();
+
+        if m.drop != Piece::EMPTY {
+            // pawn drops leave out the piece like pawn moves do
+            if m.drop != Piece::PAWN {
+                san.push_str(&m.drop.to_string().to_uppercase());
+            }
+            san.push('@');
+            san.push_str(&square_name(m.to));
+        } else if m.castling {
+            san.push_str(if m.to > m.from { "O-O" } else { "O-O-O" });
+        } else if piece == Piece::PAWN {
+            if m.capture != Piece::EMPTY {
+                san.push_str(&square_name(m.from)[..1]);
+                san.push('x');
+            }
+            san.push_str(&square_name(m.to));
+            if m.promote_to != Piece::EMPTY {
+                san.push('=');
+                san.push_str(&m.promote_to.to_string().to_uppercase());
+            }
+        } else {
+            san.push_str(&piece.to_string().to_uppercase());
+
+            // disambiguate by file first, then by rank, then by both
+            let others: Vec<Move> = self.generate_legal()
+                .into_iter()
+                .filter(|o| o.to == m.to && o.from != m.from && self.pieces[o.from] == piece && !o.castling && o.drop == Piece::EMPTY)
+                .collect();
+            if !others.is_empty() {
+                let (rank, file) = self.calc_rank_and_file(m.from);
+                let from = square_name(m.from);
+                if others.iter().all(|o| self.calc_rank_and_file(o.from).1 != file) {
+                    san.push_str(&from[..1]);
+                } else if others.iter().all(|o| self.calc_rank_and_file(o.from).0 != rank) {
+                    san.push_str(&from[1..]);
+                } else {
+                    san.push_str(&from);
+                }
+            }
+
+            if m.capture != Piece::EMPTY {
+                san.push('x');
+            }
+            san.push_str(&square_name(m.to));
+        }
+
+        // blowing up the king in Atomic counts as mate
+        let b = self.apply(m);
+        if b.is_check(b.next) || b.variant_outcome() == Some(Outcome::LOSS) {
+            san.push(if b.generate_legal().is_empty() { '#' } else { '+' });
+        }
+
+        san
+    }
+
+    fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        // strip check, mate and annotation suffixes
+        let token = san.trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves = self.generate_legal();
+
+        if matches!(token, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+            let king_side = token.len() == 3;
+            return legal_moves
+                .into_iter()
+                .find(|m| m.castling && (m.to > m.from) == king_side)
+                .ok_or_else(|| SanError::Illegal(san.to_string()));
+        }
+
+        // Crazyhouse drops, `N@f3` or `@e4` and `P@e4` for pawns
+        if let Some((piece, to)) = token.split_once('@') {
+            let piece = match piece {
+                "" => Piece::PAWN,
+                "P" | "N" | "B" | "R" | "Q" => Piece::from_str(piece).unwrap(),
+                _ => return Err(SanError::Invalid(san.to_string())),
+            };
+            let to = parse_square(to).ok_or_else(|| SanError::Invalid(san.to_string()))?;
+            return legal_moves
+                .into_iter()
+                .find(|m| m.drop == piece && m.to == to)
+                .ok_or_else(|| SanError::Illegal(san.to_string()));
+        }
+
+        let mut chars: Vec<char> = token.chars().collect();
+
+        // promotion, either `e8=Q` or `e8Q`
+        let mut promote_to = Piece::EMPTY;
+        if let Some(&last) = chars.last() {
+            if "QRBNK".contains(last) && chars.len() > 2 {
+                promote_to = Piece::from_str(last.to_string().as_str()).unwrap();
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        let piece = match chars.first() {
+            Some(c) if "KQRBN".contains(*c) => {
+                let piece = Piece::from_str(c.to_string().as_str()).unwrap();
+                chars.remove(0);
+                piece
+            }
+            _ => Piece::PAWN,
+        };
+
+        if chars.len() < 2 {
+            return Err(SanError::Invalid(san.to_string()));
+        }
+        let to: String = chars[chars.len() - 2..].iter().collect();
+        let to = parse_square(&to).ok_or_else(|| SanError::Invalid(san.to_string()))?;
+
+        // whatever is left between piece and target field is disambiguation and the capture marker
+        let mut from_file = None;
+        let mut from_rank = None;
+        let mut capture = false;
+        for c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' => from_file = Some(*c as usize - 'a' as usize),
+                '1'..='8' => from_rank = Some(*c as usize - '1' as usize),
+                'x' | ':' => capture = true,
+                _ => return Err(SanError::Invalid(san.to_string())),
+            }
+        }
+
+        let candidates: Vec<Move> = legal_moves
+            .into_iter()
+            .filter(|m| {
+                let (rank, file) = self.calc_rank_and_file(m.from);
+                m.to == to
+                    && !m.castling
+                    && m.drop == Piece::EMPTY
+                    && self.pieces[m.from] == piece
+                    && m.promote_to == promote_to
+                    && from_file.is_none_or(|f| f == file)
+                    && from_rank.is_none_or(|r| r == rank)
+                    && (!capture || m.capture != Piece::EMPTY)
+            })
+            .collect();
+
+        match candidates.len() {
+            0 => Err(SanError::Illegal(san.to_string())),
+            1 => Ok(candidates[0]),
+            _ => Err(SanError::Ambiguous(san.to_string())),
+        }
+    }
+}
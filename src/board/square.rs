@@ -0,0 +1,21 @@
+const FILES: &str = "abcdefgh";
+
+/// Returns the algebraic name of a board index, e.g. `0` => `a8` and `63` => `h1`.
+pub fn square_name(index: usize) -> String {
+    let rank = 7 - index / 8;
+    let file = index % 8;
+
+    format!("{}{}", FILES.chars().nth(file).unwrap(), rank + 1)
+}
+
+/// Parses an algebraic square name (`e4`) into a board index.
+pub fn parse_square(name: &str) -> Option<usize> {
+    let mut chars = name.chars();
+    let file = FILES.find(chars.next()?)?;
+    let rank = chars.next()?.to_digit(10)? as usize;
+    if chars.next().is_some() || !(1..=8).contains(&rank) {
+        return None;
+    }
+
+    Some((8 - rank) * 8 + file)
+}
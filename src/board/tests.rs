@@ -1,14 +1,20 @@
-#[cfg(test)]
-use crate::{Board, DEFAULT_COLORS, DEFAULT_PIECES};
+use crate::{Board, Color, DEFAULT_COLORS, DEFAULT_PIECES};
+use crate::board::chess960::{Chess960, StartPositions};
+use crate::board::defaults::{BLACK_QUEEN_SIDE, HORDE_FEN, STARTING_FEN, WHITE_QUEEN_SIDE};
+use crate::board::fen::Fen;
 use crate::board::generator::MoveGenerator;
+use crate::board::perft::Perft;
+use crate::board::san::{San, SanError};
+use crate::board::variant::{Outcome, Variant};
 
 #[test]
 fn test_calc_row_and_rank_at_0() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
-    let result = board.calc_row_and_rank(0);
+    let result = board.calc_rank_and_file(0);
     assert_eq!(result, (7, 0));
 }
 
@@ -17,8 +23,9 @@ fn test_calc_row_and_rank_at_7() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
-    let result = board.calc_row_and_rank(7);
+    let result = board.calc_rank_and_file(7);
     assert_eq!(result, (7, 7));
 }
 
@@ -27,8 +34,9 @@ fn test_calc_row_and_rank_at_8() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
-    let result = board.calc_row_and_rank(8);
+    let result = board.calc_rank_and_file(8);
     assert_eq!(result, (6, 0));
 }
 
@@ -37,8 +45,9 @@ fn test_calc_row_and_rank_at_9() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
-    let result = board.calc_row_and_rank(9);
+    let result = board.calc_rank_and_file(9);
     assert_eq!(result, (6, 1));
 }
 
@@ -47,8 +56,9 @@ fn test_calc_row_and_rank_at_14() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
-    let result = board.calc_row_and_rank(14);
+    let result = board.calc_rank_and_file(14);
     assert_eq!(result, (6, 6));
 }
 
@@ -57,8 +67,9 @@ fn test_calc_row_and_rank_at_15() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
-    let result = board.calc_row_and_rank(15);
+    let result = board.calc_rank_and_file(15);
     assert_eq!(result, (6, 7));
 }
 
@@ -67,8 +78,9 @@ fn test_calc_row_and_rank_at_56() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
-    let result = board.calc_row_and_rank(56);
+    let result = board.calc_rank_and_file(56);
     assert_eq!(result, (0, 0));
 }
 
@@ -77,8 +89,9 @@ fn test_calc_row_and_rank_at_63() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
-    let result = board.calc_row_and_rank(63);
+    let result = board.calc_rank_and_file(63);
     assert_eq!(result, (0, 7));
 }
 
@@ -87,6 +100,7 @@ fn test_calc_index_at_0_0() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
 
     let result = board.calc_index(0, 0);
@@ -98,6 +112,7 @@ fn test_calc_index_at_0_7() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
 
     let result = board.calc_index(0, 7);
@@ -109,6 +124,7 @@ fn test_calc_index_at_1_0() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
 
     let result = board.calc_index(1, 0);
@@ -120,6 +136,7 @@ fn test_calc_index_at_7_7() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
 
     let result = board.calc_index(7, 7);
@@ -132,8 +149,519 @@ fn test_calc_index_at_7_0() {
     let board = Board {
         pieces: DEFAULT_PIECES,
         colors: DEFAULT_COLORS,
+        ..Default::default()
     };
 
     let result = board.calc_index(7, 0);
     assert_eq!(result, 0);
 }
+
+#[test]
+fn test_fen_round_trip() {
+    for fen in [
+        STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+    ] {
+        assert_eq!(Board::from_fen(fen).to_fen(), fen);
+    }
+}
+
+#[test]
+fn test_fen_default_board() {
+    assert!(Board::from_fen(STARTING_FEN) == Board { ..Default::default() });
+}
+
+#[test]
+fn test_fen_invalid() {
+    assert!(Board::try_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1").is_err());
+    assert!(Board::try_from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+    assert!(Board::try_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1").is_err());
+    assert!(Board::try_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1").is_err());
+}
+
+#[test]
+fn test_fen_pawns_on_back_ranks() {
+    assert_eq!(Board::try_from_fen("4k2P/8/8/8/8/8/8/4K3 w - - 0 1").unwrap_err().message, "pawn on h8");
+    assert!(Board::try_from_fen("p3k3/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+    assert!(Board::try_from_fen("4k3/8/8/8/8/8/8/p3K3 b - - 0 1").is_err());
+    // the horde starts on the first rank
+    assert!(Board::try_from_fen(HORDE_FEN).is_ok());
+}
+
+#[test]
+fn test_perft_starting_position() {
+    let board = Board { ..Default::default() };
+    assert_eq!(board.perft(1).nodes, 20);
+    assert_eq!(board.perft(2).nodes, 400);
+    assert_eq!(board.perft(3).nodes, 8902);
+}
+
+#[test]
+fn test_perft_kiwipete() {
+    let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let result = board.perft(2);
+    assert_eq!(result.nodes, 2039);
+    assert_eq!(result.captures, 351);
+    assert_eq!(result.ep, 1);
+    assert_eq!(result.castles, 91);
+    assert_eq!(result.checks, 3);
+
+    assert_eq!(board.perft(3).nodes, 97862);
+}
+
+#[test]
+fn test_perft_en_passant_and_pins() {
+    let board = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+    assert_eq!(board.perft(4).nodes, 43238);
+}
+
+#[test]
+fn test_perft_promotions() {
+    let board = Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+    assert_eq!(board.perft(3).nodes, 9467);
+
+    let board = Board::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8");
+    assert_eq!(board.perft(2).nodes, 1486);
+}
+
+#[test]
+fn test_perft_checkmates() {
+    let board = Board { ..Default::default() };
+    let result = board.perft(4);
+    assert_eq!(result.nodes, 197281);
+    assert_eq!(result.checks, 469);
+    assert_eq!(result.checkmates, 8);
+}
+
+#[test]
+fn test_apply_castling() {
+    let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let m = board.parse_san("O-O").unwrap();
+    assert_eq!(board.apply(m).to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+
+    let m = board.parse_san("O-O-O").unwrap();
+    assert_eq!(board.apply(m).to_fen(), "r3k2r/8/8/8/8/8/8/2KR3R b kq - 1 1");
+}
+
+#[test]
+fn test_castling_through_check() {
+    let board = Board::from_fen("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1");
+    assert_eq!(board.parse_san("O-O"), Err(SanError::Illegal("O-O".to_string())));
+    assert!(board.parse_san("O-O-O").is_ok());
+}
+
+#[test]
+fn test_undo() {
+    let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    for m in board.generate_legal() {
+        let undone = board.apply(m).undo(m);
+        assert!(undone.pieces == board.pieces && undone.colors == board.colors, "{}", m);
+    }
+}
+
+#[test]
+fn test_san_round_trip() {
+    let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    for m in board.generate_legal() {
+        let san = board.to_san(m);
+        assert_eq!(board.parse_san(&san), Ok(m), "{}", san);
+    }
+}
+
+#[test]
+fn test_san_disambiguation() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1");
+    assert_eq!(board.parse_san("Rd1"), Err(SanError::Ambiguous("Rd1".to_string())));
+    let m = board.parse_san("Rad1").unwrap();
+    assert_eq!(m.from, 56);
+    assert_eq!(board.to_san(m), "Rad1");
+
+    let board = Board::from_fen("4k3/8/8/8/7R/8/4K3/7R w - - 0 1");
+    let m = board.parse_san("R1h2").unwrap();
+    assert_eq!(m.from, 63);
+    assert_eq!(board.to_san(m), "R1h2");
+}
+
+#[test]
+fn test_san_promotion_and_mate() {
+    let board = Board::from_fen("6k1/4P3/6K1/8/8/8/8/8 w - - 0 1");
+    let m = board.parse_san("e8=Q").unwrap();
+    assert_eq!(m.promote_to, crate::board::piece::Piece::QUEEN);
+    assert_eq!(board.to_san(m), "e8=Q#");
+    assert_eq!(board.parse_san("e8Q#"), Ok(m));
+}
+
+
+#[test]
+fn test_chess960_start_positions() {
+    assert_eq!(Board::from_chess960(518), Some(Board { chess960: true, ..Default::default() }));
+    assert_eq!(Board::from_chess960(0).unwrap().to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    assert_eq!(Board::from_chess960(959).unwrap().to_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+    assert_eq!(Board::from_chess960(960), None);
+
+    // every index gives a different position with the king between the rooks
+    let mut ranks: Vec<String> = (0..960).map(|index| Board::from_chess960(index).unwrap().to_fen()).collect();
+    ranks.sort();
+    ranks.dedup();
+    assert_eq!(ranks.len(), 960);
+}
+
+#[test]
+fn test_chess960_fen() {
+    // Shredder-FEN names the rook files, X-FEN only when the rook is not the outermost one
+    let shredder = Board::from_fen("2r1kr2/8/8/8/8/8/8/1R2K1R1 w GBfc - 0 1");
+    let xfen = Board::from_fen("2r1kr2/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1");
+    assert_eq!(shredder, xfen);
+    assert!(shredder.chess960);
+    assert_eq!(shredder.castling_files, [6, 1, 5, 2]);
+    assert_eq!(shredder.to_fen(), "2r1kr2/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1");
+
+    let inner = Board::from_fen("rr2k3/8/8/8/8/8/8/4K3 b b - 0 1");
+    assert_eq!(inner.castling_files[BLACK_QUEEN_SIDE], 1);
+    assert_eq!(inner.to_fen(), "rr2k3/8/8/8/8/8/8/4K3 b b - 0 1");
+
+    assert!(!Board::from_fen(STARTING_FEN).chess960);
+    assert!(Board::try_from_fen("4k3/8/8/8/8/8/8/4K3 w X - 0 1").is_err());
+}
+
+#[test]
+fn test_chess960_castling() {
+    // the king stays on g1 and only the rook moves
+    let board = Board::from_fen("1r2k3/8/8/8/8/8/8/R5KR w HA - 0 1");
+    let moves: Vec<String> = board.generate_legal().iter().filter(|m| m.castling).map(|m| m.to_string()).collect();
+    assert_eq!(moves, ["g1h1", "g1a1"]);
+    assert_eq!(board.apply(board.parse_san("O-O").unwrap()).to_fen(), "1r2k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+    // the black rook on b8 attacks b1, which the king does not pass when castling queen side
+    let m = board.parse_san("O-O-O").unwrap();
+    let after = board.apply(m);
+    assert_eq!(after.to_fen(), "1r2k3/8/8/8/8/8/8/2KR3R b - - 1 1");
+    let undone = after.undo(m);
+    assert!(undone.pieces == board.pieces && undone.colors == board.colors);
+
+    // the king on b1 passes the attacked field e1
+    let board = Board::from_fen("4r1k1/8/8/8/8/8/8/1K4R1 w G - 0 1");
+    assert!(board.generate_legal().iter().all(|m| !m.castling));
+
+    // classical castling is unchanged, unless UCI asks for king takes rook
+    let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert_eq!(board.parse_san("O-O").unwrap().to_string(), "e1g1");
+    let board = Board { chess960: true, ..board };
+    assert_eq!(board.parse_san("O-O").unwrap().to_string(), "e1h1");
+    assert_eq!(board.apply(board.parse_san("O-O").unwrap()).to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+}
+
+#[test]
+fn test_perft_chess960() {
+    // reference positions from https://www.chessprogramming.org/Chess960_Perft_Results
+    let data = include_str!("chess960.perft");
+    let mut board = None;
+    let mut positions = 0;
+    for line in data.lines() {
+        if let Some(fen) = line.strip_prefix("epd ") {
+            board = Some(Board::from_fen(fen));
+            positions += 1;
+        } else if let Some(counts) = line.strip_prefix("perft ") {
+            let (depth, nodes) = counts.split_once(' ').unwrap();
+            let depth: usize = depth.parse().unwrap();
+            // deeper counts only for some positions, they take long in debug builds
+            if depth <= 2 || (depth == 3 && positions % 40 == 1) {
+                let board = board.unwrap();
+                assert_eq!(board.perft(depth).nodes, nodes.parse::<usize>().unwrap(), "{} depth {}", board.to_fen(), depth);
+            }
+        }
+    }
+    assert_eq!(positions, 960);
+}
+
+#[test]
+fn test_dfrc_start_positions() {
+    let dfrc = StartPositions::DFRC;
+    assert_eq!(dfrc.len(), 921_600);
+    assert_eq!(dfrc.board(518 * 960 + 518), Board::from_chess960(518));
+    assert_eq!(dfrc.board(921_600), None);
+
+    // independent back ranks, each color with its own castling rooks
+    let board = dfrc.board(960 + 959).unwrap();
+    assert_eq!(board.to_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/BQNBNRKR w KQkq - 0 1");
+    assert_eq!(board.castling_files, [7, 5, 2, 0]);
+    assert_eq!(board.perft(2).nodes, 400);
+}
+
+#[test]
+fn test_back_rank_start_positions() {
+    let set: StartPositions = "RRKNNQBB/rnbqkbnr".parse().unwrap();
+    assert_eq!(set.len(), 1);
+    let board = set.board(0).unwrap();
+    // both white rooks are on the queen side, so only the outer one castles
+    assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RRKNNQBB w Qkq - 0 1");
+    assert_eq!(board.castling_files[WHITE_QUEEN_SIDE], 0);
+    assert_eq!(set.board(1), None);
+
+    assert_eq!("nbrkqbrn".parse::<StartPositions>().unwrap().board(0).unwrap().to_fen(),
+               "nbrkqbrn/pppppppp/8/8/8/8/PPPPPPPP/NBRKQBRN w KQkq - 0 1");
+    assert!("RNBQKBN".parse::<StartPositions>().is_err());
+    assert!("RNBQQBNR".parse::<StartPositions>().is_err());
+    assert!("RNBPKBNR".parse::<StartPositions>().is_err());
+    assert_eq!("Chess960".parse::<StartPositions>(), Ok(StartPositions::CHESS960));
+}
+
+#[test]
+fn test_crazyhouse_fen() {
+    let fen = "r1bqk2r/pppp1ppp/2n1p3/4P3/1b1Pn3/2NB1N2/PPP2PPP/R1BQK2R[Qbp] b KQkq - 0 1";
+    let board = Board::from_fen(fen);
+    assert_eq!(board.variant, Variant::CRAZYHOUSE);
+    assert_eq!(board.pockets, [[0, 0, 0, 0, 1], [1, 0, 1, 0, 0]]);
+    assert_eq!(board.to_fen(), fen);
+
+    // pockets as a ninth rank, promoted pieces marked with a tilde
+    let board = Board::from_fen("4k3/1Q~6/8/8/4b3/8/Kpp5/8/ b - - 0 1");
+    assert_eq!(board.promoted, 1 << 9);
+    assert_eq!(board.to_fen(), "4k3/1Q~6/8/8/4b3/8/Kpp5/8[] b - - 0 1");
+
+    assert_eq!(Board::from_fen(STARTING_FEN).variant, Variant::STANDARD);
+    assert!(Board::try_from_fen("4k3/8/8/8/8/8/8/4K3[Kq] w - - 0 1").is_err());
+}
+
+#[test]
+fn test_crazyhouse_captures() {
+    // the promoted queen goes back into the pocket as a pawn
+    let board = Board::from_fen("4k3/1Q~6/8/8/4b3/8/Kpp5/8[] b - - 0 1");
+    let board = board.apply(board.parse_san("Bxb7").unwrap());
+    assert_eq!(board.to_fen(), "4k3/1b6/8/8/8/8/Kpp5/8[p] w - - 0 2");
+
+    let board = board.apply(board.parse_san("Kxb2").unwrap());
+    assert_eq!(board.pockets, [[1, 0, 0, 0, 0], [1, 0, 0, 0, 0]]);
+
+    // promoting makes a piece that is marked as promoted
+    let board = board.apply(board.parse_san("c1=Q+").unwrap());
+    assert_eq!(board.to_fen(), "4k3/1b6/8/8/8/8/1K6/2q~5[Pp] w - - 0 3");
+
+    // with the other side left in check, a pseudo-legal move takes its king, which has no pocket
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/4R1K1[] w - - 0 1");
+    let m = board.generate(board.next).into_iter().find(|m| m.capture == crate::board::piece::Piece::KING).unwrap();
+    assert_eq!(board.apply(m).pockets, [[0; 5]; 2]);
+}
+
+#[test]
+fn test_crazyhouse_drops() {
+    let board = Board::from_fen("2k5/8/8/8/8/8/8/4K3[Np] w - - 0 1");
+    let m = board.parse_san("N@f3").unwrap();
+    assert_eq!(m.to_string(), "N@f3");
+    assert_eq!(board.to_san(m), "N@f3");
+    let after = board.apply(m);
+    assert_eq!(after.to_fen(), "2k5/8/8/8/8/5N2/8/4K3[p] b - - 1 1");
+    assert_eq!(after.undo(m).pockets, board.pockets);
+
+    // pawns are not dropped on the first and last rank
+    assert!(after.parse_san("@e4").is_ok());
+    assert_eq!(after.parse_san("P@e1"), Err(SanError::Illegal("P@e1".to_string())));
+    assert!(after.parse_san("N@e4").is_err());
+
+    for m in after.generate_legal() {
+        assert_eq!(after.parse_san(&after.to_san(m)), Ok(m), "{}", m);
+    }
+}
+
+#[test]
+fn test_perft_crazyhouse() {
+    let board = Board::from_fen("2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1");
+    assert_eq!(board.perft(1).nodes, 301);
+    assert_eq!(board.perft(2).nodes, 75353);
+
+    let board = Board::from_fen("2k5/8/8/8/8/8/8/4K3[Qn] w - - 0 1");
+    assert_eq!(board.perft(3).nodes, 88634);
+
+    let board = Board::from_fen("r1bqk2r/pppp1ppp/2n1p3/4P3/1b1Pn3/2NB1N2/PPP2PPP/R1BQK2R[] b KQkq - 0 1");
+    assert_eq!(board.perft(2).nodes, 1347);
+    assert_eq!(board.perft(3).nodes, 58057);
+
+    let board = Board::from_fen("4k3/1Q~6/8/8/4b3/8/Kpp5/8/ b - - 0 1");
+    assert_eq!(board.perft(4).nodes, 132758);
+}
+
+fn atomic(fen: &str) -> Board {
+    Board { variant: Variant::ATOMIC, ..Board::from_fen(fen) }
+}
+
+#[test]
+fn test_atomic_explosions() {
+    // the knight on f6 and the bishop on d6 explode, the pawns survive
+    let board = atomic("4k3/8/3b1n2/4p3/3P1p2/8/8/4R1K1 w - - 0 1");
+    let board = board.apply(board.parse_san("Rxe5").unwrap());
+    assert_eq!(board.to_fen(), "4k3/8/8/8/3P1p2/8/8/6K1 b - - 0 1");
+
+    // exploding a rook on its home field takes away its castling right
+    let board = atomic("r3k2r/8/8/8/8/8/1B6/4K3 w kq - 0 1");
+    let board = board.apply(board.parse_san("Bxh8").unwrap());
+    assert!(!board.castling[crate::board::defaults::BLACK_KING_SIDE]);
+}
+
+#[test]
+fn test_atomic_rules() {
+    // kings cannot capture
+    let board = atomic("4k3/8/8/8/8/8/4p3/4K3 w - - 0 1");
+    assert!(board.parse_san("Kxe2").is_err());
+
+    // kings next to each other cannot be checked, so d2 is fine as well
+    let board = atomic("8/8/8/8/8/3kK3/8/3R4 b - - 0 1");
+    assert!(!board.is_check(board.next));
+    assert_eq!(board.generate_legal().len(), 7);
+
+    // a capture that blows up the own king is illegal, one that blows up the other king wins
+    let board = atomic("8/8/8/8/8/8/3qK3/4r2k w - - 0 1");
+    assert!(board.parse_san("Kxd2").is_err());
+    let board = atomic("4k3/3p4/8/8/8/8/8/3QK3 w - - 0 1");
+    let m = board.parse_san("Qxd7").unwrap();
+    assert_eq!(board.to_san(m), "Qxd7#");
+    let board = board.apply(m);
+    assert_eq!(board.variant_outcome(), Some(Outcome::LOSS));
+    assert!(board.generate_legal().is_empty());
+}
+
+#[test]
+fn test_perft_atomic() {
+    assert_eq!(atomic(STARTING_FEN).perft(3).nodes, 8902);
+    assert_eq!(atomic("rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1").perft(3).nodes, 45237);
+    assert_eq!(atomic("rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq - 0 1").perft(3).nodes, 23353);
+
+    // Chess960 castling next to the other king
+    assert_eq!(atomic("8/8/8/8/8/8/2k5/rR4KR w KQ - 0 1").perft(4).nodes, 61401);
+    assert_eq!(atomic("r3k1rR/5K2/8/8/8/8/8/8 b kq - 0 1").perft(4).nodes, 98729);
+    assert_eq!(atomic("Rr2k1rR/3K4/3p4/8/8/8/7P/8 w kq - 0 1").perft(3).nodes, 10631);
+}
+
+#[test]
+fn test_three_check_fen() {
+    // remaining checks after the en passant field, or checks given at the end
+    let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+2 0 1");
+    assert_eq!(board.variant, Variant::THREECHECK);
+    assert_eq!(board.checks, [0, 1]);
+    assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 +0+1");
+    assert_eq!(Board::from_fen(&board.to_fen()), board);
+
+    assert!(Board::try_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 +4+0").is_err());
+    assert!(Board::try_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 x").is_err());
+}
+
+#[test]
+fn test_three_check() {
+    let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 0 3 +2+0");
+    let board = board.apply(board.parse_san("Bxf7+").unwrap());
+    assert_eq!(board.checks, [3, 0]);
+    assert_eq!(board.variant_outcome(), Some(Outcome::LOSS));
+    assert!(board.generate_legal().is_empty());
+}
+
+#[test]
+fn test_perft_three_check() {
+    let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 1+1 0 1");
+    assert_eq!(board.perft(3).nodes, 97848);
+    let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 1+1 0 1");
+    assert_eq!(board.perft(3).nodes, 13410);
+}
+
+#[test]
+fn test_king_of_the_hill() {
+    let board = Board { variant: Variant::KINGOFTHEHILL, ..Board::from_fen("8/8/8/8/8/3K4/8/4k3 w - - 0 1") };
+    let board = board.apply(board.parse_san("Kd4").unwrap());
+    assert_eq!(board.variant_outcome(), Some(Outcome::LOSS));
+    assert!(board.generate_legal().is_empty());
+    assert_eq!(Board { variant: Variant::STANDARD, ..board }.generate_legal().len(), 5);
+
+    let board = Board { variant: Variant::KINGOFTHEHILL, ..Board::from_fen("8/8/8/2k5/8/8/8/4K2R w K - 0 1") };
+    assert_eq!(board.perft(4).nodes, 10437);
+}
+
+fn racing_kings(fen: &str) -> Board {
+    Board { variant: Variant::RACINGKINGS, ..Board::from_fen(fen) }
+}
+
+#[test]
+fn test_racing_kings() {
+    // moves that give check are not allowed
+    let board = racing_kings("8/8/8/8/8/k7/7R/7K w - - 0 1");
+    assert!(board.parse_san("Ra2").is_err());
+    assert!(board.parse_san("Rh3").is_err());
+    assert!(board.parse_san("Rg2").is_ok());
+
+    // black can still reach the 8th rank right after white, which is a draw
+    let board = racing_kings("7K/k7/8/8/8/8/8/8 b - - 0 1");
+    assert_eq!(board.variant_outcome(), None);
+    let board = board.apply(board.parse_san("Ka8").unwrap());
+    assert_eq!(board.variant_outcome(), Some(Outcome::DRAW));
+
+    // here it cannot
+    let board = racing_kings("7K/8/k7/8/8/8/8/8 b - - 0 1");
+    assert_eq!(board.variant_outcome(), Some(Outcome::LOSS));
+}
+
+#[test]
+fn test_perft_racing_kings() {
+    let board = racing_kings(crate::board::defaults::RACING_KINGS_FEN);
+    assert_eq!(board.perft(1).nodes, 21);
+    assert_eq!(board.perft(3).nodes, 11264);
+    assert_eq!(racing_kings("4brn1/2K2k2/8/8/8/8/8/8 w - - 0 1").perft(5).nodes, 12981);
+}
+
+fn antichess(fen: &str) -> Board {
+    Board { variant: Variant::ANTICHESS, ..Board::from_fen(fen) }
+}
+
+#[test]
+fn test_antichess() {
+    // captures are compulsory, kings can be taken and pawns promote to kings
+    let board = antichess("4k3/8/8/8/8/8/1p6/R3K3 b - - 0 1");
+    let moves: Vec<String> = board.generate_legal().iter().map(|m| m.to_string()).collect();
+    assert_eq!(moves, vec!["b2a1q", "b2a1r", "b2a1b", "b2a1n", "b2a1k"]);
+    let board = antichess("4k3/8/8/8/8/8/1p6/4K3 b - - 0 1");
+    assert_eq!(board.parse_san("b1=K").unwrap().promote_to, crate::board::piece::Piece::KING);
+    assert!(board.parse_san("Kd7").is_ok());
+
+    // no castling and no check
+    let board = antichess("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert!(board.generate_legal().iter().all(|m| !m.castling));
+    let board = antichess("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1");
+    assert!(!board.is_check(Color::WHITE));
+
+    // losing the last piece wins
+    let board = antichess("8/8/8/8/8/8/1p6/R7 b - - 0 1");
+    let board = board.apply(board.parse_san("bxa1=Q").unwrap());
+    assert_eq!(board.variant_outcome(), Some(Outcome::WIN));
+
+    // so does being stalemated
+    assert_eq!(antichess("8/8/8/8/8/p7/P7/8 w - - 0 1").variant_outcome(), Some(Outcome::WIN));
+}
+
+#[test]
+fn test_perft_antichess() {
+    assert_eq!(antichess(crate::board::defaults::ANTICHESS_FEN).perft(3).nodes, 8067);
+    assert_eq!(antichess("8/1p6/8/8/8/8/P7/8 w - - 0 1").perft(5).nodes, 1);
+    assert_eq!(antichess("8/2p5/8/8/8/8/P7/8 w - - 0 1").perft(11).nodes, 312);
+}
+
+fn horde(fen: &str) -> Board {
+    Board { variant: Variant::HORDE, ..Board::from_fen(fen) }
+}
+
+#[test]
+fn test_horde() {
+    // pawns on the first rank move two fields, but cannot be taken en passant afterwards
+    let board = horde("k7/8/8/8/8/8/8/P7 w - - 0 1");
+    let board = board.apply(board.parse_san("a3").unwrap());
+    assert_eq!(board.to_fen(), "k7/8/8/8/8/P7/8/8 b - - 0 1");
+
+    // black wins by taking all white pieces
+    let board = horde("k7/8/8/8/8/8/8/P1r5 b - - 0 1");
+    let board = board.apply(board.parse_san("Rxa1").unwrap());
+    assert_eq!(board.variant_outcome(), Some(Outcome::LOSS));
+    assert!(board.generate_legal().is_empty());
+}
+
+#[test]
+fn test_perft_horde() {
+    assert_eq!(horde(crate::board::defaults::HORDE_FEN).perft(3).nodes, 1274);
+    assert_eq!(horde("4k3/pp4q1/3P2p1/8/P3PP2/PPP2r2/PPP5/PPPP4 b - - 0 1").perft(3).nodes, 6633);
+    assert_eq!(horde("k7/5p2/4p2P/3p2P1/2p2P2/1p2P2P/p2P2P1/2P2P2 w - - 0 1").perft(3).nodes, 2205);
+}
@@ -0,0 +1,103 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::board::defaults::{ANTICHESS_FEN, HORDE_FEN, RACING_KINGS_FEN, STARTING_FEN};
+
+/// The rules a board is played by, named like the `UCI_Variant` values of other engines.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum Variant {
+    #[default]
+    STANDARD,
+    CRAZYHOUSE,
+    ATOMIC,
+    THREECHECK,
+    KINGOFTHEHILL,
+    RACINGKINGS,
+    ANTICHESS,
+    HORDE,
+}
+
+/// All variants, in the order `UCI_Variant` lists them.
+pub const VARIANTS: [Variant; 8] = [
+    Variant::STANDARD,
+    Variant::CRAZYHOUSE,
+    Variant::ATOMIC,
+    Variant::THREECHECK,
+    Variant::KINGOFTHEHILL,
+    Variant::RACINGKINGS,
+    Variant::ANTICHESS,
+    Variant::HORDE,
+];
+
+/// The fields a king wins on in King of the Hill, d5, e5, d4 and e4.
+pub const HILL: [usize; 4] = [27, 28, 35, 36];
+
+/// A game ended by a rule of its variant rather than by checkmate, for the side to move.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Outcome {
+    WIN,
+    LOSS,
+    DRAW,
+}
+
+impl Variant {
+    pub fn start_fen(&self) -> &'static str {
+        match self {
+            Variant::RACINGKINGS => RACING_KINGS_FEN,
+            Variant::ANTICHESS => ANTICHESS_FEN,
+            Variant::HORDE => HORDE_FEN,
+            _ => STARTING_FEN,
+        }
+    }
+}
+
+/// Three-check counters in FEN, either the checks each side has left like `2+3` or the checks
+/// each side has given like `+1+0`. Returns the checks given by white and black.
+pub fn parse_checks(field: &str) -> Option<[u8; 2]> {
+    let (given, (white, black)) = match field.strip_prefix('+') {
+        Some(given) => (true, given.split_once('+')?),
+        None => (false, field.split_once('+')?),
+    };
+    let mut checks = [white.parse::<u8>().ok()?, black.parse::<u8>().ok()?];
+    if checks.iter().any(|count| *count > 3) {
+        return None;
+    }
+    if !given {
+        checks = checks.map(|left| 3 - left);
+    }
+    Some(checks)
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variant::STANDARD => write!(f, "chess"),
+            Variant::CRAZYHOUSE => write!(f, "crazyhouse"),
+            Variant::ATOMIC => write!(f, "atomic"),
+            Variant::THREECHECK => write!(f, "3check"),
+            Variant::KINGOFTHEHILL => write!(f, "kingofthehill"),
+            Variant::RACINGKINGS => write!(f, "racingkings"),
+            Variant::ANTICHESS => write!(f, "antichess"),
+            Variant::HORDE => write!(f, "horde"),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "chess" | "standard" => Ok(Variant::STANDARD),
+            "crazyhouse" => Ok(Variant::CRAZYHOUSE),
+            "atomic" => Ok(Variant::ATOMIC),
+            "3check" | "threecheck" => Ok(Variant::THREECHECK),
+            "kingofthehill" | "koth" => Ok(Variant::KINGOFTHEHILL),
+            "racingkings" => Ok(Variant::RACINGKINGS),
+            "antichess" => Ok(Variant::ANTICHESS),
+            "horde" => Ok(Variant::HORDE),
+            _ => Err(())
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::io;
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::piece::Piece;
+use crate::board::zobrist::Zobrist;
+use crate::random::Random;
+use crate::Board;
+
+pub(crate) mod builder;
+#[cfg(test)]
+mod tests;
+
+const ENTRY_SIZE: usize = 16;
+
+/// A single Polyglot book entry, stored as 16 big-endian bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl Entry {
+    pub fn from_bytes(bytes: &[u8]) -> Entry {
+        Entry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// The inverse of `decode_move`.
+pub fn encode_move(board: &Board, m: Move) -> u16 {
+    let (from_row, from_file) = board.calc_rank_and_file(m.from);
+    let (mut to_row, mut to_file) = board.calc_rank_and_file(m.to);
+
+    // castling is written as the king taking its own rook
+    if m.castling {
+        (to_row, to_file) = board.calc_rank_and_file(board.castling_rook(m));
+    }
+
+    let promotion = match m.promote_to {
+        Piece::KNIGHT => 1,
+        Piece::BISHOP => 2,
+        Piece::ROOK => 3,
+        Piece::QUEEN => 4,
+        _ => 0,
+    };
+
+    (to_file | to_row << 3 | from_file << 6 | from_row << 9 | promotion << 12) as u16
+}
+
+/// Polyglot encodes moves as `to file | to row << 3 | from file << 6 | from row << 9 | promotion << 12`,
+/// with castling written as the king capturing its own rook.
+pub fn decode_move(board: &Board, raw_move: u16) -> Option<Move> {
+    let field = |file: u16, row: u16| board.calc_index(row as usize, file as usize);
+    let to = field(raw_move & 7, (raw_move >> 3) & 7);
+    let from = field((raw_move >> 6) & 7, (raw_move >> 9) & 7);
+    let promote_to = match (raw_move >> 12) & 7 {
+        0 => Piece::EMPTY,
+        1 => Piece::KNIGHT,
+        2 => Piece::BISHOP,
+        3 => Piece::ROOK,
+        4 => Piece::QUEEN,
+        _ => return None,
+    };
+
+    let castles = board.pieces[from] == Piece::KING
+        && board.pieces[to] == Piece::ROOK
+        && board.colors[to] == board.colors[from];
+
+    board.generate_legal().into_iter().find(|m| {
+        if castles {
+            m.castling && m.from == from && (m.to > m.from) == (to > from)
+        } else {
+            !m.castling && m.from == from && m.to == to && m.promote_to == promote_to
+        }
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Book {
+    entries: Vec<Entry>,
+}
+
+impl Book {
+    pub fn open(path: &str) -> io::Result<Book> {
+        let bytes = fs::read(path)?;
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a polyglot book"));
+        }
+
+        Ok(Book::from_bytes(&bytes))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Book {
+        Book { entries: bytes.chunks_exact(ENTRY_SIZE).map(Entry::from_bytes).collect() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// All entries for a position, books are sorted by key so this is a binary search.
+    pub fn lookup(&self, key: u64) -> &[Entry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// The legal book moves of a position with their weights, entries that do not decode into a
+    /// legal move are skipped.
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        self.lookup(board.hash())
+            .iter()
+            .filter_map(|entry| decode_move(board, entry.raw_move).map(|m| (m, entry.weight)))
+            .collect()
+    }
+
+    pub fn best_move(&self, board: &Board) -> Option<Move> {
+        self.moves(board)
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0)
+            .max_by_key(|(_, weight)| *weight)
+            .map(|(m, _)| m)
+    }
+
+    /// Picks a move with a probability proportional to its weight.
+    pub fn weighted_move(&self, board: &Board, random: &mut Random) -> Option<Move> {
+        let moves = self.moves(board);
+        let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pick = random.below(total);
+        for (m, weight) in moves {
+            if pick < weight as u64 {
+                return Some(m);
+            }
+            pick -= weight as u64;
+        }
+
+        None
+    }
+}
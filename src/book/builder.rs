@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use crate::board::generator::MoveGenerator;
+use crate::board::san::San;
+use crate::board::zobrist::Zobrist;
+use crate::book::{decode_move, encode_move, Entry};
+use crate::pgn::{Game, GameResult};
+use crate::{Board, Color};
+
+/// Results of the games a move was played in, from the point of view of the side that moved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Stats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Share of the points scored, between 0 and 1.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    // polyglot weights a win twice as much as a draw
+    fn weight(&self) -> u64 {
+        2 * self.wins as u64 + self.draws as u64
+    }
+}
+
+/// Aggregates the first plies of many games into Polyglot book entries.
+pub struct Builder {
+    max_plies: usize,
+    positions: HashMap<u64, HashMap<u16, Stats>>,
+}
+
+impl Builder {
+    pub fn new(max_plies: usize) -> Builder {
+        Builder { max_plies, positions: HashMap::new() }
+    }
+
+    pub fn positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Adds the opening of a game, games without a result carry no statistics and are skipped.
+    pub fn add_game(&mut self, game: &Game) {
+        if game.result == GameResult::UNKNOWN {
+            return;
+        }
+
+        for (board, m) in game.positions.iter().zip(game.moves.iter()).take(self.max_plies) {
+            let stats = self.positions
+                .entry(board.hash())
+                .or_default()
+                .entry(encode_move(board, *m))
+                .or_default();
+
+            match (game.result, board.next) {
+                (GameResult::DRAW, _) => stats.draws += 1,
+                (GameResult::WHITE, Color::WHITE) | (GameResult::BLACK, Color::BLACK) => stats.wins += 1,
+                _ => stats.losses += 1,
+            }
+        }
+    }
+
+    fn accepted(stats: &Stats, min_games: u32, min_score: f64) -> bool {
+        stats.games() >= min_games && stats.score() >= min_score
+    }
+
+    /// Book entries sorted by key and, within a position, by descending weight.
+    pub fn entries(&self, min_games: u32, min_score: f64) -> Vec<Entry> {
+        let mut entries: Vec<(u64, u16, u64)> = Vec::new();
+        for (key, moves) in &self.positions {
+            for (raw_move, stats) in moves {
+                // moves that only lost keep a zero weight, they are known but never played
+                if Builder::accepted(stats, min_games, min_score) {
+                    entries.push((*key, *raw_move, stats.weight()));
+                }
+            }
+        }
+
+        // weights are 16 bit, so scale everything down if the most played move does not fit
+        let max_weight = entries.iter().map(|(_, _, weight)| *weight).max().unwrap_or(0);
+        let scale = max_weight.div_ceil(u16::MAX as u64).max(1);
+
+        let mut entries: Vec<Entry> = entries
+            .into_iter()
+            .map(|(key, raw_move, weight)| Entry {
+                key,
+                raw_move,
+                weight: weight.div_ceil(scale) as u16,
+                learn: 0,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)).then(a.raw_move.cmp(&b.raw_move)));
+
+        entries
+    }
+
+    pub fn to_bytes(&self, min_games: u32, min_score: f64) -> Vec<u8> {
+        self.entries(min_games, min_score).iter().flat_map(|entry| entry.to_bytes()).collect()
+    }
+
+    /// An indented, human readable tree of the book moves reachable from `board`.
+    pub fn tree(&self, board: &Board, min_games: u32, min_score: f64) -> String {
+        let mut tree = String::new();
+        self.write_tree(board, 0, min_games, min_score, &mut tree);
+        tree
+    }
+
+    fn write_tree(&self, board: &Board, ply: usize, min_games: u32, min_score: f64, tree: &mut String) {
+        if ply >= self.max_plies {
+            return;
+        }
+        let Some(moves) = self.positions.get(&board.hash()) else { return };
+
+        let mut moves: Vec<(&u16, &Stats)> = moves
+            .iter()
+            .filter(|(_, stats)| Builder::accepted(stats, min_games, min_score))
+            .collect();
+        moves.sort_by(|a, b| b.1.games().cmp(&a.1.games()).then(a.0.cmp(b.0)));
+
+        for (raw_move, stats) in moves {
+            let Some(m) = decode_move(board, *raw_move) else { continue };
+            let games = stats.games() as f64;
+            tree.push_str(&format!(
+                "{}{} ({} games, +{:.0}% ={:.0}% -{:.0}%)\n",
+                "  ".repeat(ply),
+                board.to_san(m),
+                stats.games(),
+                100.0 * stats.wins as f64 / games,
+                100.0 * stats.draws as f64 / games,
+                100.0 * stats.losses as f64 / games,
+            ));
+            self.write_tree(&board.apply(m), ply + 1, min_games, min_score, tree);
+        }
+    }
+}
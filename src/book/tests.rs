@@ -0,0 +1,184 @@
+use crate::board::defaults::STARTING_FEN;
+use crate::board::fen::Fen;
+use crate::board::generator::MoveGenerator;
+use crate::board::san::San;
+use crate::board::zobrist::Zobrist;
+use crate::book::builder::Builder;
+use crate::book::{decode_move, encode_move, Book, Entry};
+use crate::pgn::reader::Reader;
+use crate::random::Random;
+use crate::Board;
+
+// reference keys from the polyglot book format specification
+const REFERENCE_KEYS: [(&str, u64); 9] = [
+    (STARTING_FEN, 0x463b96181691fc9c),
+    ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+    ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+    ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+    ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+    ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3", 0x652a607ca3f242c1),
+    ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4", 0x00fdd303c946bdd9),
+    ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+    ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+];
+
+fn entry(key: u64, raw_move: u16, weight: u16) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(key.to_be_bytes());
+    bytes.extend(raw_move.to_be_bytes());
+    bytes.extend(weight.to_be_bytes());
+    bytes.extend(0u32.to_be_bytes());
+    bytes
+}
+
+// e2e4, d2d4 and g1f3 for the starting position, sorted by key like a real book
+fn test_book() -> Book {
+    let start = Board { ..Default::default() }.hash();
+    let mut bytes = entry(start - 1, 0, 1);
+    bytes.extend(entry(start, 4 | 3 << 3 | 4 << 6 | 1 << 9, 10));
+    bytes.extend(entry(start, 3 | 3 << 3 | 3 << 6 | 1 << 9, 5));
+    bytes.extend(entry(start, 5 | 2 << 3 | 6 << 6, 0));
+    bytes.extend(entry(start + 1, 0, 1));
+    Book::from_bytes(&bytes)
+}
+
+#[test]
+fn test_polyglot_hash() {
+    for (fen, key) in REFERENCE_KEYS {
+        assert_eq!(Board::from_fen(fen).hash(), key, "{}", fen);
+    }
+}
+
+#[test]
+fn test_lookup() {
+    let book = test_book();
+    let board = Board { ..Default::default() };
+    assert_eq!(book.len(), 5);
+    assert_eq!(book.lookup(board.hash()).len(), 3);
+    assert!(book.lookup(0).is_empty());
+
+    let moves: Vec<String> = book.moves(&board).iter().map(|(m, _)| m.to_string()).collect();
+    assert_eq!(moves, vec!["e2e4", "d2d4", "g1f3"]);
+}
+
+#[test]
+fn test_best_and_weighted_move() {
+    let book = test_book();
+    let board = Board { ..Default::default() };
+    assert_eq!(book.best_move(&board).unwrap().to_string(), "e2e4");
+
+    let mut random = Random::new(42);
+    let mut counts = [0; 2];
+    for _ in 0..300 {
+        match book.weighted_move(&board, &mut random).unwrap().to_string().as_str() {
+            "e2e4" => counts[0] += 1,
+            "d2d4" => counts[1] += 1,
+            other => panic!("unexpected book move {}", other),
+        }
+    }
+    assert!(counts[0] > counts[1] && counts[1] > 50);
+}
+
+#[test]
+fn test_decode_castling() {
+    let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let m = decode_move(&board, 7 | 4 << 6).unwrap();
+    assert!(m.castling);
+    assert_eq!(m.to_string(), "e1g1");
+    let m = decode_move(&board, 4 << 6).unwrap();
+    assert!(m.castling);
+    assert_eq!(m.to_string(), "e1c1");
+
+    let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+    assert_eq!(decode_move(&board, 7 | 7 << 3 | 4 << 6 | 7 << 9).unwrap().to_string(), "e8g8");
+}
+
+#[test]
+fn test_decode_promotion() {
+    let board = Board::from_fen("8/1P6/8/8/8/8/8/k6K w - - 0 1");
+    let m = decode_move(&board, 1 | 7 << 3 | 1 << 6 | 6 << 9 | 1 << 12).unwrap();
+    assert_eq!(m.to_string(), "b7b8n");
+    assert!(decode_move(&board, 1 | 7 << 3 | 1 << 6 | 6 << 9 | 7 << 12).is_none());
+}
+
+const GAMES: &str = r#"[Result "1-0"]
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[Result "0-1"]
+1. e4 c5 2. Nf3 d6 0-1
+
+[Result "1/2-1/2"]
+1. e4 e5 2. Nf3 Nf6 1/2-1/2
+
+[Result "1-0"]
+1. d4 d5 2. c4 1-0
+
+[Result "*"]
+1. h4 *
+"#;
+
+fn build(plies: usize) -> Builder {
+    let mut builder = Builder::new(plies);
+    for game in Reader::new(GAMES) {
+        builder.add_game(&game.unwrap());
+    }
+    builder
+}
+
+#[test]
+fn test_encode_move() {
+    for fen in [STARTING_FEN, "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "r3k2r/1P6/8/8/8/8/8/R3K2R b KQkq - 0 1"] {
+        let board = Board::from_fen(fen);
+        for m in board.generate_legal() {
+            assert_eq!(decode_move(&board, encode_move(&board, m)), Some(m), "{}", m);
+        }
+    }
+}
+
+#[test]
+fn test_builder_stats() {
+    let builder = build(2);
+    // start position and the positions after 1. e4 and 1. d4
+    assert_eq!(builder.positions(), 3);
+
+    let start = Board { ..Default::default() };
+    let entries = builder.entries(1, 0.0);
+    let first: Vec<&Entry> = entries.iter().filter(|entry| entry.key == start.hash()).collect();
+    assert_eq!(first.len(), 2);
+    // e4: 1 win, 1 draw, 1 loss => 2 * 1 + 1, d4: 1 win => 2
+    assert_eq!(decode_move(&start, first[0].raw_move).unwrap().to_string(), "e2e4");
+    assert_eq!(first[0].weight, 3);
+    assert_eq!(first[1].weight, 2);
+
+    // black's replies are scored from black's point of view, c5 won
+    let after_e4 = start.apply(start.parse_san("e4").unwrap());
+    let book = Book::from_bytes(&builder.to_bytes(1, 0.6));
+    let replies: Vec<String> = book.moves(&after_e4).iter().map(|(m, _)| m.to_string()).collect();
+    assert_eq!(replies, vec!["c7c5"]);
+}
+
+#[test]
+fn test_builder_filters() {
+    let builder = build(4);
+    let start = Board { ..Default::default() };
+
+    let book = Book::from_bytes(&builder.to_bytes(2, 0.0));
+    assert_eq!(book.moves(&start).len(), 1);
+    assert!(book.moves(&Board::from_fen("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1")).is_empty());
+
+    // entries must be sorted by key for the binary search
+    let entries = builder.entries(1, 0.0);
+    assert!(entries.windows(2).all(|pair| pair[0].key <= pair[1].key));
+}
+
+#[test]
+fn test_builder_tree() {
+    let builder = build(2);
+    let tree = builder.tree(&Board { ..Default::default() }, 1, 0.0);
+    assert_eq!(tree, "e4 (3 games, +33% =33% -33%)
+  e5 (2 games, +0% =50% -50%)
+  c5 (1 games, +100% =0% -0%)
+d4 (1 games, +100% =0% -0%)
+  d5 (1 games, +0% =0% -100%)
+");
+}
@@ -1,8 +1,337 @@
-use std::io;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Instant;
+use crate::book::builder::Builder;
+use crate::board::chess960::{Chess960, StartPositions};
+use crate::board::fen::Fen;
+use crate::board::generator::MoveGenerator;
+use crate::board::perft::Perft;
+use crate::board::san::San;
+use crate::board::variant::Variant;
+use crate::dtm::{self, generator::generate, Tablebase};
+use crate::mate::problem::{Problem, Stipulation};
+use crate::mate::{Proof, Solver};
+use crate::pgn::reader::Reader;
+use crate::random::Random;
+use crate::{uci, Board};
+
+const USAGE: &str = "usage: rusty [command]
+
+without a command, rusty speaks UCI on stdin and stdout
+
+commands:
+    perft <depth> [fen]    count the leaf nodes of the move tree, divided by root move
+    perft <depth> chess960 <index>
+                           the same for Chess960 start position 0 to 959
+        --variant <name>   play by the rules of `crazyhouse`, `atomic`, `3check`, `kingofthehill`,
+                           `racingkings`, `antichess` or `horde`, starting from the variant's
+                           start position
+    pgn <file> [--export]  replay all games of a PGN file and print their final positions,
+                           or re-export them in normalized PGN
+    book <output> <pgn>... build a Polyglot book from PGN files
+        --plies <n>        number of plies to include per game (default 20)
+        --min-games <n>    minimum number of games a move was played in (default 1)
+        --min-score <p>    minimum score in percent for the side playing the move (default 0)
+        --tree <file>      also write a human readable tree of the book
+    startpos <set>...      print start positions as FEN, one per line, where a set is `chess960`,
+                           `dfrc` or back ranks like `RNBQKBNR` or `RNBQKBNR/rbnkqbnr`
+        --random <n>       pick n different positions at random instead of listing all
+        --seed <n>         seed for the random choice
+    dtm <dir> [ending]...  generate distance to mate tables like `KQvKR` into a directory,
+                           all of KQvK, KRvK, KPvK, KBNvK and KQvKR by default
+    mate <fen>             prove or disprove a forced mate by the side to move and print the line
+        --moves <n>        only look for mates in at most n moves
+        --nodes <n>        give up after n nodes (default 10000000)
+    problem <stipulation> <fen>
+                           solve a chess problem, `#n` direct mate, `h#n` helpmate, `s#n` selfmate
+                           or `=n`, `h=n`, `s=n` the same with stalemate, listing all solutions
+                           with their variations, cooks and duals";
+
+pub(crate) fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("perft") => perft(&args[1..]),
+        Some("pgn") => pgn(&args[1..]),
+        Some("book") => book(&args[1..]),
+        Some("startpos") => startpos(&args[1..]),
+        Some("dtm") => dtm(&args[1..]),
+        Some("mate") => mate(&args[1..]),
+        Some("problem") => problem(&args[1..]),
+        None => {
+            uci::run();
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn perft(args: &[String]) -> Result<(), String> {
+    let (args, variant) = match args.iter().position(|arg| arg == "--variant") {
+        Some(index) => {
+            let variant = args.get(index + 1)
+                .and_then(|variant| variant.parse::<Variant>().ok())
+                .ok_or_else(|| "unknown variant".to_string())?;
+            (&args[..index], Some(variant))
+        }
+        None => (args, None),
+    };
+    let depth = args.first()
+        .and_then(|depth| depth.parse::<usize>().ok())
+        .ok_or_else(|| USAGE.to_string())?;
+    let mut board = match args.get(1).map(String::as_str) {
+        Some("chess960") => args.get(2)
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(Board::from_chess960)
+            .ok_or_else(|| "expected a Chess960 position between 0 and 959".to_string())?,
+        Some(_) => Board::try_from_fen(&args[1..].join(" ")).map_err(|e| e.to_string())?,
+        None => Board::try_from_fen(variant.unwrap_or_default().start_fen()).map_err(|e| e.to_string())?,
+    };
+    if let Some(variant) = variant {
+        board.variant = variant;
+    }
+
+    let perft = board.divide(depth);
+    println!("perft for depth: {} => {}", perft.depth, perft);
+    Ok(())
+}
+
+fn pgn(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or_else(|| USAGE.to_string())?;
+    let input = fs::read_to_string(path).map_err(|e| format!("cannot read `{}`: {}", path, e))?;
+    let export = args.iter().any(|arg| arg == "--export");
+
+    for game in Reader::new(&input) {
+        match game {
+            Ok(game) if export => println!("{}", game.to_pgn()),
+            Ok(game) => {
+                let board = game.positions.last().unwrap();
+                println!("{} - {} {} ({} plies): {}",
+                         game.tag("White").unwrap_or("?"),
+                         game.tag("Black").unwrap_or("?"),
+                         game.result,
+                         game.moves.len(),
+                         board.to_fen());
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    Ok(())
+}
+
+// splits `--name value` options from positional arguments
+fn options(args: &[String]) -> (Vec<&String>, Vec<(&str, &String)>) {
+    let mut positional = Vec::new();
+    let mut named = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.strip_prefix("--") {
+            Some(name) => {
+                if let Some(value) = iter.next() {
+                    named.push((name, value));
+                }
+            }
+            None => positional.push(arg),
+        }
+    }
+    (positional, named)
+}
+
+fn book(args: &[String]) -> Result<(), String> {
+    let (positional, named) = options(args);
+    if positional.len() < 2 {
+        return Err(USAGE.to_string());
+    }
+
+    let mut plies = 20;
+    let mut min_games = 1;
+    let mut min_score = 0.0;
+    let mut tree = None;
+    for (name, value) in named {
+        let invalid = || format!("invalid value `{}` for --{}", value, name);
+        match name {
+            "plies" => plies = value.parse().map_err(|_| invalid())?,
+            "min-games" => min_games = value.parse().map_err(|_| invalid())?,
+            "min-score" => min_score = value.parse::<f64>().map_err(|_| invalid())? / 100.0,
+            "tree" => tree = Some(value),
+            _ => return Err(format!("unknown option --{}", name)),
+        }
+    }
+
+    let mut builder = Builder::new(plies);
+    for path in &positional[1..] {
+        let input = fs::read_to_string(path).map_err(|e| format!("cannot read `{}`: {}", path, e))?;
+        for game in Reader::new(&input) {
+            match game {
+                Ok(game) => builder.add_game(&game),
+                Err(e) => eprintln!("{}: {}", path, e),
+            }
+        }
+    }
+
+    let output = positional[0];
+    let bytes = builder.to_bytes(min_games, min_score);
+    fs::write(output, &bytes).map_err(|e| format!("cannot write `{}`: {}", output, e))?;
+    println!("wrote {} entries for {} positions to {}", bytes.len() / 16, builder.positions(), output);
+
+    if let Some(path) = tree {
+        let start = Board { ..Default::default() };
+        fs::write(path, builder.tree(&start, min_games, min_score))
+            .map_err(|e| format!("cannot write `{}`: {}", path, e))?;
+    }
+
+    Ok(())
+}
+
+fn startpos(args: &[String]) -> Result<(), String> {
+    let (positional, named) = options(args);
+    if positional.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let mut count = None;
+    let mut random = Random::from_time();
+    for (name, value) in named {
+        let invalid = || format!("invalid value `{}` for --{}", value, name);
+        match name {
+            "random" => count = Some(value.parse::<usize>().map_err(|_| invalid())?),
+            "seed" => random = Random::new(value.parse().map_err(|_| invalid())?),
+            _ => return Err(format!("unknown option --{}", name)),
+        }
+    }
+
+    let sets = positional
+        .iter()
+        .map(|set| set.parse::<StartPositions>().map_err(|_| format!("invalid start positions `{}`", set)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let total: usize = sets.iter().map(StartPositions::len).sum();
+    // the sets are numbered one after the other
+    let board = |mut index: usize| {
+        for set in &sets {
+            if index < set.len() {
+                return set.board(index).unwrap();
+            }
+            index -= set.len();
+        }
+        unreachable!()
+    };
+
+    // a locked stdout keeps long lists like all DFRC positions fast
+    let mut out = io::stdout().lock();
+    let mut write = |index: usize| writeln!(out, "{}", board(index).to_fen()).map_err(|e| e.to_string());
+    match count {
+        Some(count) if count < total => {
+            let mut picked = HashSet::new();
+            while picked.len() < count {
+                let index = random.below(total as u64) as usize;
+                if picked.insert(index) {
+                    write(index)?;
+                }
+            }
+        }
+        _ => (0..total).try_for_each(write)?,
+    }
+
+    Ok(())
+}
+
+fn dtm(args: &[String]) -> Result<(), String> {
+    let directory = args.first().ok_or_else(|| USAGE.to_string())?;
+    fs::create_dir_all(directory).map_err(|e| format!("cannot create `{}`: {}", directory, e))?;
+    // tables generated earlier are reused for the captures and promotions of larger endings
+    let mut tablebase = Tablebase::open(directory).map_err(|e| format!("cannot read `{}`: {}", directory, e))?;
+
+    let endings: Vec<&str> = if args.len() > 1 {
+        args[1..].iter().map(String::as_str).collect()
+    } else {
+        dtm::ENDINGS.to_vec()
+    };
+    for ending in endings {
+        let start = Instant::now();
+        let table = generate(ending, &tablebase)?;
+        Tablebase::save(&table, Path::new(directory)).map_err(|e| format!("cannot write {}: {}", ending, e))?;
+        println!("{}: longest mate in {} moves, {} positions in {:.1}s",
+                 table.name(),
+                 table.longest_mate(),
+                 table.size(),
+                 start.elapsed().as_secs_f64());
+        tablebase.insert(table);
+    }
+
+    Ok(())
+}
+
+fn mate(args: &[String]) -> Result<(), String> {
+    let (positional, named) = options(args);
+    if positional.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let fen = positional.iter().map(|arg| arg.as_str()).collect::<Vec<_>>().join(" ");
+    let board = Board::try_from_fen(&fen).map_err(|e| e.to_string())?;
+
+    let mut max_nodes = 10_000_000;
+    let mut moves = None;
+    for (name, value) in named {
+        let invalid = || format!("invalid value `{}` for --{}", value, name);
+        match name {
+            "nodes" => max_nodes = value.parse().map_err(|_| invalid())?,
+            "moves" => moves = Some(value.parse().ok().filter(|moves| *moves > 0).ok_or_else(invalid)?),
+            _ => return Err(format!("unknown option --{}", name)),
+        }
+    }
+
+    let start = Instant::now();
+    let mut solver = Solver::new(max_nodes);
+    let proof = match moves {
+        Some(moves) => solver.solve_in(&board, moves),
+        None => solver.solve(&board),
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+    match proof {
+        Proof::MATE(line) => {
+            let mut board = board;
+            let mut moves = Vec::new();
+            for m in line.iter() {
+                moves.push(board.to_san(*m));
+                board = board.apply(*m);
+            }
+            println!("mate in {}: {}", line.len().div_ceil(2), moves.join(" "));
+        }
+        Proof::NOMATE => println!("no forced mate"),
+        Proof::UNKNOWN => println!("unknown, no proof within {} nodes", max_nodes),
+    }
+    println!("{} nodes in {:.1}s", solver.nodes, elapsed);
+
+    Ok(())
+}
+
+fn problem(args: &[String]) -> Result<(), String> {
+    if args.len() < 2 {
+        return Err(USAGE.to_string());
+    }
+    let stipulation: Stipulation = args[0].parse().map_err(|_| format!("unknown stipulation `{}`", args[0]))?;
+    let board = Board::try_from_fen(&args[1..].join(" ")).map_err(|e| e.to_string())?;
+
+    let start = Instant::now();
+    let problem = Problem::new(board, stipulation);
+    let solutions = problem.solve();
+    print!("{}", problem.format(&solutions));
+
+    let mut summary = match problem.count(&solutions) {
+        0 => "no solution".to_string(),
+        1 => "1 solution".to_string(),
+        n => format!("{} solutions", n),
+    };
+    if problem.is_cooked(&solutions) {
+        summary.push_str(", cooked");
+    }
+    match problem.duals(&solutions) {
+        0 => (),
+        1 => summary.push_str(", 1 dual"),
+        n => summary.push_str(&format!(", {} duals", n)),
+    }
+    println!("{}: {} in {:.1}s", stipulation, summary, start.elapsed().as_secs_f64());
 
-pub(crate) fn input() -> io::Result<()> {
-    let mut buffer = String::new();
-    io::stdin().read_line(&mut buffer)?;
-    println!("{}", buffer);
     Ok(())
 }
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::board::color::inverse_color;
+use crate::board::generator::MoveGenerator;
+use crate::board::piece::Piece;
+use crate::board::variant::Variant;
+use crate::syzygy::{material, piece_count, PIECE_LETTERS};
+use crate::{Board, Color};
+
+pub(crate) mod generator;
+#[cfg(test)]
+mod tests;
+
+const MAGIC: &[u8; 4] = b"RDTM";
+const VERSION: u8 = 1;
+const SUFFIX: &str = ".rdtm";
+
+/// The endings `rusty dtm` generates, each one only needs the tables before it.
+pub const ENDINGS: [&str; 5] = ["KQvK", "KRvK", "KPvK", "KBNvK", "KQvKR"];
+
+/// Distance to mate in plies for the side to move.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dtm {
+    WIN(usize),
+    LOSS(usize),
+    DRAW,
+}
+
+impl Dtm {
+    // A byte per position: 0 for draws, 1 to 127 to mate in that many moves and 128 + n to be
+    // mated in n moves, 128 is checkmate.
+    fn from_byte(byte: u8) -> Dtm {
+        match byte {
+            0 => Dtm::DRAW,
+            1..=127 => Dtm::WIN(byte as usize * 2 - 1),
+            _ => Dtm::LOSS((byte as usize - 128) * 2),
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Dtm::DRAW => 0,
+            Dtm::WIN(plies) => plies.div_ceil(2) as u8,
+            Dtm::LOSS(plies) => 128 + (plies / 2) as u8,
+        }
+    }
+}
+
+// the eight symmetries of the board, squares from a8 like on the board
+fn transform(square: usize, symmetry: usize) -> usize {
+    let (mut rank, mut file) = (7 - square / 8, square % 8);
+    if symmetry & 1 != 0 {
+        file = 7 - file;
+    }
+    if symmetry & 2 != 0 {
+        rank = 7 - rank;
+    }
+    if symmetry & 4 != 0 {
+        (rank, file) = (file, rank);
+    }
+    (7 - rank) * 8 + file
+}
+
+// the board with colors swapped and mirrored top to bottom
+fn flip(board: &Board) -> Board {
+    let mut flipped = *board;
+    for i in 0..64 {
+        flipped.pieces[i ^ 56] = board.pieces[i];
+        flipped.colors[i ^ 56] = match board.colors[i] {
+            Color::NONE => Color::NONE,
+            color => inverse_color(color),
+        };
+    }
+    flipped.next = inverse_color(board.next);
+    flipped.en_passant = -1;
+    flipped
+}
+
+/// A distance to mate table for one ending, white is the stronger side. Positions are indexed by
+/// the side to move, the square of the white king and the squares of all other pieces. Symmetric
+/// positions share an index, so the white king is always on a1-d1-d4 or, with pawns, on the
+/// queen side.
+pub struct Table {
+    name: String,
+    pieces: Vec<(Piece, Color)>,
+    pawns: bool,
+    // the squares the white king may stand on, in index order
+    kings: Vec<usize>,
+    king_codes: [Option<usize>; 64],
+    data: Vec<u8>,
+}
+
+impl Table {
+    /// An empty table for an ending like `KQvKR`.
+    pub fn new(name: &str) -> Result<Table, String> {
+        let invalid = || format!("invalid ending `{}`", name);
+        let (white, black) = name.split_once('v').ok_or_else(invalid)?;
+        let mut pieces = Vec::new();
+        for (side, color) in [(white, Color::WHITE), (black, Color::BLACK)] {
+            if !side.starts_with('K') || side.matches('K').count() != 1 {
+                return Err(invalid());
+            }
+            // pieces are listed strongest first, starting with the king
+            let mut order = 0;
+            for letter in side.chars() {
+                let position = PIECE_LETTERS.iter().position(|(_, l)| *l == letter).ok_or_else(invalid)?;
+                if position < order {
+                    return Err(invalid());
+                }
+                order = position;
+                pieces.push((PIECE_LETTERS[position].0, color));
+            }
+        }
+        if pieces.len() > 5 {
+            return Err(format!("{} has too many pieces", name));
+        }
+
+        let pawns = pieces.iter().any(|(piece, _)| *piece == Piece::PAWN);
+        let kings: Vec<usize> = (0..64)
+            .map(|square| (7 - square / 8, square % 8))
+            .filter(|(rank, file)| *file <= 3 && (pawns || rank <= file))
+            .map(|(rank, file)| (7 - rank) * 8 + file)
+            .collect();
+        let mut king_codes = [None; 64];
+        for (code, square) in kings.iter().enumerate() {
+            king_codes[*square] = Some(code);
+        }
+
+        let mut table = Table { name: name.to_string(), pieces, pawns, kings, king_codes, data: Vec::new() };
+        table.data = vec![0; table.size()];
+        Ok(table)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of indexes, valid or not.
+    pub fn size(&self) -> usize {
+        2 * self.half_size()
+    }
+
+    // the indexes for one side to move
+    fn half_size(&self) -> usize {
+        self.kings.len() * 64usize.pow(self.pieces.len() as u32 - 1)
+    }
+
+    /// The index of a position with the material of the table.
+    pub fn index(&self, board: &Board) -> usize {
+        let mut used = [false; 64];
+        let squares: Vec<usize> = self
+            .pieces
+            .iter()
+            .map(|(piece, color)| {
+                let square = (0..64).find(|i| !used[*i] && board.pieces[*i] == *piece && board.colors[*i] == *color).unwrap();
+                used[square] = true;
+                square
+            })
+            .collect();
+
+        // pawns cannot be mirrored top to bottom, so only the files are flipped
+        let symmetries = if self.pawns { 2 } else { 8 };
+        let mut best: Option<Vec<usize>> = None;
+        for symmetry in 0..symmetries {
+            if self.king_codes[transform(squares[0], symmetry)].is_none() {
+                continue;
+            }
+            let mut transformed: Vec<usize> = squares.iter().map(|square| transform(*square, symmetry)).collect();
+            // equal pieces may trade places
+            for i in 1..self.pieces.len() {
+                let mut j = i;
+                while j > 1 && self.pieces[j - 1] == self.pieces[j] && transformed[j - 1] > transformed[j] {
+                    transformed.swap(j - 1, j);
+                    j -= 1;
+                }
+            }
+            if best.as_ref().is_none_or(|best| transformed < *best) {
+                best = Some(transformed);
+            }
+        }
+
+        let best = best.unwrap();
+        let index = best[1..].iter().fold(self.king_codes[best[0]].unwrap(), |index, square| index * 64 + square);
+        if board.next == Color::BLACK {
+            index + self.half_size()
+        } else {
+            index
+        }
+    }
+
+    /// The position of an index, `None` for impossible positions and for indexes that are not
+    /// the one its symmetric positions are stored under.
+    pub fn position(&self, index: usize) -> Option<Board> {
+        let next = if index >= self.half_size() { Color::BLACK } else { Color::WHITE };
+        let mut rest = index % self.half_size();
+        let mut squares = vec![0; self.pieces.len()];
+        for i in (1..self.pieces.len()).rev() {
+            squares[i] = rest % 64;
+            rest /= 64;
+        }
+        squares[0] = self.kings[rest];
+
+        let mut board = Board {
+            pieces: [Piece::EMPTY; 64],
+            colors: [Color::NONE; 64],
+            next,
+            castling: [false; 4],
+            ..Default::default()
+        };
+        for ((piece, color), square) in self.pieces.iter().zip(squares) {
+            let rank = 7 - square / 8;
+            if board.pieces[square] != Piece::EMPTY || (*piece == Piece::PAWN && (rank == 0 || rank == 7)) {
+                return None;
+            }
+            board.pieces[square] = *piece;
+            board.colors[square] = *color;
+        }
+
+        // the side that just moved cannot be in check
+        if board.is_check(inverse_color(next)) || self.index(&board) != index {
+            return None;
+        }
+        Some(board)
+    }
+
+    pub fn get(&self, index: usize) -> Dtm {
+        Dtm::from_byte(self.data[index])
+    }
+
+    fn set(&mut self, index: usize, dtm: Dtm) {
+        self.data[index] = dtm.to_byte();
+    }
+
+    /// The distance to mate of a position with the material of the table.
+    pub fn probe(&self, board: &Board) -> Dtm {
+        self.get(self.index(board))
+    }
+
+    /// The longest mate in the table, in moves.
+    pub fn longest_mate(&self) -> usize {
+        self.data.iter().filter(|byte| **byte < 128).max().copied().unwrap_or(0) as usize
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(self.name.len() as u8);
+        bytes.extend(self.name.as_bytes());
+        bytes.extend(&self.data);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Table, String> {
+        if bytes.len() < 6 || &bytes[..4] != MAGIC || bytes[4] != VERSION {
+            return Err("not a dtm table".to_string());
+        }
+        let end = 6 + bytes[5] as usize;
+        let name = bytes.get(6..end).and_then(|name| std::str::from_utf8(name).ok()).ok_or("invalid table name")?;
+        let mut table = Table::new(name)?;
+        if bytes.len() - end != table.size() {
+            return Err(format!("{} has the wrong size", name));
+        }
+        table.data.copy_from_slice(&bytes[end..]);
+        Ok(table)
+    }
+}
+
+/// Distance to mate tables built by `rusty dtm`, stored as one `.rdtm` file per ending.
+#[derive(Default)]
+pub struct Tablebase {
+    tables: HashMap<String, Table>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    /// Loads all tables in a directory.
+    pub fn open(path: &str) -> io::Result<Tablebase> {
+        let mut tablebase = Tablebase::default();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.to_string_lossy().ends_with(SUFFIX) {
+                let table = Table::from_bytes(&fs::read(&path)?)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+                tablebase.insert(table);
+            }
+        }
+        Ok(tablebase)
+    }
+
+    /// Writes a table to a directory, named after its ending.
+    pub fn save(table: &Table, directory: &Path) -> io::Result<()> {
+        fs::write(directory.join(format!("{}{}", table.name, SUFFIX)), table.to_bytes())
+    }
+
+    pub fn insert(&mut self, table: Table) {
+        self.max_pieces = self.max_pieces.max(table.pieces.len());
+        self.tables.insert(table.name.clone(), table);
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// The distance to mate of a position, `None` if there is no table for it or it is played
+    /// by the rules of a variant. Bare kings and a single minor piece are always drawn.
+    pub fn probe(&self, board: &Board) -> Option<Dtm> {
+        let count = piece_count(board);
+        if count > self.max_pieces.max(3) || board.castling.iter().any(|right| *right) || board.variant != Variant::STANDARD {
+            return None;
+        }
+
+        let (white, black) = material(board);
+        let (strong, weak) = if white.len() >= black.len() { (&white, &black) } else { (&black, &white) };
+        if weak == "K" && ["K", "KB", "KN"].contains(&strong.as_str()) {
+            return Some(Dtm::DRAW);
+        }
+
+        match self.tables.get(&format!("{}v{}", white, black)) {
+            Some(table) => Some(table.probe(board)),
+            None => self.tables.get(&format!("{}v{}", black, white)).map(|table| table.probe(&flip(board))),
+        }
+    }
+}
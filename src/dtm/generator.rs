@@ -0,0 +1,162 @@
+use std::mem;
+use crate::board::color::inverse_color;
+use crate::board::generator::MoveGenerator;
+use crate::board::piece::Piece;
+use crate::dtm::{Dtm, Table, Tablebase};
+use crate::syzygy::material;
+use crate::{Board, Color};
+
+// mates further away do not fit into a byte
+const MAX_PLIES: usize = 2 * 127 - 1;
+
+// The positions one move before `board`, with the side that is not to move having made a quiet
+// move. Captures and promotions lead out of the table and are found by looking forward instead.
+fn predecessors(table: &Table, board: &Board) -> Vec<usize> {
+    let mover = inverse_color(board.next);
+    // pairs of the square a piece stands on and the square it came from
+    let mut moves = Vec::new();
+    for square in (0..64).filter(|i| board.colors[*i] == mover) {
+        let generated = match board.pieces[square] {
+            Piece::PAWN => {
+                // pawns walk backwards, two squares if they could have come from their second rank
+                let (rank, file) = board.calc_rank_and_file(square);
+                let (forward, second) = if mover == Color::WHITE { (1, 1) } else { (-1, 6) };
+                let behind = rank as isize - forward;
+                if (1..=6).contains(&behind) && board.is_empty_field(board.calc_index(behind as usize, file) as isize) {
+                    moves.push((square, board.calc_index(behind as usize, file)));
+                    let start = behind - forward;
+                    if start == second && board.is_empty_field(board.calc_index(start as usize, file) as isize) {
+                        moves.push((square, board.calc_index(start as usize, file)));
+                    }
+                }
+                continue;
+            }
+            Piece::ROOK => board.generate_rook_moves(square, mover),
+            Piece::KNIGHT => board.generate_knight_moves(square, mover),
+            Piece::BISHOP => board.generate_bishop_moves(square, mover),
+            Piece::QUEEN => board.generate_queen_moves(square, mover),
+            _ => board.generate_king_moves(square, mover),
+        };
+        moves.extend(generated.iter().filter(|m| m.capture == Piece::EMPTY && !m.castling).map(|m| (square, m.to)));
+    }
+
+    let mut indexes: Vec<usize> = moves
+        .into_iter()
+        .filter_map(|(square, origin)| {
+            let mut before = *board;
+            before.pieces[origin] = board.pieces[square];
+            before.colors[origin] = mover;
+            before.pieces[square] = Piece::EMPTY;
+            before.colors[square] = Color::NONE;
+            before.next = mover;
+            // the side to move now cannot have been in check with the other side to move
+            (!before.is_check(board.next)).then(|| table.index(&before))
+        })
+        .collect();
+    indexes.sort_unstable();
+    indexes.dedup();
+    indexes
+}
+
+/// Builds the table for an ending by retrograde analysis. Captures and promotions are looked up
+/// in `known`, which must hold all smaller tables the ending can turn into.
+///
+/// Mates are found first, then every position where the side to move can reach a lost position
+/// is won one ply later, and a position where every move reaches a won position is lost. What
+/// is left at the end is drawn.
+pub fn generate(name: &str, known: &Tablebase) -> Result<Table, String> {
+    let mut table = Table::new(name)?;
+    let size = table.size();
+
+    // moves that do not lead to a known result yet, and the longest loss found so far
+    let mut remaining = vec![0u8; size];
+    let mut longest = vec![0u16; size];
+    let mut resolved = vec![false; size];
+    let mut wins: Vec<Vec<usize>> = vec![Vec::new(); MAX_PLIES + 2];
+    let mut losses: Vec<Vec<usize>> = vec![Vec::new(); MAX_PLIES + 2];
+
+    for index in 0..size {
+        let Some(board) = table.position(index) else {
+            resolved[index] = true;
+            continue;
+        };
+
+        let moves = board.generate_legal();
+        if moves.is_empty() {
+            // stalemates stay drawn
+            resolved[index] = true;
+            if board.is_check(board.next) {
+                losses[0].push(index);
+            }
+            continue;
+        }
+
+        let mut children = Vec::new();
+        for m in moves {
+            let after = board.apply(m);
+            if m.capture == Piece::EMPTY && m.promote_to == Piece::EMPTY {
+                children.push(table.index(&after));
+                continue;
+            }
+            match known.probe(&after) {
+                Some(Dtm::LOSS(plies)) => {
+                    wins[plies + 1].push(index);
+                    remaining[index] += 1;
+                }
+                Some(Dtm::WIN(plies)) => longest[index] = longest[index].max(plies as u16 + 1),
+                Some(Dtm::DRAW) => remaining[index] += 1,
+                None => {
+                    let (white, black) = material(&after);
+                    return Err(format!("{} needs the table for {}v{}", name, white, black));
+                }
+            }
+        }
+        children.sort_unstable();
+        children.dedup();
+        remaining[index] += children.len() as u8;
+
+        // every capture and promotion loses and there is nothing else to play
+        if remaining[index] == 0 {
+            resolved[index] = true;
+            losses[longest[index] as usize].push(index);
+        }
+    }
+
+    for plies in 0..=MAX_PLIES {
+        for index in mem::take(&mut losses[plies]) {
+            table.set(index, Dtm::LOSS(plies));
+            let board = table.position(index).unwrap();
+            for previous in predecessors(&table, &board) {
+                if !resolved[previous] {
+                    wins[plies + 1].push(previous);
+                }
+            }
+        }
+
+        for index in mem::take(&mut wins[plies]) {
+            if resolved[index] {
+                continue;
+            }
+            resolved[index] = true;
+            table.set(index, Dtm::WIN(plies));
+
+            let board = table.position(index).unwrap();
+            for previous in predecessors(&table, &board) {
+                if resolved[previous] {
+                    continue;
+                }
+                remaining[previous] -= 1;
+                longest[previous] = longest[previous].max(plies as u16 + 1);
+                if remaining[previous] == 0 {
+                    resolved[previous] = true;
+                    losses[longest[previous] as usize].push(previous);
+                }
+            }
+        }
+    }
+
+    if wins.iter().chain(&losses).any(|bucket| !bucket.is_empty()) {
+        return Err(format!("{} has mates longer than {} plies", name, MAX_PLIES));
+    }
+    Ok(table)
+}
@@ -0,0 +1,160 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use crate::board::fen::Fen;
+use crate::board::generator::MoveGenerator;
+use crate::dtm::generator::generate;
+use crate::dtm::{Dtm, Table, Tablebase};
+use crate::search::{Limits, Search};
+use crate::Board;
+
+// generating takes a while in debug builds, so all tests share the tables
+fn tablebase() -> &'static Tablebase {
+    static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+    TABLEBASE.get_or_init(|| {
+        let mut tablebase = Tablebase::default();
+        for name in ["KQvK", "KRvK", "KPvK"] {
+            let table = generate(name, &tablebase).unwrap();
+            tablebase.insert(table);
+        }
+        tablebase
+    })
+}
+
+fn probe(fen: &str) -> Option<Dtm> {
+    tablebase().probe(&Board::from_fen(fen))
+}
+
+#[test]
+fn test_table_names() {
+    assert_eq!(Table::new("KQvKR").unwrap().size(), 2 * 10 * 64 * 64 * 64);
+    assert_eq!(Table::new("KPvK").unwrap().size(), 2 * 32 * 64 * 64);
+    assert!(Table::new("KvK").is_ok());
+    assert!(Table::new("QKvK").is_err());
+    assert!(Table::new("KPQvK").is_err());
+    assert!(Table::new("KQK").is_err());
+    assert!(Table::new("KQvKQv").is_err());
+}
+
+#[test]
+fn test_symmetric_positions() {
+    let table = Table::new("KRvK").unwrap();
+    let fens = [
+        "8/8/8/8/8/2k5/8/K6R w - - 0 1",
+        "8/8/8/8/8/5k2/8/R6K w - - 0 1",
+        "K6R/8/2k5/8/8/8/8/8 w - - 0 1",
+        "R7/8/8/8/8/2k5/8/K7 w - - 0 1",
+    ];
+    let index = table.index(&Board::from_fen(fens[0]));
+    for fen in fens {
+        assert_eq!(table.index(&Board::from_fen(fen)), index, "{}", fen);
+    }
+    assert_ne!(table.index(&Board::from_fen("8/8/8/8/8/2k5/8/K6R b - - 0 1")), index);
+
+    // every valid index decodes into a position that indexes back to it
+    for index in (0..table.size()).step_by(7) {
+        if let Some(board) = table.position(index) {
+            assert_eq!(table.index(&board), index);
+        }
+    }
+}
+
+#[test]
+fn test_longest_mates() {
+    let tablebase = tablebase();
+    let longest = |name: &str| tablebase.tables[name].longest_mate();
+    assert_eq!(longest("KQvK"), 10);
+    assert_eq!(longest("KRvK"), 16);
+    assert_eq!(longest("KPvK"), 28);
+}
+
+#[test]
+fn test_probe() {
+    assert_eq!(probe("k7/8/1K6/8/8/8/7Q/8 w - - 0 1"), Some(Dtm::WIN(1)));
+    assert_eq!(probe("k6Q/8/1K6/8/8/8/8/8 b - - 0 1"), Some(Dtm::LOSS(0)));
+    // stalemate
+    assert_eq!(probe("k7/8/1Q6/8/8/8/8/K7 b - - 0 1"), Some(Dtm::DRAW));
+    // black is the stronger side
+    assert_eq!(probe("8/7q/8/8/8/1k6/8/K7 b - - 0 1"), Some(Dtm::WIN(1)));
+    // the king can take the undefended queen
+    assert_eq!(probe("8/8/8/8/8/8/1q6/K6k w - - 0 1"), Some(Dtm::DRAW));
+
+    // rook pawns cannot win against a king in front of them, other pawns with the king ahead can
+    assert_eq!(probe("k7/8/8/8/8/8/P7/K7 w - - 0 1"), Some(Dtm::DRAW));
+    assert!(matches!(probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Dtm::WIN(_))));
+    assert!(matches!(probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(Dtm::LOSS(_))));
+
+    assert_eq!(probe("8/8/8/8/8/8/8/K5Bk w - - 0 1"), Some(Dtm::DRAW));
+    assert_eq!(probe("8/8/8/8/8/8/7r/K5Bk w - - 0 1"), None);
+    assert_eq!(probe("8/8/8/8/8/8/8/4K2R w K - 0 1"), None);
+}
+
+// The value of a position follows from the values of its children, which checks the tables
+// against move generation from the other direction.
+#[test]
+fn test_consistency() {
+    let tablebase = tablebase();
+    for name in ["KQvK", "KRvK", "KPvK"] {
+        let table = &tablebase.tables[name];
+        for index in (0..table.size()).step_by(101) {
+            let Some(board) = table.position(index) else { continue };
+            let moves = board.generate_legal();
+
+            let expected = if moves.is_empty() {
+                if board.is_check(board.next) { Dtm::LOSS(0) } else { Dtm::DRAW }
+            } else {
+                let children: Vec<Dtm> = moves.iter().map(|m| tablebase.probe(&board.apply(*m)).unwrap()).collect();
+                let fastest_win = children.iter().filter_map(|dtm| match dtm {
+                    Dtm::LOSS(plies) => Some(plies + 1),
+                    _ => None,
+                }).min();
+                let slowest_loss = children.iter().filter_map(|dtm| match dtm {
+                    Dtm::WIN(plies) => Some(plies + 1),
+                    _ => None,
+                }).max();
+                match fastest_win {
+                    Some(plies) => Dtm::WIN(plies),
+                    None if children.contains(&Dtm::DRAW) => Dtm::DRAW,
+                    None => Dtm::LOSS(slowest_loss.unwrap()),
+                }
+            };
+            assert_eq!(table.get(index), expected, "{} {}", name, board.to_fen());
+        }
+    }
+}
+
+#[test]
+fn test_bytes() {
+    let table = &tablebase().tables["KRvK"];
+    let bytes = table.to_bytes();
+    let read = Table::from_bytes(&bytes).unwrap();
+    assert_eq!(read.name(), "KRvK");
+    assert_eq!(read.data, table.data);
+
+    assert!(Table::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Table::from_bytes(b"RDTM").is_err());
+}
+
+#[test]
+fn test_missing_table() {
+    let error = generate("KPvK", &Tablebase::default()).err().unwrap();
+    assert!(error.contains("needs the table"), "{}", error);
+}
+
+#[test]
+fn test_search_mate() {
+    let mut tables = Tablebase::default();
+    tables.insert(Table::from_bytes(&tablebase().tables["KRvK"].to_bytes()).unwrap());
+
+    let mut search = Search::new(Vec::new());
+    search.dtm = Some(Arc::new(tables));
+    let limits = Limits { movetime: Some(Duration::from_millis(500)), ..Default::default() };
+    // the mate is far beyond the search horizon
+    let board = Board::from_fen("8/8/8/8/3k4/8/8/K6R w - - 0 1");
+    let info = search.run(&board, &limits, |_| {});
+
+    let Some(Dtm::WIN(plies)) = probe("8/8/8/8/3k4/8/8/K6R w - - 0 1") else { panic!("not a win") };
+    assert!(plies > 20);
+    assert!(info.tbhits > 0);
+    assert_eq!(info.mate_in(), Some(plies.div_ceil(2) as isize));
+}
+
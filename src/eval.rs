@@ -0,0 +1,150 @@
+use crate::board::crazyhouse::POCKET_PIECES;
+use crate::board::piece::Piece;
+use crate::{Board, Color};
+use endgame::{evaluate_endgame, scale_factor, Material, SCALE_NORMAL};
+use king::king_safety;
+use pawns::{passed_pawns, PawnTable};
+use pieces::activity;
+
+pub(crate) mod endgame;
+pub(crate) mod king;
+pub(crate) mod kpk;
+pub(crate) mod pawns;
+pub(crate) mod pieces;
+#[cfg(test)]
+mod tests;
+
+pub const PAWN_VALUE: isize = 100;
+pub const KNIGHT_VALUE: isize = 320;
+pub const BISHOP_VALUE: isize = 330;
+pub const ROOK_VALUE: isize = 500;
+pub const QUEEN_VALUE: isize = 900;
+
+// piece-square tables from white's point of view, a8 = 0 like the board itself
+const PAWN_TABLE: [isize; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+const KNIGHT_TABLE: [isize; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+const BISHOP_TABLE: [isize; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+const ROOK_TABLE: [isize; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+const QUEEN_TABLE: [isize; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+const KING_TABLE: [isize; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+pub fn piece_value(piece: Piece) -> isize {
+    match piece {
+        Piece::PAWN => PAWN_VALUE,
+        Piece::KNIGHT => KNIGHT_VALUE,
+        Piece::BISHOP => BISHOP_VALUE,
+        Piece::ROOK => ROOK_VALUE,
+        Piece::QUEEN => QUEEN_VALUE,
+        Piece::KING | Piece::EMPTY => 0,
+    }
+}
+
+pub trait Evaluation {
+    /// Static evaluation in centipawns from the point of view of the side to move, with the
+    /// pawn structure looked up in `pawns` before working it out.
+    fn evaluate(&self, pawns: &mut PawnTable) -> isize;
+}
+
+impl Evaluation for Board {
+    fn evaluate(&self, pawns: &mut PawnTable) -> isize {
+        let material = Material::new(self);
+        if let Some(score) = evaluate_endgame(self, &material) {
+            return if self.next == Color::WHITE { score } else { -score };
+        }
+
+        let structure = pawns.probe(self);
+        let mut score = structure.score + passed_pawns(self, &structure) + activity(self, &structure) + king_safety(self, &structure);
+
+        for index in 0..64 {
+            let piece = self.pieces[index];
+            if piece == Piece::EMPTY {
+                continue;
+            }
+
+            // black reads the tables upside down
+            let field = if self.colors[index] == Color::WHITE { index } else { index ^ 56 };
+            let positional = match piece {
+                Piece::PAWN => PAWN_TABLE[field],
+                Piece::KNIGHT => KNIGHT_TABLE[field],
+                Piece::BISHOP => BISHOP_TABLE[field],
+                Piece::ROOK => ROOK_TABLE[field],
+                Piece::QUEEN => QUEEN_TABLE[field],
+                Piece::KING => KING_TABLE[field],
+                Piece::EMPTY => 0,
+            };
+
+            let value = piece_value(piece) + positional;
+            score += if self.colors[index] == Color::WHITE { value } else { -value };
+        }
+
+        // pieces in a Crazyhouse pocket can go anywhere
+        for (piece, (white, black)) in POCKET_PIECES.iter().zip(self.pockets[0].iter().zip(self.pockets[1])) {
+            score += piece_value(*piece) * (*white as isize - black as isize);
+        }
+
+        // drawish material leaves the side ahead with less of its advantage
+        let strong = if score > 0 { Color::WHITE } else { Color::BLACK };
+        let score = score * scale_factor(self, &material, &structure, strong) / SCALE_NORMAL;
+
+        if self.next == Color::WHITE { score } else { -score }
+    }
+}
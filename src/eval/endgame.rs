@@ -0,0 +1,204 @@
+use crate::board::color::{color_index, inverse_color};
+use crate::board::generator::MoveGenerator;
+use crate::board::piece::Piece;
+use crate::board::variant::Variant;
+use crate::eval::pawns::{ahead, distance, relative_rank, PawnEntry, FILE_A};
+use crate::eval::{kpk, piece_value, BISHOP_VALUE, KNIGHT_VALUE, PAWN_VALUE, ROOK_VALUE};
+use crate::{Board, Color};
+
+/// Far above any positional advantage, but below the scores of tablebase wins and mates.
+pub const KNOWN_WIN: isize = 10_000;
+/// The scale factor that keeps an evaluation as it is.
+pub const SCALE_NORMAL: isize = 64;
+
+// for each step the lone king is from the centre or the right corner, and the kings are closer together
+const PUSH_TO_EDGE: isize = 20;
+const PUSH_TO_CORNER: isize = 30;
+const PUSH_CLOSE: isize = 10;
+// opposite-colored bishops on their own, with that much more for each passed pawn
+const OPPOSITE_BISHOPS: isize = 16;
+const OPPOSITE_BISHOPS_PASSED: isize = 8;
+const OPPOSITE_BISHOPS_WITH_PIECES: isize = 48;
+// without pawns and less than a rook ahead, depending on what the other side has left
+const NO_PAWNS_MINOR: isize = 4;
+const NO_PAWNS: isize = 14;
+
+/// How many pieces of each kind the colors have, by color index and piece.
+pub struct Material {
+    counts: [[usize; 6]; 2],
+    // everything but pawns and kings
+    pieces: [isize; 2],
+}
+
+impl Material {
+    pub fn new(board: &Board) -> Material {
+        let mut material = Material { counts: [[0; 6]; 2], pieces: [0; 2] };
+        for index in (0..64).filter(|index| board.pieces[*index] != Piece::EMPTY) {
+            let (piece, color) = (board.pieces[index], color_index(board.colors[index]));
+            material.counts[color][piece as usize] += 1;
+            if piece != Piece::PAWN {
+                material.pieces[color] += piece_value(piece);
+            }
+        }
+        material
+    }
+
+    fn count(&self, color: Color, piece: Piece) -> usize {
+        self.counts[color_index(color)][piece as usize]
+    }
+
+    fn pieces(&self, color: Color) -> isize {
+        self.pieces[color_index(color)]
+    }
+
+    // nothing but the king
+    fn is_bare(&self, color: Color) -> bool {
+        self.pieces(color) == 0 && self.count(color, Piece::PAWN) == 0
+    }
+}
+
+fn find(board: &Board, color: Color, piece: Piece) -> impl Iterator<Item = usize> + '_ {
+    (0..64).filter(move |index| board.pieces[*index] == piece && board.colors[*index] == color)
+}
+
+fn is_dark(index: usize) -> bool {
+    (index / 8 + index % 8) % 2 == 1
+}
+
+// steps from the four centre squares, 0 to 6
+fn edge(index: usize) -> isize {
+    let (row, file) = ((index / 8) as isize, (index % 8) as isize);
+    (3 - row).max(row - 4) + (3 - file).max(file - 4)
+}
+
+// drives the lone king to the edge with the other king coming closer, which mates with a queen or a rook
+fn mate_on_edge(material: &Material, strong: Color, strong_king: usize, weak_king: usize) -> isize {
+    KNOWN_WIN + material.pieces(strong) + PAWN_VALUE * material.count(strong, Piece::PAWN) as isize + PUSH_TO_EDGE * edge(weak_king) + PUSH_CLOSE * (7 - distance(strong_king, weak_king) as isize)
+}
+
+// a bishop and a knight only mate in a corner the bishop can reach
+fn bishop_and_knight(board: &Board, strong: Color, strong_king: usize, weak_king: usize) -> isize {
+    let bishop = find(board, strong, Piece::BISHOP).next().unwrap();
+    let corners = if is_dark(bishop) { [56, 7] } else { [0, 63] };
+    let corner = corners.iter().map(|corner| distance(weak_king, *corner)).min().unwrap() as isize;
+    KNOWN_WIN + BISHOP_VALUE + KNIGHT_VALUE + PUSH_TO_CORNER * (7 - corner) + PUSH_CLOSE * (7 - distance(strong_king, weak_king) as isize)
+}
+
+fn king_and_pawn(board: &Board, strong: Color, strong_king: usize, weak_king: usize) -> isize {
+    let pawn = find(board, strong, Piece::PAWN).next().unwrap();
+    if kpk::probe(strong, strong_king, pawn, weak_king, board.next) {
+        KNOWN_WIN + PAWN_VALUE + PUSH_CLOSE * relative_rank(pawn, strong) as isize
+    } else {
+        0
+    }
+}
+
+// a rook against a pawn is won if the king gets in front of the pawn or the other king is too far
+// away to help it, otherwise it is about how close the kings are to the pawn's way forward
+fn rook_against_pawn(board: &Board, strong: Color, strong_king: usize, weak_king: usize) -> isize {
+    let weak = inverse_color(strong);
+    let rook = find(board, strong, Piece::ROOK).next().unwrap();
+    let pawn = find(board, weak, Piece::PAWN).next().unwrap();
+    let file = FILE_A << (pawn % 8);
+    let queening = if weak == Color::WHITE { pawn % 8 } else { 56 + pawn % 8 };
+    let push = if weak == Color::WHITE { pawn - 8 } else { pawn + 8 };
+    let tempo = |color: Color| usize::from(board.next == color);
+
+    let in_front = file & ahead(pawn, weak) & 1 << strong_king != 0;
+    if in_front || (distance(weak_king, pawn) >= 3 + tempo(weak) && distance(weak_king, rook) >= 3) {
+        ROOK_VALUE - distance(strong_king, pawn) as isize
+    } else if relative_rank(weak_king, strong) <= 2 && distance(weak_king, pawn) == 1 && relative_rank(strong_king, strong) >= 3 && distance(strong_king, pawn) > 2 + tempo(strong) {
+        80 - 8 * distance(strong_king, pawn) as isize
+    } else {
+        200 - 8 * (distance(strong_king, push) as isize - distance(weak_king, push) as isize - distance(pawn, queening) as isize)
+    }
+}
+
+/// The evaluation of an endgame whose outcome is known, from white's point of view: king and
+/// pawn against king from a bitbase, mates with a queen or a rook or with a bishop and a knight,
+/// and a rook against a pawn.
+pub fn evaluate_endgame(board: &Board, material: &Material) -> Option<isize> {
+    if board.variant != Variant::STANDARD {
+        return None;
+    }
+
+    for strong in [Color::WHITE, Color::BLACK] {
+        let weak = inverse_color(strong);
+        let (strong_king, weak_king) = (board.king_position(strong), board.king_position(weak));
+        if strong_king < 0 || weak_king < 0 {
+            return None;
+        }
+        let (strong_king, weak_king) = (strong_king as usize, weak_king as usize);
+        let pawns = material.count(strong, Piece::PAWN);
+
+        let value = if material.is_bare(weak) {
+            if material.count(strong, Piece::QUEEN) > 0 || material.count(strong, Piece::ROOK) > 0 {
+                mate_on_edge(material, strong, strong_king, weak_king)
+            } else if pawns == 0 && material.pieces(strong) == BISHOP_VALUE + KNIGHT_VALUE && material.count(strong, Piece::BISHOP) == 1 {
+                bishop_and_knight(board, strong, strong_king, weak_king)
+            } else if pawns == 1 && material.pieces(strong) == 0 {
+                king_and_pawn(board, strong, strong_king, weak_king)
+            } else {
+                continue;
+            }
+        } else if pawns == 0 && material.pieces(strong) == ROOK_VALUE && material.count(strong, Piece::ROOK) == 1 && material.pieces(weak) == 0 && material.count(weak, Piece::PAWN) == 1 {
+            rook_against_pawn(board, strong, strong_king, weak_king)
+        } else {
+            continue;
+        };
+        return Some(if strong == Color::WHITE { value } else { -value });
+    }
+    None
+}
+
+/// How much of its advantage, out of `SCALE_NORMAL`, the side ahead can expect to keep: none with
+/// a bishop that does not control the queening square of its rook pawns, or without pawns and
+/// with too little to mate, and not much with opposite-colored bishops.
+pub fn scale_factor(board: &Board, material: &Material, structure: &PawnEntry, strong: Color) -> isize {
+    if board.variant != Variant::STANDARD {
+        return SCALE_NORMAL;
+    }
+    let weak = inverse_color(strong);
+    let pawns = structure.pawns[color_index(strong)];
+
+    if pawns == 0 {
+        // two knights cannot force a mate either
+        if material.is_bare(weak) && material.pieces(strong) == 2 * KNIGHT_VALUE && material.count(strong, Piece::KNIGHT) == 2 {
+            return 0;
+        }
+        if material.pieces(strong) - material.pieces(weak) <= BISHOP_VALUE {
+            return if material.pieces(strong) < ROOK_VALUE {
+                0
+            } else if material.pieces(weak) <= BISHOP_VALUE {
+                NO_PAWNS_MINOR
+            } else {
+                NO_PAWNS
+            };
+        }
+    }
+
+    if pawns != 0 && material.pieces(strong) == BISHOP_VALUE && material.count(strong, Piece::BISHOP) == 1 && material.pieces(weak) == 0 {
+        // all pawns on a rook file the bishop cannot help to queen on, with the king in the corner
+        let file = if pawns & !FILE_A == 0 { Some(0) } else if pawns & !(FILE_A << 7) == 0 { Some(7) } else { None };
+        if let Some(file) = file {
+            let bishop = find(board, strong, Piece::BISHOP).next().unwrap();
+            let queening = if strong == Color::WHITE { file } else { 56 + file };
+            let king = board.king_position(weak);
+            if is_dark(bishop) != is_dark(queening) && king > -1 && distance(king as usize, queening) <= 1 {
+                return 0;
+            }
+        }
+    }
+
+    if material.count(strong, Piece::BISHOP) == 1 && material.count(weak, Piece::BISHOP) == 1 {
+        let bishops = [strong, weak].map(|color| find(board, color, Piece::BISHOP).next().unwrap());
+        if is_dark(bishops[0]) != is_dark(bishops[1]) {
+            if material.pieces(strong) == BISHOP_VALUE && material.pieces(weak) == BISHOP_VALUE {
+                let passed = structure.passed[color_index(strong)].count_ones() as isize;
+                return (OPPOSITE_BISHOPS + OPPOSITE_BISHOPS_PASSED * passed).min(SCALE_NORMAL);
+            }
+            return OPPOSITE_BISHOPS_WITH_PIECES;
+        }
+    }
+    SCALE_NORMAL
+}
@@ -0,0 +1,101 @@
+use crate::board::color::{color_index, inverse_color};
+use crate::board::generator::MoveGenerator;
+use crate::board::piece::Piece;
+use crate::board::variant::Variant;
+use crate::eval::pawns::{adjacent_files, relative_rank, row, PawnEntry, FILE_A};
+use crate::eval::pieces::attacks;
+use crate::{Board, Color};
+
+// how much an enemy piece reaching into the king zone counts, by piece
+fn attack_weight(piece: Piece) -> isize {
+    match piece {
+        Piece::KNIGHT | Piece::BISHOP => 2,
+        Piece::ROOK => 3,
+        Piece::QUEEN => 5,
+        _ => 0,
+    }
+}
+
+// for each pawn in front of the king, on the first and the second rank ahead of it
+const SHIELD: [isize; 2] = [12, 6];
+// for each enemy pawn coming up the files around the king, by its rank from the king's side
+const STORM: [isize; 8] = [0, 0, 20, 12, 6, 0, 0, 0];
+const SEMI_OPEN_FILE: isize = 12;
+const OPEN_FILE: isize = 20;
+// the material of the enemy, in minor pieces, at which the king is fully exposed
+const FULL_ATTACK: isize = 12;
+
+// the king's square and those around it, whoever stands on them
+fn zone(king: usize) -> u64 {
+    let (row, file) = (king / 8, king % 8);
+    let rows = row.saturating_sub(1)..=(row + 1).min(7);
+    rows.flat_map(|row| (file.saturating_sub(1)..=(file + 1).min(7)).map(move |file| row * 8 + file)).fold(0, |set, index| set | 1 << index)
+}
+
+/// How safe both kings are, from white's point of view. Each king loses for the enemy pieces
+/// reaching into the squares around it, for enemy pawns storming towards it and for open files
+/// next to it, and gains for the pawns shielding it, the less so the fewer pieces the enemy has left.
+pub fn king_safety(board: &Board, structure: &PawnEntry) -> isize {
+    // kings cannot be checked there, or are no more than another piece
+    if matches!(board.variant, Variant::ANTICHESS | Variant::RACINGKINGS) {
+        return 0;
+    }
+
+    let mut score = 0;
+    for color in [Color::WHITE, Color::BLACK] {
+        let king = board.king_position(color);
+        if king < 0 {
+            continue;
+        }
+        let king = king as usize;
+        let enemy = inverse_color(color);
+        let zone = zone(king);
+
+        let mut attackers = 0;
+        let mut weight = 0;
+        let mut material = 0;
+        for index in (0..64).filter(|index| board.colors[*index] == enemy) {
+            let piece = board.pieces[index];
+            material += match piece {
+                Piece::KNIGHT | Piece::BISHOP => 1,
+                Piece::ROOK => 2,
+                Piece::QUEEN => 4,
+                _ => 0,
+            };
+            if attack_weight(piece) > 0 && attacks(board, index) & zone != 0 {
+                attackers += 1;
+                weight += attack_weight(piece);
+            }
+        }
+        // a single attacker rarely gets anywhere
+        let mut danger = if attackers >= 2 { weight * weight / 2 } else { 0 };
+
+        // pawns only shield a king that stays on its first two ranks
+        let own_pawns = structure.pawns[color_index(color)];
+        let enemy_pawns = structure.pawns[color_index(enemy)];
+        let files = FILE_A << (king % 8) | adjacent_files(king % 8);
+        if relative_rank(king, color) <= 1 {
+            for (distance, bonus) in SHIELD.iter().enumerate() {
+                let shield_row = if color == Color::WHITE { (king / 8).checked_sub(distance + 1) } else { Some(king / 8 + distance + 1) };
+                if let Some(shield_row) = shield_row {
+                    danger -= bonus * (own_pawns & files & row(shield_row)).count_ones() as isize;
+                }
+            }
+        }
+        for index in (0..64).filter(|index| enemy_pawns & files & 1 << index != 0) {
+            danger += STORM[relative_rank(index, color)];
+        }
+        for file in (0..8).filter(|file| files & FILE_A << file != 0) {
+            let file = FILE_A << file;
+            if (own_pawns | enemy_pawns) & file == 0 {
+                danger += OPEN_FILE;
+            } else if own_pawns & file == 0 {
+                danger += SEMI_OPEN_FILE;
+            }
+        }
+
+        let danger = danger * material.min(FULL_ATTACK) / FULL_ATTACK;
+        score += if color == Color::WHITE { -danger } else { danger };
+    }
+    score
+}
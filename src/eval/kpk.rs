@@ -0,0 +1,136 @@
+use std::sync::OnceLock;
+use crate::eval::pawns::distance;
+use crate::Color;
+
+// white to move or not, both kings anywhere, the pawn on files a to d of the second to seventh rank
+const SIZE: usize = 2 * 64 * 64 * 24;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    INVALID,
+    UNKNOWN,
+    DRAW,
+    WIN,
+}
+
+// positions are seen with white as the side with the pawn, a8 = 0 like the board
+fn index(white_to_move: bool, white_king: usize, black_king: usize, pawn: usize) -> usize {
+    let pawn = (pawn / 8 - 1) * 4 + pawn % 8;
+    usize::from(!white_to_move) + 2 * (white_king + 64 * (black_king + 64 * pawn))
+}
+
+fn decode(index: usize) -> (bool, usize, usize, usize) {
+    let pawn = index / (2 * 64 * 64);
+    (index.is_multiple_of(2), index / 2 % 64, index / 128 % 64, (pawn / 4 + 1) * 8 + pawn % 4)
+}
+
+fn neighbours(square: usize) -> impl Iterator<Item = usize> {
+    (0..64).filter(move |other| *other != square && distance(square, *other) == 1)
+}
+
+fn pawn_attacks(pawn: usize, square: usize) -> bool {
+    square / 8 + 1 == pawn / 8 && (square % 8).abs_diff(pawn % 8) == 1
+}
+
+fn initial(white_to_move: bool, white_king: usize, black_king: usize, pawn: usize) -> Class {
+    if white_king == black_king || white_king == pawn || black_king == pawn || distance(white_king, black_king) <= 1 {
+        return Class::INVALID;
+    }
+    if white_to_move {
+        if pawn_attacks(pawn, black_king) {
+            return Class::INVALID;
+        }
+        // the pawn queens and cannot be taken right away
+        let queen = pawn - 8;
+        if pawn / 8 == 1 && queen != white_king && queen != black_king && (distance(black_king, queen) > 1 || distance(white_king, queen) == 1) {
+            return Class::WIN;
+        }
+        return Class::UNKNOWN;
+    }
+
+    let escapes = neighbours(black_king).filter(|square| distance(*square, white_king) > 1 && !pawn_attacks(pawn, *square)).count();
+    if escapes == 0 {
+        return if pawn_attacks(pawn, black_king) { Class::WIN } else { Class::DRAW };
+    }
+    if distance(black_king, pawn) == 1 && distance(white_king, pawn) > 1 {
+        return Class::DRAW;
+    }
+    Class::UNKNOWN
+}
+
+fn classify(table: &[Class], position: usize) -> Class {
+    let (white_to_move, white_king, black_king, pawn) = decode(position);
+    let successors: Vec<Class> = if white_to_move {
+        let mut successors: Vec<Class> = neighbours(white_king).map(|square| table[index(false, square, black_king, pawn)]).collect();
+        // pushes to the last rank were looked at when the table was set up
+        let push = pawn - 8;
+        if pawn / 8 > 1 && push != white_king && push != black_king {
+            successors.push(table[index(false, white_king, black_king, push)]);
+            if pawn / 8 == 6 && push - 8 != white_king && push - 8 != black_king {
+                successors.push(table[index(false, white_king, black_king, push - 8)]);
+            }
+        }
+        successors
+    } else {
+        neighbours(black_king).map(|square| table[index(true, white_king, square, pawn)]).collect()
+    };
+
+    let mut successors = successors.into_iter().filter(|class| *class != Class::INVALID).peekable();
+    if successors.peek().is_none() {
+        return Class::DRAW;
+    }
+    let (good, bad) = if white_to_move { (Class::WIN, Class::DRAW) } else { (Class::DRAW, Class::WIN) };
+    let mut all_bad = true;
+    for class in successors {
+        if class == good {
+            return good;
+        }
+        all_bad &= class == bad;
+    }
+    if all_bad { bad } else { Class::UNKNOWN }
+}
+
+// a bit for each position won for white, worked out backwards until nothing changes any more
+fn bitbase() -> &'static Vec<u64> {
+    static BITBASE: OnceLock<Vec<u64>> = OnceLock::new();
+    BITBASE.get_or_init(|| {
+        let mut table: Vec<Class> = (0..SIZE)
+            .map(|index| {
+                let (white_to_move, white_king, black_king, pawn) = decode(index);
+                initial(white_to_move, white_king, black_king, pawn)
+            })
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..SIZE {
+                if table[index] == Class::UNKNOWN {
+                    table[index] = classify(&table, index);
+                    changed |= table[index] != Class::UNKNOWN;
+                }
+            }
+        }
+
+        let mut bits = vec![0u64; SIZE / 64];
+        for (index, class) in table.iter().enumerate() {
+            if *class == Class::WIN {
+                bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+        bits
+    })
+}
+
+/// Whether the side with the pawn wins king and pawn against king. The table is built the first
+/// time it is asked.
+pub fn probe(strong: Color, strong_king: usize, pawn: usize, weak_king: usize, next: Color) -> bool {
+    // looked at from white's side, with the pawn on the left half of the board
+    let flip = |square: usize| {
+        let square = if strong == Color::WHITE { square } else { square ^ 56 };
+        if pawn % 8 > 3 { square ^ 7 } else { square }
+    };
+    let index = index(next == strong, flip(strong_king), flip(weak_king), flip(pawn));
+    bitbase()[index / 64] & 1 << (index % 64) != 0
+}
@@ -0,0 +1,197 @@
+use crate::board::color::{color_index, inverse_color};
+use crate::board::piece::Piece;
+use crate::board::zobrist::Zobrist;
+use crate::{Board, Color};
+
+/// Entries of the pawn table unless asked for another number.
+pub const DEFAULT_ENTRIES: usize = 1 << 14;
+
+// by rank counted from the pawn's own side, so a pawn that has not moved is at index 1
+const PASSED: [isize; 8] = [0, 5, 10, 20, 35, 60, 100, 0];
+const CONNECTED: [isize; 8] = [0, 3, 5, 8, 12, 20, 30, 0];
+const ISOLATED: isize = 15;
+const DOUBLED: isize = 12;
+const BACKWARD: isize = 10;
+// for each group of pawns beyond the first
+const ISLAND: isize = 8;
+// a passed pawn the king cannot catch in a pawn ending all but queens
+const UNSTOPPABLE: isize = 600;
+
+pub const FILE_A: u64 = 0x0101_0101_0101_0101;
+const FILE_H: u64 = FILE_A << 7;
+
+/// What the pawns alone are worth, from white's point of view, and where the pawns and the
+/// passed pawns are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PawnEntry {
+    key: u64,
+    pub score: isize,
+    // by color index, a bit for each square
+    pub pawns: [u64; 2],
+    pub passed: [u64; 2],
+}
+
+/// A cache of pawn structure evaluations indexed by the pawn hash. The pawns change far less
+/// often than the pieces, so most lookups during a search find their structure here.
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnTable {
+    /// A table without entries caches nothing.
+    pub fn new(entries: usize) -> PawnTable {
+        PawnTable { entries: vec![PawnEntry::default(); entries] }
+    }
+
+    pub fn probe(&mut self, board: &Board) -> PawnEntry {
+        if self.entries.is_empty() {
+            return structure(board);
+        }
+        // without pawns the hash is 0, which the empty entries already evaluate correctly
+        let key = board.pawn_hash();
+        let slot = (key % self.entries.len() as u64) as usize;
+        if self.entries[slot].key != key {
+            self.entries[slot] = structure(board);
+        }
+        self.entries[slot]
+    }
+}
+
+fn pawns(board: &Board, color: Color) -> u64 {
+    (0..64)
+        .filter(|index| board.pieces[*index] == Piece::PAWN && board.colors[*index] == color)
+        .fold(0, |set, index| set | 1 << index)
+}
+
+pub fn squares(mut set: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (set != 0).then(|| {
+            let index = set.trailing_zeros() as usize;
+            set &= set - 1;
+            index
+        })
+    })
+}
+
+// counted from the side's own back rank
+pub fn relative_rank(index: usize, color: Color) -> usize {
+    if color == Color::WHITE { 7 - index / 8 } else { index / 8 }
+}
+
+pub fn row(row: usize) -> u64 {
+    if row < 8 { 0xFF << (8 * row) } else { 0 }
+}
+
+pub fn adjacent_files(file: usize) -> u64 {
+    let left = if file > 0 { FILE_A << (file - 1) } else { 0 };
+    let right = if file < 7 { FILE_A << (file + 1) } else { 0 };
+    left | right
+}
+
+// the rows in front of a square, seen from the side moving up the board
+pub fn ahead(index: usize, color: Color) -> u64 {
+    if color == Color::WHITE {
+        (1u64 << (8 * (index / 8))) - 1
+    } else {
+        u64::MAX.checked_shl(8 * (index as u32 / 8 + 1)).unwrap_or(0)
+    }
+}
+
+fn forward(index: usize, color: Color) -> usize {
+    if color == Color::WHITE { index - 8 } else { index + 8 }
+}
+
+/// The squares the pawns of a color attack.
+pub fn pawn_attacks(pawns: u64, color: Color) -> u64 {
+    if color == Color::WHITE {
+        (pawns & !FILE_A) >> 9 | (pawns & !FILE_H) >> 7
+    } else {
+        (pawns & !FILE_A) << 7 | (pawns & !FILE_H) << 9
+    }
+}
+
+fn structure(board: &Board) -> PawnEntry {
+    let mut entry = PawnEntry { key: board.pawn_hash(), ..Default::default() };
+    for color in [Color::WHITE, Color::BLACK] {
+        let own = pawns(board, color);
+        let enemy = pawns(board, inverse_color(color));
+        entry.pawns[color_index(color)] = own;
+        let mut score = 0;
+
+        for index in squares(own) {
+            let (file, rank) = (index % 8, relative_rank(index, color));
+            let adjacent = adjacent_files(file);
+            let in_front = ahead(index, color);
+
+            if enemy & in_front & (adjacent | FILE_A << file) == 0 {
+                score += PASSED[rank];
+                entry.passed[color_index(color)] |= 1 << index;
+            }
+
+            let isolated = own & adjacent == 0;
+            if isolated {
+                score -= ISOLATED;
+            }
+
+            // side by side with or defended by another pawn, Horde has white pawns on the first rank
+            let behind = if color == Color::WHITE { index / 8 + 1 } else { (index / 8).wrapping_sub(1) };
+            if own & adjacent & (row(index / 8) | row(behind)) != 0 {
+                score += CONNECTED[rank];
+            } else if !isolated && own & adjacent & !in_front == 0 && rank < 6 {
+                // the pawns beside it have all gone ahead, and an enemy pawn stops it from following
+                let stop = forward(index, color);
+                if enemy & adjacent & row(forward(stop, color) / 8) != 0 {
+                    score -= BACKWARD;
+                }
+            }
+        }
+
+        for file in 0..8 {
+            let count = (own & FILE_A << file).count_ones() as isize;
+            score -= DOUBLED * (count - 1).max(0);
+        }
+        let files = (0..8).filter(|file| own & FILE_A << file != 0).fold(0u8, |files, file| files | 1 << file);
+        let islands = (files & !(files << 1)).count_ones() as isize;
+        score -= ISLAND * (islands - 1).max(0);
+
+        entry.score += if color == Color::WHITE { score } else { -score };
+    }
+    entry
+}
+
+pub fn distance(a: usize, b: usize) -> usize {
+    (a / 8).abs_diff(b / 8).max((a % 8).abs_diff(b % 8))
+}
+
+/// What the passed pawns are worth beyond the pawn structure, from white's point of view: a
+/// blocked one is worth half as much, and one the king cannot catch in a pawn ending nearly a queen.
+pub fn passed_pawns(board: &Board, entry: &PawnEntry) -> isize {
+    let mut score = 0;
+    for color in [Color::WHITE, Color::BLACK] {
+        let enemy = inverse_color(color);
+        let king = (0..64).find(|index| board.pieces[*index] == Piece::KING && board.colors[*index] == enemy);
+        let pawn_ending = (0..64).all(|index| board.colors[index] != enemy || matches!(board.pieces[index], Piece::PAWN | Piece::KING | Piece::EMPTY));
+
+        for index in squares(entry.passed[color_index(color)]) {
+            let rank = relative_rank(index, color);
+            let mut value = 0;
+            if board.pieces[forward(index, color)] != Piece::EMPTY {
+                value -= PASSED[rank] / 2;
+            }
+
+            if let (Some(king), true) = (king, pawn_ending) {
+                let promotion = if color == Color::WHITE { index % 8 } else { 56 + index % 8 };
+                let path = (index / 8).min(promotion / 8)..=(index / 8).max(promotion / 8);
+                let clear = path.filter(|row| row * 8 + index % 8 != index).all(|row| board.pieces[row * 8 + index % 8] == Piece::EMPTY);
+                // the first move may go two squares, which saves a move from the first rank in Horde too
+                let moves = 7 - rank - usize::from(rank <= 1);
+                let tempo = usize::from(board.next == enemy);
+                if clear && distance(king, promotion) > moves + tempo {
+                    value += UNSTOPPABLE;
+                }
+            }
+            score += if color == Color::WHITE { value } else { -value };
+        }
+    }
+    score
+}
@@ -0,0 +1,98 @@
+use crate::board::color::{color_index, inverse_color};
+use crate::board::generator::MoveGenerator;
+use crate::board::piece::Piece;
+use crate::eval::pawns::{adjacent_files, ahead, pawn_attacks, relative_rank, row, PawnEntry, FILE_A};
+use crate::{Board, Color};
+
+// per square a piece can go to beyond the number it typically has, by piece
+const KNIGHT_MOBILITY: (isize, isize) = (4, 4);
+const BISHOP_MOBILITY: (isize, isize) = (7, 5);
+const ROOK_MOBILITY: (isize, isize) = (7, 2);
+const QUEEN_MOBILITY: (isize, isize) = (14, 1);
+
+const ROOK_OPEN_FILE: isize = 20;
+const ROOK_SEMI_OPEN_FILE: isize = 10;
+const ROOK_ON_SEVENTH: isize = 20;
+const KNIGHT_OUTPOST: isize = 20;
+const BISHOP_OUTPOST: isize = 10;
+const BISHOP_PAIR: isize = 30;
+
+/// The squares a piece can move to or capture on, from the moves the generator makes for it.
+pub fn attacks(board: &Board, index: usize) -> u64 {
+    let color = board.colors[index];
+    let moves = match board.pieces[index] {
+        Piece::KNIGHT => board.generate_knight_moves(index, color),
+        Piece::BISHOP => board.generate_bishop_moves(index, color),
+        Piece::ROOK => board.generate_rook_moves(index, color),
+        Piece::QUEEN => board.generate_queen_moves(index, color),
+        Piece::KING => board.generate_king_moves(index, color),
+        _ => Vec::new(),
+    };
+    moves.iter().fold(0, |set, m| set | 1 << m.to)
+}
+
+/// Mobility, rooks on open files and the seventh rank, outposts and the bishop pair, from
+/// white's point of view.
+pub fn activity(board: &Board, structure: &PawnEntry) -> isize {
+    let mut score = 0;
+    for color in [Color::WHITE, Color::BLACK] {
+        let enemy = inverse_color(color);
+        let own_pawns = structure.pawns[color_index(color)];
+        let enemy_pawns = structure.pawns[color_index(enemy)];
+        // squares an enemy pawn attacks are no place for a piece
+        let unsafe_squares = pawn_attacks(enemy_pawns, enemy);
+        let enemy_king = board.king_position(enemy);
+        let mut bishops = 0;
+        let mut value = 0;
+
+        for index in (0..64).filter(|index| board.colors[*index] == color) {
+            let piece = board.pieces[index];
+            let (typical, weight) = match piece {
+                Piece::KNIGHT => KNIGHT_MOBILITY,
+                Piece::BISHOP => BISHOP_MOBILITY,
+                Piece::ROOK => ROOK_MOBILITY,
+                Piece::QUEEN => QUEEN_MOBILITY,
+                _ => continue,
+            };
+            let mobility = (attacks(board, index) & !unsafe_squares).count_ones() as isize;
+            value += (mobility - typical) * weight;
+
+            let file = FILE_A << (index % 8);
+            let rank = relative_rank(index, color);
+            match piece {
+                Piece::ROOK => {
+                    if (own_pawns | enemy_pawns) & file == 0 {
+                        value += ROOK_OPEN_FILE;
+                    } else if own_pawns & file == 0 {
+                        value += ROOK_SEMI_OPEN_FILE;
+                    }
+                    // where it attacks the pawns at home or cuts off the king
+                    let enemy_home = if color == Color::WHITE { 1 } else { 6 };
+                    let king_home = enemy_king > -1 && relative_rank(enemy_king as usize, color) == 7;
+                    if rank == 6 && (enemy_pawns & row(enemy_home) != 0 || king_home) {
+                        value += ROOK_ON_SEVENTH;
+                    }
+                }
+                Piece::KNIGHT | Piece::BISHOP => {
+                    if piece == Piece::BISHOP {
+                        bishops += 1;
+                    }
+                    // on the fourth to sixth rank, defended by a pawn, and no enemy pawn can ever drive it away
+                    let defended = pawn_attacks(own_pawns, color) & 1 << index != 0;
+                    let chased = enemy_pawns & adjacent_files(index % 8) & ahead(index, color) != 0;
+                    if (3..=5).contains(&rank) && defended && !chased {
+                        value += if piece == Piece::KNIGHT { KNIGHT_OUTPOST } else { BISHOP_OUTPOST };
+                    }
+                }
+                _ => {}
+            }
+        }
+        if bishops >= 2 {
+            value += BISHOP_PAIR;
+        }
+
+        score += if color == Color::WHITE { value } else { -value };
+    }
+    score
+}
+
@@ -0,0 +1,181 @@
+use crate::board::fen::Fen;
+use crate::board::variant::Variant;
+use crate::board::zobrist::Zobrist;
+use crate::eval::endgame::KNOWN_WIN;
+use crate::eval::king::king_safety;
+use crate::eval::pawns::{passed_pawns, PawnTable};
+use crate::eval::pieces::activity;
+use crate::eval::Evaluation;
+use crate::Board;
+
+fn structure(fen: &str) -> isize {
+    PawnTable::new(0).probe(&Board::from_fen(fen)).score
+}
+
+#[test]
+fn test_pawn_hash() {
+    let board = Board::from_fen("4k3/8/8/3p4/8/8/3P4/4K3 w - - 0 1");
+    assert_eq!(board.pawn_hash(), Board::from_fen("r3k3/8/8/3p4/8/2N5/3P4/1Q4K1 b - - 0 1").pawn_hash());
+    assert_ne!(board.pawn_hash(), Board::from_fen("4k3/8/8/3p4/8/3P4/8/4K3 w - - 0 1").pawn_hash());
+    assert_ne!(board.pawn_hash(), Board::from_fen("4k3/8/8/3P4/8/8/3p4/4K3 w - - 0 1").pawn_hash());
+    assert_eq!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").pawn_hash(), 0);
+}
+
+#[test]
+fn test_pawn_structure() {
+    // a lone pawn is passed but isolated
+    assert_eq!(structure("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1"), 5 - 15);
+    // doubled on top of that
+    assert_eq!(structure("4k3/8/8/8/8/3P4/3P4/4K3 w - - 0 1"), 5 + 10 - 2 * 15 - 12);
+    // side by side they guard each other
+    assert_eq!(structure("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1"), 2 * 5 + 2 * 3);
+    // two islands of isolated pawns
+    assert_eq!(structure("4k3/8/8/8/8/8/P6P/4K3 w - - 0 1"), 2 * 5 - 2 * 15 - 8);
+    // d2 is left behind by c3 and cannot advance past e4, which is isolated itself
+    assert_eq!(structure("4k3/8/8/8/4p3/2P5/3P4/4K3 w - - 0 1"), (10 + 5 - 10) + 15);
+    // the same structure with colors reversed scores the other way
+    assert_eq!(structure("4k3/3p4/2p5/4P3/8/8/8/4K3 w - - 0 1"), -20);
+}
+
+#[test]
+fn test_passed_pawns() {
+    let board = Board::from_fen("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1");
+    let entry = PawnTable::new(0).probe(&board);
+    assert_eq!(entry.passed, [1 << 27, 0]);
+    // an enemy pawn on an adjacent file in front stops it from being passed
+    let entry = PawnTable::new(0).probe(&Board::from_fen("4k3/4p3/8/3P4/8/8/8/4K3 w - - 0 1"));
+    assert_eq!(entry.passed, [0, 0]);
+
+    // a blocked passed pawn is worth half
+    let board = Board::from_fen("4k3/8/3n4/3P4/8/8/8/4K3 w - - 0 1");
+    assert_eq!(passed_pawns(&board, &PawnTable::new(0).probe(&board)), -35 / 2);
+}
+
+#[test]
+fn test_unstoppable_pawn() {
+    let board = Board::from_fen("8/8/8/P7/8/8/8/4K2k w - - 0 1");
+    assert!(passed_pawns(&board, &PawnTable::new(0).probe(&board)) > 500);
+    // the king is in the square of the pawn
+    let board = Board::from_fen("8/8/8/P7/3k4/8/8/4K3 b - - 0 1");
+    assert_eq!(passed_pawns(&board, &PawnTable::new(0).probe(&board)), 0);
+    // with a piece left the king is not on its own
+    let board = Board::from_fen("8/8/8/P7/8/8/8/4K1nk w - - 0 1");
+    assert_eq!(passed_pawns(&board, &PawnTable::new(0).probe(&board)), 0);
+
+    // from the first rank in Horde the double step still leaves six moves to go
+    let board = Board { variant: Variant::HORDE, ..Board::from_fen("8/8/8/8/8/8/7k/P7 b - - 0 1") };
+    assert_eq!(passed_pawns(&board, &PawnTable::new(0).probe(&board)), 0);
+    let board = Board { variant: Variant::HORDE, ..Board::from_fen("8/8/8/8/8/8/7k/P7 w - - 0 1") };
+    assert!(passed_pawns(&board, &PawnTable::new(0).probe(&board)) > 500);
+}
+
+#[test]
+fn test_pawn_table() {
+    let mut table = PawnTable::new(64);
+    for fen in ["rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1", "4k3/8/8/8/4p3/2P5/3P4/4K3 w - - 0 1", "4k3/8/8/8/8/8/8/4K3 w - - 0 1"] {
+        let board = Board::from_fen(fen);
+        let uncached = board.evaluate(&mut PawnTable::new(0));
+        assert_eq!(board.evaluate(&mut table), uncached);
+        assert_eq!(board.evaluate(&mut table), uncached);
+    }
+}
+
+fn activity_of(fen: &str) -> isize {
+    let board = Board::from_fen(fen);
+    activity(&board, &PawnTable::new(0).probe(&board))
+}
+
+fn king_safety_of(fen: &str) -> isize {
+    let board = Board::from_fen(fen);
+    king_safety(&board, &PawnTable::new(0).probe(&board))
+}
+
+#[test]
+fn test_start_position_is_balanced() {
+    let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert_eq!(board.evaluate(&mut PawnTable::new(0)), 0);
+    assert_eq!(activity_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), 0);
+    assert_eq!(king_safety_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), 0);
+}
+
+#[test]
+fn test_piece_activity() {
+    // the bishops do not get into each other's way, so together they are only worth the pair more
+    let pair = activity_of("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1");
+    assert_eq!(pair - activity_of("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1") - activity_of("4k3/8/8/8/8/8/8/4KB2 w - - 0 1"), 30);
+
+    // an open file is worth 10 more than a semi-open one, and d8 is one more square to go to
+    assert_eq!(activity_of("4k3/8/8/8/8/8/8/3RK3 w - - 0 1") - activity_of("4k3/3p4/8/8/8/8/8/3RK3 w - - 0 1"), 10 + 2);
+
+    // c7 could drive the knight away and takes b6 from it
+    assert_eq!(activity_of("4k3/8/8/3N4/4P3/8/8/4K3 w - - 0 1") - activity_of("4k3/2p5/8/3N4/4P3/8/8/4K3 w - - 0 1"), 20 + 4);
+
+    // more squares to go to are better
+    assert!(activity_of("4k3/8/8/3Q4/8/8/8/4K3 w - - 0 1") > activity_of("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1"));
+}
+
+#[test]
+fn test_king_safety() {
+    // white keeps its shield while black has pushed its pawns away from the king
+    assert!(king_safety_of("r2q1rk1/ppp5/8/8/8/8/5PPP/R2Q1RK1 w - - 0 1") > 0);
+    // queen and rook bearing down on the king are worse than the same pieces far away
+    let attacked = king_safety_of("6k1/5ppp/8/8/8/6q1/5PPr/6K1 w - - 0 1");
+    assert!(attacked < king_safety_of("6k1/5ppp/q7/r7/8/8/5PP1/6K1 w - - 0 1"));
+    // without pieces left, the king does not need shelter
+    assert_eq!(king_safety_of("6k1/8/8/8/8/8/8/6K1 w - - 0 1"), 0);
+}
+
+fn evaluate(fen: &str) -> isize {
+    Board::from_fen(fen).evaluate(&mut PawnTable::new(0))
+}
+
+#[test]
+fn test_king_and_pawn() {
+    // in front of its pawn on the sixth rank the king wins whoever is to move
+    assert!(evaluate("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1") > KNOWN_WIN);
+    assert!(evaluate("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1") < -KNOWN_WIN);
+    // further back it takes the opposition to win
+    assert!(evaluate("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1") < -KNOWN_WIN);
+    assert_eq!(evaluate("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"), 0);
+    // the same for black, on the other half of the board
+    assert!(evaluate("8/8/8/3p4/3k4/8/3K4/8 w - - 0 1") < -KNOWN_WIN);
+    assert_eq!(evaluate("8/8/8/3p4/3k4/8/3K4/8 b - - 0 1"), 0);
+    // a rook pawn is a draw once the king reaches the corner
+    assert_eq!(evaluate("k7/8/8/8/P7/8/8/7K w - - 0 1"), 0);
+    // and a king far away cannot catch the pawn
+    assert!(evaluate("7k/8/8/8/P7/8/8/K7 w - - 0 1") > KNOWN_WIN);
+}
+
+#[test]
+fn test_mating_material() {
+    // the lone king is driven to the edge, and to the bishop's corner with a bishop and a knight
+    assert!(evaluate("8/8/8/3k4/8/8/8/QK6 w - - 0 1") > KNOWN_WIN);
+    assert!(evaluate("k7/8/8/8/8/8/8/QK6 w - - 0 1") > evaluate("8/8/8/3k4/8/8/8/QK6 w - - 0 1"));
+    assert!(evaluate("8/8/8/8/8/8/8/RK5k b - - 0 1") < -KNOWN_WIN);
+    assert!(evaluate("8/8/8/8/8/8/1K6/k1B1N3 w - - 0 1") > evaluate("k7/8/8/8/8/8/1K6/2B1N3 w - - 0 1"));
+
+    // a minor piece or two knights cannot mate
+    assert_eq!(evaluate("8/8/8/3k4/8/8/8/NK6 w - - 0 1"), 0);
+    assert_eq!(evaluate("8/8/8/3k4/8/8/8/NK4N1 w - - 0 1"), 0);
+    // a rook against a minor piece is mostly drawn
+    assert!(evaluate("8/8/8/3k4/8/8/2b5/RK6 w - - 0 1").abs() < 50);
+}
+
+#[test]
+fn test_rook_against_pawn() {
+    // the king in front of the pawn
+    assert!(evaluate("8/8/8/8/8/3p4/3K4/R5k1 b - - 0 1") < -400);
+    // the pawn is about to queen with its king, and the other king is far away
+    assert!(evaluate("K7/8/8/8/8/8/2kp4/7R w - - 0 1") < 100);
+}
+
+#[test]
+fn test_drawish_bishops() {
+    // a bishop of the wrong color for the rook pawn
+    assert_eq!(evaluate("k7/8/8/P7/8/8/8/2B1K3 w - - 0 1"), 0);
+    assert!(evaluate("k7/8/8/P7/8/8/8/1B2K3 w - - 0 1") > 200);
+
+    // a pawn up with opposite-colored bishops is far less than with bishops on the same color
+    let opposite = evaluate("4k3/5b2/8/8/3P4/8/8/2B1K3 w - - 0 1");
+    assert!(opposite > 0 && 2 * opposite < evaluate("4k3/4b3/8/8/3P4/8/8/2B1K3 w - - 0 1"));
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::variant::Outcome;
+use crate::board::zobrist::Zobrist;
+use crate::{Board, Color};
+
+pub(crate) mod problem;
+#[cfg(test)]
+mod tests;
+
+// proof and disproof numbers saturate here, which stands for infinity
+const INFINITE: u64 = u64::MAX / 4;
+
+/// The result of a mate search.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Proof {
+    /// A forced mate, the attacker's moves against the longest defence.
+    MATE(Vec<Move>),
+    /// The side to move cannot force mate.
+    NOMATE,
+    /// The node budget ran out first.
+    UNKNOWN,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    pn: u64,
+    dn: u64,
+    // length of the mate once proven
+    plies: usize,
+    // disproven only because the defence repeats a position on the current path
+    repetition: bool,
+}
+
+const UNSEARCHED: Entry = Entry { pn: 1, dn: 1, plies: 0, repetition: false };
+const PROVEN: Entry = Entry { pn: 0, dn: INFINITE, plies: 0, repetition: false };
+const DISPROVEN: Entry = Entry { pn: INFINITE, dn: 0, plies: 0, repetition: false };
+const REPEATED: Entry = Entry { repetition: true, ..DISPROVEN };
+
+fn key(hash: u64, plies: Option<usize>) -> u64 {
+    match plies {
+        Some(plies) => hash ^ (plies as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+        None => hash,
+    }
+}
+
+// folded into the key of a path, in any order since a repetition does not depend on it
+fn mix(hash: u64) -> u64 {
+    hash.wrapping_mul(0xD6E8_FEB8_6659_FD93)
+}
+
+// a disproof through a repetition holds for the path it was found on only, reusing it elsewhere
+// could miss a mate that goes through the repeated position
+fn on_path(key: u64, path_key: u64) -> u64 {
+    key ^ mix(path_key.wrapping_add(1)).rotate_left(32)
+}
+
+/// Depth-first proof-number search (df-pn) for a forced mate by the side to move. Unlike
+/// alpha-beta it needs no depth, it follows the moves that leave the defender the fewest
+/// replies, so long forcing lines are proven quickly.
+pub struct Solver {
+    pub nodes: usize,
+    max_nodes: usize,
+    // the node count at which the current search gives up
+    limit: usize,
+    attacker: Color,
+    table: HashMap<u64, Entry>,
+    // hashes of the positions from the root to the current node, repeating one is a draw
+    path: Vec<u64>,
+    // the path's positions folded together, which disproofs through a repetition are stored under
+    path_key: u64,
+}
+
+impl Solver {
+    pub fn new(max_nodes: usize) -> Solver {
+        Solver { nodes: 0, max_nodes, limit: max_nodes, attacker: Color::WHITE, table: HashMap::new(), path: Vec::new(), path_key: 0 }
+    }
+
+    /// Proves or disproves a forced mate, returning the shortest one found within the budget.
+    pub fn solve(&mut self, board: &Board) -> Proof {
+        self.nodes = 0;
+        // disproofs through repetitions are not shared between paths, which can make the search
+        // without a length slow, so it leaves half the budget to looking for ever longer mates
+        self.limit = self.max_nodes / 2;
+        let proof = self.search(board, None);
+        self.limit = self.max_nodes;
+        // proof-number search finds a mate, not the fastest one, so look for shorter ones
+        let longest = match &proof {
+            Proof::MATE(line) => line.len().div_ceil(2),
+            Proof::NOMATE => return proof,
+            Proof::UNKNOWN => usize::MAX,
+        };
+        for moves in 1..longest {
+            match self.search(board, Some(2 * moves - 1)) {
+                Proof::MATE(shorter) => return Proof::MATE(shorter),
+                Proof::UNKNOWN => break,
+                Proof::NOMATE => (),
+            }
+        }
+        proof
+    }
+
+    /// Proves or disproves a forced mate in at most the given number of moves.
+    pub fn solve_in(&mut self, board: &Board, moves: usize) -> Proof {
+        self.nodes = 0;
+        if moves == 0 {
+            return Proof::NOMATE;
+        }
+        self.limit = self.max_nodes;
+        self.search(board, Some(2 * moves - 1))
+    }
+
+    fn search(&mut self, board: &Board, plies: Option<usize>) -> Proof {
+        self.attacker = board.next;
+        self.table.clear();
+        self.mid(board, plies, INFINITE, INFINITE);
+
+        let root = self.entry(board.hash(), plies);
+        if root.pn == 0 {
+            Proof::MATE(self.line(board, plies, root.plies))
+        } else if root.dn == 0 {
+            Proof::NOMATE
+        } else {
+            Proof::UNKNOWN
+        }
+    }
+
+    // a position is stored once per number of plies left, proofs at one length don't carry over
+    fn entry(&self, hash: u64, plies: Option<usize>) -> Entry {
+        if self.path.contains(&hash) {
+            return REPEATED;
+        }
+        let key = key(hash, plies);
+        self.table.get(&on_path(key, self.path_key)).or_else(|| self.table.get(&key)).copied().unwrap_or(UNSEARCHED)
+    }
+
+    // stores a position reached by a path of the given key
+    fn store(&mut self, hash: u64, plies: Option<usize>, path_key: u64, entry: Entry) {
+        let key = key(hash, plies);
+        let on_path = on_path(key, path_key);
+        if entry.repetition {
+            self.table.insert(on_path, entry);
+        } else {
+            self.table.remove(&on_path);
+            self.table.insert(key, entry);
+        }
+    }
+
+    // proven or disproven without looking at the children
+    fn terminal(&self, board: &Board, moves: &[Move], plies: Option<usize>) -> Option<Entry> {
+        let attacking = board.next == self.attacker;
+        if let Some(outcome) = board.variant_outcome() {
+            let won = (attacking && outcome == Outcome::WIN) || (!attacking && outcome == Outcome::LOSS);
+            return Some(if won { PROVEN } else { DISPROVEN });
+        }
+        if moves.is_empty() {
+            return Some(if !attacking && board.in_check() { PROVEN } else { DISPROVEN });
+        }
+        if plies == Some(0) {
+            return Some(DISPROVEN);
+        }
+        None
+    }
+
+    // the attacker needs one proven move, the defender all of them
+    fn combine(&self, children: &[(Board, u64)], plies: Option<usize>, attacking: bool) -> Entry {
+        let entries: Vec<Entry> = children.iter().map(|(_, hash)| self.entry(*hash, plies)).collect();
+        let min_pn = entries.iter().map(|entry| entry.pn).min().unwrap();
+        let min_dn = entries.iter().map(|entry| entry.dn).min().unwrap();
+        let sum_pn = entries.iter().fold(0, |sum, entry| (sum + entry.pn).min(INFINITE));
+        let sum_dn = entries.iter().fold(0, |sum, entry| (sum + entry.dn).min(INFINITE));
+        let (pn, dn) = if attacking { (min_pn, sum_dn) } else { (sum_pn, min_dn) };
+
+        let proven = entries.iter().filter(|entry| entry.pn == 0).map(|entry| entry.plies);
+        let plies = if pn != 0 {
+            0
+        } else if attacking {
+            1 + proven.min().unwrap()
+        } else {
+            1 + proven.max().unwrap()
+        };
+        // the attacker is refuted by a repetition if one of its moves is, the defender only if
+        // each of its refutations is one
+        let mut disproven = entries.iter().filter(|entry| entry.dn == 0);
+        let repetition = dn == 0 && if attacking { disproven.any(|entry| entry.repetition) } else { disproven.all(|entry| entry.repetition) };
+        Entry { pn, dn, plies, repetition }
+    }
+
+    // searches the node until its proof or disproof number reaches its threshold
+    fn mid(&mut self, board: &Board, plies: Option<usize>, threshold_pn: u64, threshold_dn: u64) {
+        self.nodes += 1;
+        let hash = board.hash();
+        let moves = board.generate_legal();
+        if let Some(entry) = self.terminal(board, &moves, plies) {
+            self.store(hash, plies, self.path_key, entry);
+            return;
+        }
+        let remaining = plies.map(|plies| plies - 1);
+
+        let attacking = board.next == self.attacker;
+        let children: Vec<(Board, u64)> = moves
+            .into_iter()
+            .map(|m| {
+                let child = board.apply(m);
+                (child, child.hash())
+            })
+            .collect();
+
+        let path_key = self.path_key;
+        self.path.push(hash);
+        self.path_key ^= mix(hash);
+        loop {
+            let entry = self.combine(&children, remaining, attacking);
+            self.store(hash, plies, path_key, entry);
+            if entry.pn >= threshold_pn || entry.dn >= threshold_dn || self.nodes >= self.limit {
+                break;
+            }
+
+            // the most promising child, and how far it may go before its sibling takes over
+            let rank = |child: &Entry| if attacking { child.pn } else { child.dn };
+            let mut ranked: Vec<(usize, Entry)> = children
+                .iter()
+                .map(|(_, hash)| self.entry(*hash, remaining))
+                .enumerate()
+                .collect();
+            ranked.sort_by_key(|(_, child)| rank(child));
+            let (best, child) = ranked[0];
+            let second = ranked.get(1).map_or(INFINITE, |(_, sibling)| rank(sibling));
+            let (child_pn, child_dn) = if attacking {
+                (threshold_pn.min(second.saturating_add(1)), (threshold_dn - entry.dn + child.dn).min(INFINITE))
+            } else {
+                ((threshold_pn - entry.pn + child.pn).min(INFINITE), threshold_dn.min(second.saturating_add(1)))
+            };
+            self.mid(&children[best].0, remaining, child_pn, child_dn);
+        }
+        self.path.pop();
+        self.path_key = path_key;
+    }
+
+    // the shortest proven attack against the longest defence
+    fn line(&self, board: &Board, mut remaining: Option<usize>, length: usize) -> Vec<Move> {
+        let mut line = Vec::new();
+        let mut board = *board;
+        while line.len() < length {
+            remaining = remaining.map(|plies| plies - 1);
+            let attacking = board.next == self.attacker;
+            let proven = board
+                .generate_legal()
+                .into_iter()
+                .map(|m| (m, self.entry(board.apply(m).hash(), remaining)))
+                .filter(|(_, entry)| entry.pn == 0);
+            let next = if attacking {
+                proven.min_by_key(|(_, entry)| entry.plies)
+            } else {
+                proven.max_by_key(|(_, entry)| entry.plies)
+            };
+            let Some((m, _)) = next else { break };
+            line.push(m);
+            board = board.apply(m);
+        }
+        line
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::board::color::inverse_color;
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::san::San;
+use crate::Board;
+
+/// Who drives the play towards the goal.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    // the side to move forces the goal against any defence
+    DIRECT,
+    // both sides cooperate, the side to move is the one mated or stalemated
+    HELP,
+    // the side to move forces the other side to mate or stalemate it
+    SELF,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    MATE,
+    STALEMATE,
+}
+
+/// A problem stipulation like `#2`, `h#3`, `s#2` or `=3`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stipulation {
+    pub kind: Kind,
+    pub goal: Goal,
+    pub moves: usize,
+}
+
+impl FromStr for Stipulation {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, rest) = match s.as_bytes().first() {
+            Some(b'h') => (Kind::HELP, &s[1..]),
+            Some(b's') => (Kind::SELF, &s[1..]),
+            _ => (Kind::DIRECT, s),
+        };
+        let (goal, moves) = match rest.as_bytes().first() {
+            Some(b'#') => (Goal::MATE, &rest[1..]),
+            Some(b'=') => (Goal::STALEMATE, &rest[1..]),
+            _ => return Err(()),
+        };
+        let moves = moves.parse().map_err(|_| ())?;
+        if moves == 0 {
+            return Err(());
+        }
+        Ok(Stipulation { kind, goal, moves })
+    }
+}
+
+impl fmt::Display for Stipulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            Kind::DIRECT => "",
+            Kind::HELP => "h",
+            Kind::SELF => "s",
+        };
+        let goal = match self.goal {
+            Goal::MATE => "#",
+            Goal::STALEMATE => "=",
+        };
+        write!(f, "{}{}{}", kind, goal, self.moves)
+    }
+}
+
+/// A move of a solution and the play that follows it. After a move of the side fulfilling the
+/// stipulation the replies are all defences, after a defence they are the moves that still
+/// work, so more than one is a dual.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub m: Move,
+    // what would work if the defender could pass
+    pub threats: Vec<Line>,
+    pub replies: Vec<Line>,
+}
+
+impl Line {
+    fn new(m: Move, replies: Vec<Line>) -> Line {
+        Line { m, threats: Vec::new(), replies }
+    }
+}
+
+/// Solves a chess problem exhaustively, finding every solution so cooks and duals show up.
+pub struct Problem {
+    pub board: Board,
+    pub stipulation: Stipulation,
+}
+
+impl Problem {
+    pub fn new(board: Board, stipulation: Stipulation) -> Problem {
+        Problem { board, stipulation }
+    }
+
+    /// All solutions, for direct and selfmates the key moves with their variations.
+    pub fn solve(&self) -> Vec<Line> {
+        let moves = self.stipulation.moves;
+        match self.stipulation.kind {
+            Kind::HELP => self.help(&self.board, 2 * moves),
+            _ => self.attacks(&self.board, moves, true),
+        }
+    }
+
+    /// The number of solutions, for helpmates every complete line counts.
+    pub fn count(&self, solutions: &[Line]) -> usize {
+        fn leaves(line: &Line) -> usize {
+            if line.replies.is_empty() {
+                1
+            } else {
+                line.replies.iter().map(leaves).sum()
+            }
+        }
+        match self.stipulation.kind {
+            Kind::HELP => solutions.iter().map(leaves).sum(),
+            _ => solutions.len(),
+        }
+    }
+
+    /// More than one key in a direct or selfmate, where the composer intends a single one.
+    pub fn is_cooked(&self, solutions: &[Line]) -> bool {
+        self.stipulation.kind != Kind::HELP && solutions.len() > 1
+    }
+
+    /// The number of defences answered by more than one move.
+    pub fn duals(&self, solutions: &[Line]) -> usize {
+        fn count(line: &Line, defending: bool) -> usize {
+            let dual = usize::from(defending && line.replies.len() > 1);
+            dual + line.replies.iter().map(|reply| count(reply, !defending)).sum::<usize>()
+        }
+        match self.stipulation.kind {
+            Kind::HELP => 0,
+            _ => solutions.iter().map(|line| count(line, false)).sum(),
+        }
+    }
+
+    // the side to move has no moves left in the way the stipulation asks for
+    fn reached(&self, board: &Board) -> bool {
+        board.generate_legal().is_empty() && board.in_check() == (self.stipulation.goal == Goal::MATE)
+    }
+
+    // the attacker's moves that fulfill the stipulation within the given moves, with threats for key moves
+    fn attacks(&self, board: &Board, moves: usize, key: bool) -> Vec<Line> {
+        let mut lines: Vec<Line> = board
+            .generate_legal()
+            .into_iter()
+            .filter(|m| self.forced(&board.apply(*m), moves))
+            .map(|m| {
+                let after = board.apply(m);
+                let mut line = Line::new(m, self.defences(&after, moves));
+                if key && moves > 1 && !after.in_check() {
+                    line.threats = self.attacks(&passed(&after), moves - 1, false);
+                }
+                line
+            })
+            .collect();
+
+        // a move that finishes at once makes longer alternatives no dual
+        let finishing = |line: &Line| line.replies.is_empty() || (self.stipulation.kind == Kind::SELF && self.finishes(line));
+        if !key && lines.iter().any(finishing) {
+            lines.retain(finishing);
+        }
+        lines
+    }
+
+    // in a selfmate every defence mates or stalemates the attacker
+    fn finishes(&self, line: &Line) -> bool {
+        line.replies.iter().all(|reply| reply.replies.is_empty())
+    }
+
+    // whether the defender, to move, cannot escape within the given moves
+    fn forced(&self, board: &Board, moves: usize) -> bool {
+        let defences = board.generate_legal();
+        match self.stipulation.kind {
+            Kind::SELF => {
+                !defences.is_empty() && defences.into_iter().all(|m| {
+                    let after = board.apply(m);
+                    self.reached(&after) || (moves > 1 && self.can_force(&after, moves - 1))
+                })
+            }
+            _ => {
+                if defences.is_empty() {
+                    return self.reached(board);
+                }
+                moves > 1 && defences.into_iter().all(|m| self.can_force(&board.apply(m), moves - 1))
+            }
+        }
+    }
+
+    fn can_force(&self, board: &Board, moves: usize) -> bool {
+        board.generate_legal().into_iter().any(|m| self.forced(&board.apply(m), moves))
+    }
+
+    // every defence with the moves that still work against it
+    fn defences(&self, board: &Board, moves: usize) -> Vec<Line> {
+        board
+            .generate_legal()
+            .into_iter()
+            .map(|m| {
+                let after = board.apply(m);
+                let replies = if self.stipulation.kind == Kind::SELF && self.reached(&after) {
+                    Vec::new()
+                } else {
+                    self.attacks(&after, moves - 1, false)
+                };
+                Line::new(m, replies)
+            })
+            .collect()
+    }
+
+    // cooperative play where the last of the given plies reaches the goal
+    fn help(&self, board: &Board, plies: usize) -> Vec<Line> {
+        board
+            .generate_legal()
+            .into_iter()
+            .filter_map(|m| {
+                let after = board.apply(m);
+                if plies == 1 {
+                    return self.reached(&after).then(|| Line::new(m, Vec::new()));
+                }
+                let rest = self.help(&after, plies - 1);
+                (!rest.is_empty()).then(|| Line::new(m, rest))
+            })
+            .collect()
+    }
+
+    /// The solutions in the notation of problem solving software, key moves marked with `!`.
+    pub fn format(&self, solutions: &[Line]) -> String {
+        let mut out = String::new();
+        for line in solutions {
+            match self.stipulation.kind {
+                Kind::HELP => self.format_help(&self.board, line, 0, &mut Vec::new(), &mut out),
+                _ => self.format_attack(&self.board, line, 1, 0, true, &mut out),
+            }
+        }
+        out
+    }
+
+    fn format_attack(&self, board: &Board, line: &Line, number: usize, indent: usize, key: bool, out: &mut String) {
+        let after = board.apply(line.m);
+        out.push_str(&format!("{:indent$}{}.{}{}", "", number, board.to_san(line.m), if key { "!" } else { "" }));
+        if !line.threats.is_empty() {
+            let threats: Vec<String> = line.threats.iter().map(|threat| format!("{}.{}", number + 1, passed(&after).to_san(threat.m))).collect();
+            out.push_str(&format!(" threat: {}", threats.join(" ")));
+        }
+        out.push('\n');
+
+        for defence in &line.replies {
+            let next = after.apply(defence.m);
+            out.push_str(&format!("{:indent$}{}...{}", "", number, after.to_san(defence.m), indent = indent + 4));
+            // continuations that end the play go on the same line, longer ones below it
+            if defence.replies.iter().all(|reply| reply.replies.is_empty()) {
+                for reply in &defence.replies {
+                    out.push_str(&format!(" {}.{}", number + 1, next.to_san(reply.m)));
+                }
+                out.push('\n');
+            } else {
+                out.push('\n');
+                for reply in &defence.replies {
+                    self.format_attack(&next, reply, number + 1, indent + 8, false, out);
+                }
+            }
+        }
+    }
+
+    // one line per solution, numbered in pairs of moves starting with the side to move
+    fn format_help(&self, board: &Board, line: &Line, ply: usize, moves: &mut Vec<String>, out: &mut String) {
+        let san = board.to_san(line.m);
+        moves.push(if ply.is_multiple_of(2) { format!("{}.{}", ply / 2 + 1, san) } else { san });
+        if line.replies.is_empty() {
+            out.push_str(&moves.join(" "));
+            out.push('\n');
+        }
+        let after = board.apply(line.m);
+        for reply in &line.replies {
+            self.format_help(&after, reply, ply + 1, moves, out);
+        }
+        moves.pop();
+    }
+}
+
+// the same position with the other side to move, for threats
+fn passed(board: &Board) -> Board {
+    Board { next: inverse_color(board.next), en_passant: -1, ..*board }
+}
@@ -0,0 +1,165 @@
+use crate::board::fen::Fen;
+use crate::board::generator::MoveGenerator;
+use crate::board::zobrist::Zobrist;
+use crate::mate::problem::{Goal, Kind, Line, Problem, Stipulation};
+use crate::mate::{mix, Proof, Solver, INFINITE};
+use crate::Board;
+
+fn solve(fen: &str, max_nodes: usize) -> Proof {
+    Solver::new(max_nodes).solve(&Board::from_fen(fen))
+}
+
+fn line(proof: &Proof) -> Vec<String> {
+    match proof {
+        Proof::MATE(line) => line.iter().map(|m| m.to_string()).collect(),
+        _ => panic!("expected a mate, got {:?}", proof),
+    }
+}
+
+#[test]
+fn test_mate_in_one() {
+    let proof = solve("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 10_000);
+    assert_eq!(line(&proof), ["a1a8"]);
+}
+
+#[test]
+fn test_quiet_mate_in_two() {
+    let proof = solve("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", 100_000);
+    let line = line(&proof);
+    assert_eq!(line.len(), 3);
+    assert_eq!(line[0], "a1a6");
+}
+
+#[test]
+fn test_line_ends_in_mate() {
+    let board = Board::from_fen("r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 0 1");
+    let Proof::MATE(line) = Solver::new(100_000).solve(&board) else { panic!("expected a mate") };
+    assert_eq!(line.len(), 3);
+    assert_eq!(line[0].to_string(), "d5d8");
+
+    let board = line.iter().fold(board, |board, m| board.apply(*m));
+    assert!(board.in_check());
+    assert!(board.generate_legal().is_empty());
+}
+
+#[test]
+fn test_stalemate_is_no_mate() {
+    assert_eq!(solve("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 10_000), Proof::NOMATE);
+}
+
+#[test]
+fn test_mate_in_limit() {
+    let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let mut solver = Solver::new(10_000);
+    assert_eq!(line(&solver.solve_in(&board, 1)), ["a1a8"]);
+    assert_eq!(solver.solve_in(&board, 0), Proof::NOMATE);
+}
+
+#[test]
+fn test_budget_runs_out() {
+    let mut solver = Solver::new(500);
+    assert_eq!(solver.solve(&Board::from_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1")), Proof::UNKNOWN);
+    assert!(solver.nodes <= 500);
+}
+
+#[test]
+fn test_repetition_disproof_stays_on_its_path() {
+    let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let mate = board.apply(board.generate_legal().into_iter().find(|m| m.to_string() == "a1a8").unwrap());
+    let mut solver = Solver::new(10_000);
+    // on this path the mate would repeat a position, so the only mate in one is refuted
+    solver.path.push(mate.hash());
+    solver.path_key ^= mix(mate.hash());
+    solver.mid(&board, Some(1), INFINITE, INFINITE);
+    assert_eq!(solver.entry(board.hash(), Some(1)).dn, 0);
+
+    // reached another way, the mate is still there
+    solver.path.clear();
+    solver.path_key = 0;
+    assert_ne!(solver.entry(board.hash(), Some(1)).dn, 0);
+    solver.mid(&board, Some(1), INFINITE, INFINITE);
+    assert_eq!(solver.entry(board.hash(), Some(1)).pn, 0);
+}
+
+fn solve_problem(fen: &str, stipulation: &str) -> (Problem, Vec<Line>) {
+    let problem = Problem::new(Board::from_fen(fen), stipulation.parse().unwrap());
+    let solutions = problem.solve();
+    (problem, solutions)
+}
+
+fn keys(solutions: &[Line]) -> Vec<String> {
+    solutions.iter().map(|line| line.m.to_string()).collect()
+}
+
+#[test]
+fn test_parse_stipulation() {
+    for s in ["#2", "h#3", "s#5", "=1", "h=2", "s=3"] {
+        assert_eq!(s.parse::<Stipulation>().unwrap().to_string(), s);
+    }
+    let stipulation: Stipulation = "h#3".parse().unwrap();
+    assert_eq!((stipulation.kind, stipulation.goal, stipulation.moves), (Kind::HELP, Goal::MATE, 3));
+    for s in ["", "#", "#0", "x#2", "h2", "#two"] {
+        assert!(s.parse::<Stipulation>().is_err());
+    }
+}
+
+#[test]
+fn test_direct_mate() {
+    let (problem, solutions) = solve_problem("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", "#2");
+    assert_eq!(keys(&solutions), ["a1a6"]);
+    assert!(!problem.is_cooked(&solutions));
+    // a block, with the pawn still on b7 white has nothing to threaten
+    assert!(solutions[0].threats.is_empty());
+    let output = problem.format(&solutions);
+    assert!(output.starts_with("1.Ra6!\n    1...Bc7 2.Rxa7#\n"));
+    assert!(output.ends_with("    1...bxa6 2.b7#\n"));
+    assert_eq!(problem.duals(&solutions), 0);
+}
+
+#[test]
+fn test_cook() {
+    let (problem, solutions) = solve_problem("6k1/5ppp/8/8/8/8/8/RR4K1 w - - 0 1", "#1");
+    assert_eq!(keys(&solutions), ["a1a8", "b1b8"]);
+    assert!(problem.is_cooked(&solutions));
+}
+
+#[test]
+fn test_threat_and_dual() {
+    let (problem, solutions) = solve_problem("7k/8/6K1/8/8/8/8/RR6 w - - 0 1", "#2");
+    // the mates in one solve a mate in two as well, and after a waiting move either rook mates
+    assert!(problem.is_cooked(&solutions));
+    assert!(keys(&solutions).contains(&"a1a8".to_string()));
+    let waiting = solutions.iter().find(|line| line.m.to_string() == "a1a2").unwrap();
+    assert_eq!(keys(&waiting.threats), ["a2a8", "b1b8"]);
+    assert!(problem.duals(&solutions) > 0);
+    assert!(problem.format(&solutions).contains("1.Ra2! threat: 2.Ra8# 2.Rb8#\n    1...Kg8 2.Ra8# 2.Rb8#\n"));
+}
+
+#[test]
+fn test_helpmate() {
+    let (problem, solutions) = solve_problem("k7/8/1K6/8/8/8/8/7R b - - 0 1", "h#1");
+    assert_eq!(problem.format(&solutions), "1.Kb8 Rh8#\n");
+    assert!(!problem.is_cooked(&solutions));
+
+    // every waiting move of the rook along the first rank is a solution of its own
+    let (problem, solutions) = solve_problem("1k6/8/1K6/8/8/8/8/3R4 b - - 0 1", "h#2");
+    assert_eq!(problem.count(&solutions), 21);
+    assert!(problem.format(&solutions).contains("1.Kc8 Rh1 2.Kb8 Rh8#\n"));
+}
+
+#[test]
+fn test_selfmate() {
+    // black is boxed in and only has pawn captures left, which mate once white has used up its tempo
+    let (problem, solutions) = solve_problem("k7/8/2NN4/8/4P3/5p1p/5PPP/6BK w - - 0 1", "s#1");
+    assert_eq!(keys(&solutions), ["e4e5"]);
+    assert_eq!(problem.format(&solutions), "1.e5!\n    1...fxg2#\n    1...hxg2#\n");
+}
+
+#[test]
+fn test_stalemate() {
+    let (_, solutions) = solve_problem("k7/2K5/8/1Q6/8/8/8/8 w - - 0 1", "=1");
+    assert!(keys(&solutions).contains(&"b5b6".to_string()));
+    assert!(!keys(&solutions).contains(&"b5a6".to_string()));
+}
+
+
@@ -0,0 +1,89 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::board::generator::Move;
+use crate::Board;
+
+pub(crate) mod reader;
+pub(crate) mod writer;
+#[cfg(test)]
+mod tests;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum GameResult {
+    /// `1-0`
+    WHITE,
+    /// `0-1`
+    BLACK,
+    /// `1/2-1/2`
+    DRAW,
+    /// `*`, the game is still in progress or the result is unknown
+    UNKNOWN,
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameResult::WHITE => write!(f, "1-0"),
+            GameResult::BLACK => write!(f, "0-1"),
+            GameResult::DRAW => write!(f, "1/2-1/2"),
+            GameResult::UNKNOWN => write!(f, "*"),
+        }
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1-0" => Ok(GameResult::WHITE),
+            "0-1" => Ok(GameResult::BLACK),
+            "1/2-1/2" => Ok(GameResult::DRAW),
+            "*" => Ok(GameResult::UNKNOWN),
+            _ => Err(())
+        }
+    }
+}
+
+/// A game of the main line, replayed through the move generator.
+///
+/// `positions[0]` is the starting position and `positions[i + 1]` the position after `moves[i]`.
+/// Comments and NAGs are kept per move, variations are validated syntactically but skipped.
+#[derive(Clone, Debug)]
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<Move>,
+    pub positions: Vec<Board>,
+    pub comments: Vec<Option<String>>,
+    pub nags: Vec<Vec<u8>>,
+    pub result: GameResult,
+}
+
+impl Game {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PgnError {
+    /// 1-based index of the game within the input
+    pub game: usize,
+    /// 1-based line the offending token starts on
+    pub line: usize,
+    /// 1-based ply of the offending move, if the error is caused by a move
+    pub ply: Option<usize>,
+    pub token: String,
+    pub message: String,
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "game {}, line {}", self.game, self.line)?;
+        if let Some(ply) = self.ply {
+            write!(f, ", ply {} (`{}`)", ply, self.token)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+use crate::board::fen::Fen;
+use crate::board::generator::MoveGenerator;
+use crate::board::san::San;
+use crate::pgn::{Game, GameResult, PgnError};
+use crate::{Board, Color};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Nag(u8),
+    MoveNumber,
+    Result(GameResult),
+    San(String),
+}
+
+/// Reads games from PGN text, one `Result` per game, so a broken game does not stop the
+/// remaining games of a collection from being read.
+pub struct Reader<'a> {
+    input: &'a str,
+    position: usize,
+    line: usize,
+    game: usize,
+    pending: VecDeque<(Token, usize)>,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a str) -> Reader<'a> {
+        Reader {
+            input,
+            position: 0,
+            line: 1,
+            game: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.position += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    // reads until (and consuming) `end`, returns everything in between
+    fn read_until(&mut self, end: char) -> String {
+        let mut value = String::new();
+        while let Some(c) = self.next_char() {
+            if c == end {
+                break;
+            }
+            value.push(c);
+        }
+        value
+    }
+
+    fn read_tag(&mut self) -> Token {
+        let content = self.read_until(']');
+        let content = content.trim();
+        let (name, value) = content.split_once(char::is_whitespace).unwrap_or((content, ""));
+
+        // tag values are quoted and may escape `"` and `\` with a backslash
+        let mut unescaped = String::new();
+        let mut chars = value.trim().trim_start_matches('"').trim_end_matches('"').chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    unescaped.push(escaped);
+                }
+            } else {
+                unescaped.push(c);
+            }
+        }
+
+        Token::Tag(name.to_string(), unescaped)
+    }
+
+    fn next_token(&mut self) -> Option<(Token, usize)> {
+        if let Some(pending) = self.pending.pop_front() {
+            return Some(pending);
+        }
+
+        loop {
+            let at_line_start = self.position == 0 || self.input[..self.position].ends_with('\n');
+            let c = self.peek_char()?;
+            let line = self.line;
+
+            if c.is_whitespace() {
+                self.next_char();
+                continue;
+            }
+
+            // escape mechanism: lines starting with `%` are ignored
+            if c == '%' && at_line_start {
+                self.read_until('\n');
+                continue;
+            }
+
+            self.next_char();
+            let token = match c {
+                '[' => self.read_tag(),
+                '{' => Token::Comment(self.read_until('}').trim().to_string()),
+                ';' => Token::Comment(self.read_until('\n').trim().to_string()),
+                '(' => Token::VariationStart,
+                ')' => Token::VariationEnd,
+                '$' => {
+                    let mut digits = String::new();
+                    while let Some(d) = self.peek_char().filter(|d| d.is_ascii_digit()) {
+                        digits.push(d);
+                        self.next_char();
+                    }
+                    Token::Nag(digits.parse().unwrap_or(0))
+                }
+                _ => {
+                    let mut symbol = c.to_string();
+                    while let Some(s) = self.peek_char() {
+                        if s.is_whitespace() || "[]{}();$".contains(s) {
+                            break;
+                        }
+                        symbol.push(s);
+                        self.next_char();
+                    }
+                    self.classify(&symbol, line);
+                    return self.pending.pop_front();
+                }
+            };
+
+            return Some((token, line));
+        }
+    }
+
+    // turns a symbol into a result, move number or SAN token (with suffix annotations as NAGs)
+    fn classify(&mut self, symbol: &str, line: usize) {
+        if let Ok(result) = GameResult::from_str(symbol) {
+            self.pending.push_back((Token::Result(result), line));
+            return;
+        }
+
+        let mut san = symbol;
+        if san.starts_with(|c: char| c.is_ascii_digit()) && !san.starts_with("0-0") {
+            // move numbers (`12.`, `12...`), possibly glued to the move (`12.e4`)
+            san = san.trim_start_matches(|c: char| c.is_ascii_digit()).trim_start_matches('.');
+            if san.is_empty() {
+                self.pending.push_back((Token::MoveNumber, line));
+                return;
+            }
+        }
+        if san.chars().all(|c| c == '.') {
+            self.pending.push_back((Token::MoveNumber, line));
+            return;
+        }
+
+        let move_text = san.trim_end_matches(['!', '?']);
+        let nag = match &san[move_text.len()..] {
+            "!" => Some(1),
+            "?" => Some(2),
+            "!!" => Some(3),
+            "??" => Some(4),
+            "!?" => Some(5),
+            "?!" => Some(6),
+            _ => None,
+        };
+
+        self.pending.push_back((Token::San(move_text.to_string()), line));
+        if let Some(nag) = nag {
+            self.pending.push_back((Token::Nag(nag), line));
+        }
+    }
+
+    fn error(&self, line: usize, ply: Option<usize>, token: String, message: String) -> PgnError {
+        PgnError { game: self.game, line, ply, token, message }
+    }
+
+    // skips the remainder of a broken game, up to its result or the next tag section
+    fn skip_game(&mut self) {
+        while let Some((token, line)) = self.next_token() {
+            match token {
+                Token::Tag(..) => {
+                    self.pending.push_front((token, line));
+                    return;
+                }
+                Token::Result(_) => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn start_position(&self, tags: &[(String, String)], line: usize) -> Result<Board, PgnError> {
+        match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Board::try_from_fen(fen)
+                .map_err(|e| self.error(line, None, fen.clone(), e.to_string())),
+            None => Ok(Board { ..Default::default() }),
+        }
+    }
+}
+
+impl Iterator for Reader<'_> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut game = Game {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            positions: Vec::new(),
+            comments: Vec::new(),
+            nags: Vec::new(),
+            result: GameResult::UNKNOWN,
+        };
+        let mut in_movetext = false;
+        let mut depth = 0;
+        let mut result = None;
+
+        self.game += 1;
+        while let Some((token, line)) = self.next_token() {
+            if !in_movetext && !matches!(token, Token::Tag(..)) {
+                in_movetext = true;
+                match self.start_position(&game.tags, line) {
+                    Ok(board) => game.positions.push(board),
+                    Err(e) => {
+                        self.pending.push_front((token, line));
+                        self.skip_game();
+                        return Some(Err(e));
+                    }
+                }
+            }
+
+            match token {
+                Token::Tag(name, value) => {
+                    if in_movetext {
+                        // a tag after movetext starts the next game
+                        self.pending.push_front((Token::Tag(name, value), line));
+                        break;
+                    }
+                    game.tags.push((name, value));
+                }
+                Token::Comment(comment) => {
+                    if depth == 0 && !game.comments.is_empty() {
+                        let last = game.comments.last_mut().unwrap();
+                        *last = Some(match last.take() {
+                            Some(previous) => format!("{} {}", previous, comment),
+                            None => comment,
+                        });
+                    }
+                }
+                Token::VariationStart => depth += 1,
+                Token::VariationEnd => {
+                    if depth == 0 {
+                        self.skip_game();
+                        return Some(Err(self.error(line, None, ")".to_string(), "unbalanced variation".to_string())));
+                    }
+                    depth -= 1;
+                }
+                Token::Nag(nag) => {
+                    if depth == 0 && !game.nags.is_empty() {
+                        game.nags.last_mut().unwrap().push(nag);
+                    }
+                }
+                Token::MoveNumber => {}
+                Token::Result(r) => {
+                    if depth == 0 {
+                        result = Some(r);
+                        break;
+                    }
+                }
+                Token::San(san) => {
+                    if depth > 0 {
+                        continue;
+                    }
+
+                    let board = *game.positions.last().unwrap();
+                    match board.parse_san(&san) {
+                        Ok(m) => {
+                            game.moves.push(m);
+                            game.positions.push(board.apply(m));
+                            game.comments.push(None);
+                            game.nags.push(Vec::new());
+                        }
+                        Err(e) => {
+                            let ply = game.moves.len() + 1;
+                            let dots = if board.next == Color::WHITE { "." } else { "..." };
+                            let token = format!("{}{} {}", board.fullmove_clock, dots, san);
+                            self.skip_game();
+                            return Some(Err(self.error(line, Some(ply), token, e.to_string())));
+                        }
+                    }
+                }
+            }
+        }
+
+        if !in_movetext && game.tags.is_empty() {
+            return None;
+        }
+        if game.positions.is_empty() {
+            match self.start_position(&game.tags, self.line) {
+                Ok(board) => game.positions.push(board),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        if depth > 0 {
+            return Some(Err(self.error(self.line, None, "(".to_string(), "unterminated variation".to_string())));
+        }
+
+        game.result = result
+            .or_else(|| game.tag("Result").and_then(|r| GameResult::from_str(r).ok()))
+            .unwrap_or(GameResult::UNKNOWN);
+
+        Some(Ok(game))
+    }
+}
@@ -0,0 +1,149 @@
+use std::time::Duration;
+use crate::board::fen::Fen;
+use crate::board::generator::MoveGenerator;
+use crate::board::san::San;
+use crate::pgn::reader::Reader;
+use crate::pgn::writer::{write_game, Annotation};
+use crate::pgn::GameResult;
+use crate::Board;
+
+const IMMORTAL_GAME: &str = r#"[Event "London"]
+[Site "London ENG"]
+[Date "1851.06.21"]
+[White "Adolf Anderssen"]
+[Black "Lionel Kieseritzky"]
+[Result "1-0"]
+
+1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5 5. Bxb5 Nf6 6. Nf3 Qh6 7. d3 Nh5 8. Nh4
+Qg5 9. Nf5 c6 10. g4 Nf6 11. Rg1 cxb5 12. h4 Qg6 13. h5 Qg5 14. Qf3 Ng8 15. Bxf4
+Qf6 16. Nc3 Bc5 17. Nd5 Qxb2 18. Bd6 Bxg1 19. e5 Qxa1+ 20. Ke2 Na6 21. Nxg7+ Kd8
+22. Qf6+ Nxf6 23. Be7# 1-0
+"#;
+
+#[test]
+fn test_read_game() {
+    let games: Vec<_> = Reader::new(IMMORTAL_GAME).collect();
+    assert_eq!(games.len(), 1);
+
+    let game = games[0].as_ref().unwrap();
+    assert_eq!(game.tag("White"), Some("Adolf Anderssen"));
+    assert_eq!(game.result, GameResult::WHITE);
+    assert_eq!(game.moves.len(), 45);
+    assert_eq!(game.positions.len(), 46);
+
+    let last = game.positions.last().unwrap();
+    assert_eq!(last.to_fen(), "r1bk3r/p2pBpNp/n4n2/1p1NP2P/6P1/3P4/P1P1K3/q5b1 b - - 1 23");
+    assert!(last.is_check(last.next));
+    assert!(last.generate_legal().is_empty());
+}
+
+#[test]
+fn test_read_comments_variations_and_nags() {
+    let pgn = r#"[Event "?"]
+
+{opening comment} 1. e4 {best by test} e5 2. Nf3!? (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6 $1
+; rest of line comment
+3. Bb5 a6?! 4.Ba4 *
+"#;
+    let game = Reader::new(pgn).next().unwrap().unwrap();
+    assert_eq!(game.moves.len(), 7);
+    assert_eq!(game.result, GameResult::UNKNOWN);
+    assert_eq!(game.comments[0], Some("best by test".to_string()));
+    assert_eq!(game.comments[3], Some("rest of line comment".to_string()));
+    assert_eq!(game.nags[2], vec![5]);
+    assert_eq!(game.nags[3], vec![1]);
+    assert_eq!(game.nags[5], vec![6]);
+}
+
+#[test]
+fn test_read_from_fen_tag() {
+    let pgn = r#"[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 30"]
+
+30... Kd7 31. e4 Kc6 0-1"#;
+    let game = Reader::new(pgn).next().unwrap().unwrap();
+    assert_eq!(game.result, GameResult::BLACK);
+    assert_eq!(game.positions.last().unwrap().to_fen(), "8/8/2k5/8/4P3/8/8/4K3 w - - 1 32");
+}
+
+#[test]
+fn test_illegal_move_error() {
+    let pgn = "[Event \"first\"]\n\n1. e4 e5 2. Ke3 Nc6 1-0\n\n[Event \"second\"]\n\n1. d4 d5 1/2-1/2\n";
+    let games: Vec<_> = Reader::new(pgn).collect();
+    assert_eq!(games.len(), 2);
+
+    let error = games[0].as_ref().unwrap_err();
+    assert_eq!(error.game, 1);
+    assert_eq!(error.line, 3);
+    assert_eq!(error.ply, Some(3));
+    assert_eq!(error.token, "2. Ke3");
+    assert_eq!(error.to_string(), "game 1, line 3, ply 3 (`2. Ke3`): illegal move `Ke3`");
+
+    let game = games[1].as_ref().unwrap();
+    assert_eq!(game.tag("Event"), Some("second"));
+    assert_eq!(game.result, GameResult::DRAW);
+}
+
+#[test]
+fn test_ambiguous_move_error() {
+    let pgn = "[FEN \"4k3/8/8/8/8/8/4K3/R6R w - - 0 1\"]\n\n1. Rd1 *";
+    let error = Reader::new(pgn).next().unwrap().unwrap_err();
+    assert_eq!(error.ply, Some(1));
+    assert_eq!(error.message, "ambiguous move `Rd1`");
+}
+
+#[test]
+fn test_write_round_trip() {
+    let game = Reader::new(IMMORTAL_GAME).next().unwrap().unwrap();
+    let pgn = game.to_pgn();
+
+    assert!(pgn.starts_with("[Event \"London\"]\n[Site \"London ENG\"]\n[Date \"1851.06.21\"]\n[Round \"?\"]\n"));
+    assert!(pgn.contains("\n\n1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5 "));
+    assert!(pgn.ends_with("22. Qf6+ Nxf6 23. Be7# 1-0\n"));
+    assert!(pgn.lines().all(|line| line.len() <= 80));
+
+    let reread = Reader::new(&pgn).next().unwrap().unwrap();
+    assert_eq!(reread.moves, game.moves);
+    assert_eq!(reread.tag("White"), game.tag("White"));
+    assert_eq!(reread.tag("Round"), Some("?"));
+}
+
+#[test]
+fn test_write_setup_and_annotations() {
+    let start = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 30");
+    let kd7 = start.parse_san("Kd7").unwrap();
+    let after = start.apply(kd7);
+    let e4 = after.parse_san("e4").unwrap();
+    let annotations = [
+        Annotation { eval: Some(-15), depth: Some(20), clock: Some(Duration::from_secs(3723)), ..Default::default() },
+        Annotation { mate: Some(7), comment: Some("only move".to_string()), nags: vec![1], ..Default::default() },
+    ];
+
+    let pgn = write_game(&[("White".to_string(), "rusty \"dev\"".to_string())], &start, &[kd7, e4], GameResult::UNKNOWN, &annotations);
+    assert_eq!(pgn, r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "rusty \"dev\""]
+[Black "?"]
+[Result "*"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 30"]
+
+30... Kd7 {[%eval -0.15,20] [%clk 1:02:03]} 31. e4 $1 {[%eval #7] only move} *
+"#);
+}
+
+#[test]
+fn test_write_wraps_comments() {
+    let board = Board { ..Default::default() };
+    let m = board.parse_san("e4").unwrap();
+    let comment = "a very long comment ".repeat(10);
+    let annotations = [Annotation { comment: Some(comment), ..Default::default() }];
+
+    let pgn = write_game(&[], &board, &[m, board.apply(m).parse_san("e5").unwrap()], GameResult::DRAW, &annotations);
+    let movetext: Vec<&str> = pgn.split("\n\n").nth(1).unwrap().lines().collect();
+    assert!(movetext.len() > 1);
+    assert!(movetext.iter().all(|line| line.len() <= 80));
+    assert!(movetext.last().unwrap().ends_with("comment} 1... e5 1/2-1/2"));
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::fen::Fen;
+use crate::board::san::San;
+use crate::pgn::{Game, GameResult};
+use crate::{Board, Color};
+
+const MAX_LINE_LENGTH: usize = 80;
+
+// the seven tag roster, always exported first and in this order
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Everything that can be attached to a single move.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Annotation {
+    /// evaluation in centipawns from white's point of view
+    pub eval: Option<isize>,
+    /// mate in the given number of moves, positive if white mates
+    pub mate: Option<isize>,
+    /// search depth the evaluation was obtained with
+    pub depth: Option<usize>,
+    /// remaining time on the clock of the player who moved
+    pub clock: Option<Duration>,
+    pub comment: Option<String>,
+    pub nags: Vec<u8>,
+}
+
+impl Annotation {
+    // renders the `{...}` comment, `[%eval]` and `[%clk]` commands first
+    fn to_comment(&self) -> Option<String> {
+        let mut parts: Vec<String> = Vec::new();
+
+        let eval = match (self.mate, self.eval) {
+            (Some(mate), _) => Some(format!("#{}", mate)),
+            (None, Some(eval)) => {
+                let sign = if eval < 0 { "-" } else { "" };
+                Some(format!("{}{}.{:02}", sign, eval.abs() / 100, eval.abs() % 100))
+            }
+            _ => None,
+        };
+        if let Some(eval) = eval {
+            match self.depth {
+                Some(depth) => parts.push(format!("[%eval {},{}]", eval, depth)),
+                None => parts.push(format!("[%eval {}]", eval)),
+            }
+        }
+
+        if let Some(clock) = self.clock {
+            let seconds = clock.as_secs();
+            parts.push(format!("[%clk {}:{:02}:{:02}]", seconds / 3600, seconds / 60 % 60, seconds % 60));
+        }
+
+        if let Some(comment) = &self.comment {
+            // a closing brace would end the comment early
+            parts.push(comment.trim().replace('}', ""));
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(format!("{{{}}}", parts.join(" ")))
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Writes a single game in PGN export format.
+///
+/// `annotations` may be shorter than `moves`, moves without annotation are written bare. `SetUp`
+/// and `FEN` tags are added whenever `start` is not the standard starting position.
+pub fn write_game(tags: &[(String, String)], start: &Board, moves: &[Move], result: GameResult, annotations: &[Annotation]) -> String {
+    let mut pgn = String::new();
+
+    for (name, default) in SEVEN_TAG_ROSTER {
+        let value = match name {
+            "Result" => result.to_string(),
+            _ => tags.iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+                .unwrap_or(default.to_string()),
+        };
+        pgn += format!("[{} \"{}\"]\n", name, escape(&value)).as_str();
+    }
+
+    let standard = *start == Board { ..Default::default() };
+    if !standard {
+        pgn += "[SetUp \"1\"]\n";
+        pgn += format!("[FEN \"{}\"]\n", start.to_fen()).as_str();
+    }
+
+    for (name, value) in tags {
+        let is_roster = SEVEN_TAG_ROSTER.iter().any(|(key, _)| key == name);
+        if is_roster || name == "SetUp" || name == "FEN" {
+            continue;
+        }
+        pgn += format!("[{} \"{}\"]\n", name, escape(value)).as_str();
+    }
+    pgn += "\n";
+
+    // movetext as a list of words, so that wrapping never needs to look into tokens
+    let mut words: Vec<String> = Vec::new();
+    let mut board = *start;
+    let mut needs_number = true;
+    for (ply, m) in moves.iter().enumerate() {
+        if board.next == Color::WHITE {
+            words.push(format!("{}.", board.fullmove_clock));
+        } else if needs_number {
+            words.push(format!("{}...", board.fullmove_clock));
+        }
+        words.push(board.to_san(*m));
+        needs_number = false;
+
+        if let Some(annotation) = annotations.get(ply) {
+            for nag in &annotation.nags {
+                words.push(format!("${}", nag));
+            }
+            if let Some(comment) = annotation.to_comment() {
+                words.extend(comment.split_whitespace().map(String::from));
+                // black's reply after a comment needs its move number repeated
+                needs_number = true;
+            }
+        }
+
+        board = board.apply(*m);
+    }
+    words.push(result.to_string());
+
+    let mut line = String::new();
+    for word in words {
+        if !line.is_empty() && line.len() + 1 + word.len() > MAX_LINE_LENGTH {
+            pgn += line.as_str();
+            pgn += "\n";
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+    }
+    pgn += line.as_str();
+    pgn += "\n";
+
+    pgn
+}
+
+impl Game {
+    pub fn to_pgn(&self) -> String {
+        let annotations: Vec<Annotation> = self.comments
+            .iter()
+            .zip(self.nags.iter())
+            .map(|(comment, nags)| Annotation { comment: comment.clone(), nags: nags.clone(), ..Default::default() })
+            .collect();
+
+        write_game(&self.tags, &self.positions[0], &self.moves, self.result, &annotations)
+    }
+}
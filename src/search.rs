@@ -0,0 +1,808 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::board::color::inverse_color;
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::piece::Piece;
+use crate::board::variant::{Outcome, Variant};
+use crate::board::zobrist::Zobrist;
+use crate::dtm::{self, Dtm};
+use crate::eval::pawns::{self, PawnTable};
+use crate::eval::{piece_value, Evaluation};
+use crate::random::Random;
+use crate::syzygy::{Tablebase, Wdl};
+use crate::{Board, Color};
+use skill::Skill;
+use transposition::{Bound, TranspositionTable};
+
+#[cfg(test)]
+mod tests;
+pub(crate) mod skill;
+pub(crate) mod transposition;
+
+pub const MATE: isize = 100_000;
+pub const INFINITY: isize = 1_000_000;
+pub const MAX_DEPTH: usize = 64;
+pub const MAX_THREADS: usize = 64;
+pub const MAX_MULTIPV: usize = 256;
+/// Score of a tablebase win, below all mate scores.
+pub const TB_WIN: isize = MATE - 2 * MAX_DEPTH as isize - 1;
+
+// nodes between two looks at the clock
+const CHECK_INTERVAL: usize = 1024;
+
+// margin per ply by which the static evaluation must beat beta to cut a node without searching it
+const REVERSE_FUTILITY_MARGIN: isize = 120;
+// quiet moves cannot raise the evaluation by more than this plus a margin per ply
+const FUTILITY_MARGIN: isize = 100;
+const FUTILITY_MARGIN_PER_PLY: isize = 150;
+// iterations from this depth on search a window around the previous score, widened on failure
+const ASPIRATION_DEPTH: usize = 4;
+const ASPIRATION_WINDOW: isize = 25;
+// singular extensions are tried from this depth on, against a margin per ply below the table score
+const SINGULAR_DEPTH: usize = 6;
+const SINGULAR_MARGIN: isize = 2;
+// history scores are halved once one of them reaches this bound
+const HISTORY_MAX: isize = 4_000;
+
+// a game won or lost by the rules of its variant scores like a mate
+fn outcome_score(outcome: Outcome, ply: usize) -> isize {
+    match outcome {
+        Outcome::WIN => MATE - ply as isize,
+        Outcome::LOSS => -MATE + ply as isize,
+        Outcome::DRAW => 0,
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    pub movetime: Option<Duration>,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<usize>,
+    pub depth: Option<usize>,
+    pub nodes: Option<usize>,
+    /// Stop once a mate in this many moves is found.
+    pub mate: Option<usize>,
+    /// Only these root moves are searched, all of them if empty.
+    pub searchmoves: Vec<Move>,
+}
+
+impl Limits {
+    /// How long to think about the next move, `None` if the search is not limited by time.
+    pub fn time_budget(&self, color: Color) -> Option<Duration> {
+        if self.movetime.is_some() {
+            return self.movetime;
+        }
+
+        let (time, increment) = match color {
+            Color::WHITE => (self.wtime, self.winc),
+            _ => (self.btime, self.binc),
+        };
+        let time = time?;
+        let moves = self.movestogo.unwrap_or(30).max(1) as u32;
+        let budget = time / moves + increment.unwrap_or_default() / 2;
+
+        // always keep a reserve on the clock
+        Some(budget.min(time * 4 / 5))
+    }
+}
+
+// mate and tablebase scores are stored relative to the node instead of the root
+fn score_to_tt(score: isize, ply: usize) -> isize {
+    if score > TB_WIN - MAX_DEPTH as isize {
+        score + ply as isize
+    } else if score < -TB_WIN + MAX_DEPTH as isize {
+        score - ply as isize
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: isize, ply: usize) -> isize {
+    if score > TB_WIN - MAX_DEPTH as isize {
+        score - ply as isize
+    } else if score < -TB_WIN + MAX_DEPTH as isize {
+        score + ply as isize
+    } else {
+        score
+    }
+}
+
+/// Selective search techniques, each can be switched off to measure what it is worth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pruning {
+    pub null_move: bool,
+    pub late_move_reductions: bool,
+    pub reverse_futility: bool,
+    pub futility: bool,
+    pub late_move_pruning: bool,
+}
+
+impl Default for Pruning {
+    fn default() -> Self {
+        Pruning { null_move: true, late_move_reductions: true, reverse_futility: true, futility: true, late_move_pruning: true }
+    }
+}
+
+// the side to move has pieces besides pawns and king, without them passing is often the best move
+fn non_pawn_pieces(board: &Board) -> usize {
+    (0..64)
+        .filter(|&i| board.colors[i] == board.next && !matches!(board.pieces[i], Piece::PAWN | Piece::KING | Piece::EMPTY))
+        .count()
+}
+
+fn is_quiet(m: Move) -> bool {
+    m.capture == Piece::EMPTY && m.promote_to == Piece::EMPTY
+}
+
+// reductions grow with the depth left and with how late the move comes
+fn late_move_reduction(depth: usize, index: usize) -> usize {
+    (0.5 + (depth as f64).ln() * (index as f64).ln() / 2.5) as usize
+}
+
+/// The outcome of a completed iteration, or of one that failed high or low on its aspiration
+/// window, in which case the score is only a bound.
+#[derive(Clone, Debug, Default)]
+pub struct SearchInfo {
+    pub depth: usize,
+    // the rank of the line among those searched, 1 for the best
+    pub multipv: usize,
+    pub score: isize,
+    pub bound: Bound,
+    pub nodes: usize,
+    pub tbhits: usize,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+
+    /// Moves until mate, positive if the side to move mates.
+    pub fn mate_in(&self) -> Option<isize> {
+        if self.score.abs() < MATE - MAX_DEPTH as isize * 2 {
+            return None;
+        }
+        let plies = MATE - self.score.abs();
+        let moves = (plies + 1) / 2;
+        Some(if self.score > 0 { moves } else { -moves })
+    }
+}
+
+pub struct Search {
+    pub nodes: usize,
+    pub tbhits: usize,
+    pub tablebase: Option<Arc<Tablebase>>,
+    pub dtm: Option<Arc<dtm::Tablebase>>,
+    pub pruning: Pruning,
+    pub tt: Arc<TranspositionTable>,
+    pub threads: usize,
+    // the number of best lines to search
+    pub multipv: usize,
+    // set to end the search
+    pub stop: Arc<AtomicBool>,
+    // set while pondering, the clock only starts once it is cleared
+    pub ponder: Arc<AtomicBool>,
+    pub skill: Skill,
+    // nodes searched by the helper threads, counted in steps of `CHECK_INTERVAL`
+    helper_nodes: Arc<AtomicUsize>,
+    // 0 for the main thread
+    thread_id: usize,
+    // hashes of all positions from the start of the game up to the current node
+    history: Vec<u64>,
+    start: Instant,
+    clock: Instant,
+    budget: Option<Duration>,
+    deadline: Option<Instant>,
+    stopped: bool,
+    previous_pv: Vec<Move>,
+    // how often quiet moves by from and to square caused a cutoff
+    quiet_history: [[isize; 64]; 64],
+    // the moves from the root to the current node, `None` for a null move
+    line: Vec<Option<Move>>,
+    // plies the current branch was extended by
+    extensions: usize,
+    root_depth: usize,
+    // the move left out by the next node searched, to test whether it is singular
+    excluded: Option<Move>,
+    // root moves of the lines already found in this iteration
+    root_excluded: Vec<Move>,
+    // the root moves to search, all if empty
+    root_moves: Vec<Move>,
+    max_nodes: Option<usize>,
+    pawns: PawnTable,
+}
+
+impl Search {
+    /// `history` holds the hashes of the positions played before the root, for repetition detection.
+    pub fn new(history: Vec<u64>) -> Search {
+        Search {
+            nodes: 0,
+            tbhits: 0,
+            tablebase: None,
+            dtm: None,
+            pruning: Pruning::default(),
+            tt: Arc::new(TranspositionTable::new(transposition::DEFAULT_MEGABYTES)),
+            threads: 1,
+            multipv: 1,
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: Arc::new(AtomicBool::new(false)),
+            skill: Skill::default(),
+            helper_nodes: Arc::new(AtomicUsize::new(0)),
+            thread_id: 0,
+            history,
+            start: Instant::now(),
+            clock: Instant::now(),
+            budget: None,
+            deadline: None,
+            stopped: false,
+            previous_pv: Vec::new(),
+            quiet_history: [[0; 64]; 64],
+            line: Vec::new(),
+            extensions: 0,
+            root_depth: 0,
+            excluded: None,
+            root_excluded: Vec::new(),
+            root_moves: Vec::new(),
+            max_nodes: None,
+            pawns: PawnTable::new(pawns::DEFAULT_ENTRIES),
+        }
+    }
+
+    // a helper thread shares the table, is stopped by the main thread and orders quiet moves slightly differently
+    fn helper(&self, id: usize, stop: &Arc<AtomicBool>) -> Search {
+        let mut random = Random::new(id as u64);
+        let mut helper = Search {
+            tablebase: self.tablebase.clone(),
+            dtm: self.dtm.clone(),
+            pruning: self.pruning,
+            tt: self.tt.clone(),
+            stop: stop.clone(),
+            helper_nodes: self.helper_nodes.clone(),
+            thread_id: id,
+            ..Search::new(self.history.clone())
+        };
+        helper.quiet_history.iter_mut().flatten().for_each(|score| *score = random.below(32) as isize);
+        helper
+    }
+
+    /// Iterative deepening until the time budget is used up or the maximum depth is reached,
+    /// `report` is called after every completed iteration. With more than one thread, helpers
+    /// search the same position until the main thread is done (Lazy SMP).
+    pub fn run<F: FnMut(&SearchInfo)>(&mut self, board: &Board, limits: &Limits, report: F) -> SearchInfo {
+        self.helper_nodes.store(0, Ordering::Relaxed);
+        // helper threads would make a search limited by nodes come out differently every time
+        if self.threads <= 1 || limits.nodes.is_some() || self.skill.is_limited() {
+            return self.iterate(board, limits, report);
+        }
+
+        // helpers are not limited by time, the main thread stops them
+        let helper_limits = Limits { depth: limits.depth, mate: limits.mate, searchmoves: limits.searchmoves.clone(), ..Default::default() };
+        let done = Arc::new(AtomicBool::new(false));
+        let mut helpers: Vec<Search> = (1..self.threads.min(MAX_THREADS)).map(|id| self.helper(id, &done)).collect();
+        let mut best = thread::scope(|scope| {
+            let handles: Vec<_> = helpers
+                .iter_mut()
+                .map(|helper| scope.spawn(|| helper.iterate(board, &helper_limits, |_| {})))
+                .collect();
+            let mut best = self.iterate(board, limits, report);
+            done.store(true, Ordering::Relaxed);
+
+            // a helper that completed a deeper iteration knows better
+            for handle in handles {
+                let info = handle.join().unwrap();
+                if info.depth > best.depth && !info.pv.is_empty() {
+                    best = SearchInfo { tbhits: best.tbhits, time: best.time, ..info };
+                }
+            }
+            best
+        });
+        best.nodes = self.nodes + helpers.iter().map(|helper| helper.nodes).sum::<usize>();
+        best
+    }
+
+    fn iterate<F: FnMut(&SearchInfo)>(&mut self, board: &Board, limits: &Limits, mut report: F) -> SearchInfo {
+        self.start = Instant::now();
+        self.budget = limits.time_budget(board.next);
+        self.deadline = None;
+        self.start_clock();
+        self.max_nodes = match (limits.nodes, self.skill.max_nodes()) {
+            (Some(nodes), Some(max_nodes)) => Some(nodes.min(max_nodes)),
+            (nodes, max_nodes) => nodes.or(max_nodes),
+        };
+        self.root_moves = limits.searchmoves.clone();
+        let root_moves = if self.root_moves.is_empty() { board.generate_legal().len() } else { self.root_moves.len() };
+        // a weaker player chooses among several lines, but only the asked for ones are reported
+        let lines = self.multipv.max(self.skill.lines()).clamp(1, root_moves.max(1));
+        // a mate in n moves takes 2n - 1 plies, reductions can hide it from that iteration though
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).min(limits.mate.map_or(MAX_DEPTH, |moves| 2 * moves + 2)).min(MAX_DEPTH);
+
+        let mut best = SearchInfo::default();
+        let mut previous: Vec<SearchInfo> = Vec::new();
+        // every other helper begins one iteration ahead, so the threads spread over two depths
+        for depth in 1 + self.thread_id % 2..=max_depth {
+            self.root_depth = depth;
+            // each further line leaves out the root moves of the lines found before it
+            let mut current: Vec<SearchInfo> = Vec::new();
+            for line in 0..lines {
+                self.root_excluded = current.iter().filter_map(|info| info.best_move()).collect();
+                self.previous_pv = previous.get(line).map(|info| info.pv.clone()).unwrap_or_default();
+                let previous_score = previous.get(line).map(|info| info.score);
+                let (score, pv) = self.search_root(board, depth, previous_score, line + 1, &mut report);
+                if self.stopped {
+                    break;
+                }
+                current.push(SearchInfo { depth, multipv: line + 1, score, pv, ..Default::default() });
+            }
+            self.root_excluded.clear();
+            if self.stopped {
+                // results of an unfinished iteration cannot be trusted
+                break;
+            }
+
+            // a later line can come out better than an earlier one only through search instability
+            current.sort_by_key(|info| -info.score);
+            for (index, info) in current.iter_mut().enumerate() {
+                *info = SearchInfo { multipv: index + 1, nodes: self.total_nodes(), tbhits: self.tbhits, time: self.start.elapsed(), ..info.clone() };
+                if index < self.multipv.max(1) {
+                    report(info);
+                }
+            }
+            best = current[0].clone();
+            self.previous_pv = best.pv.clone();
+            previous = current;
+
+            // while analysing several lines, the others may still be of interest after a mate is found,
+            // and a longer mate than asked for may still turn into a short enough one
+            let mated = match limits.mate {
+                Some(moves) => best.mate_in().is_some_and(|mate| (1..=moves as isize).contains(&mate)),
+                None => best.mate_in().is_some(),
+            };
+            if best.pv.is_empty() || (lines == 1 && mated) {
+                break;
+            }
+            // the next iteration takes several times longer, so it would most likely not finish
+            self.start_clock();
+            if let (Some(budget), Some(_)) = (self.budget, self.deadline) {
+                if self.clock.elapsed() > budget / 2 {
+                    break;
+                }
+            }
+        }
+
+        if self.skill.is_limited() {
+            best = self.skill.pick(board.hash(), &previous).unwrap_or(best);
+        }
+        best
+    }
+
+    // searches the root within a window around the score of the previous iteration, which is
+    // widened until the score falls inside it
+    fn search_root<F: FnMut(&SearchInfo)>(&mut self, board: &Board, depth: usize, previous_score: Option<isize>, multipv: usize, report: &mut F) -> (isize, Vec<Move>) {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match previous_score {
+            Some(score) if depth >= ASPIRATION_DEPTH && score.abs() < TB_WIN => (score - delta, score + delta),
+            _ => (-INFINITY, INFINITY),
+        };
+
+        loop {
+            let mut pv = Vec::new();
+            let score = self.alpha_beta(board, depth, 0, alpha, beta, &mut pv, true);
+            if self.stopped || (score > alpha && score < beta) {
+                return (score, pv);
+            }
+
+            // a failed low search has no best move, so the previous one is reported
+            let (bound, pv) = if score <= alpha { (Bound::UPPER, self.previous_pv.clone()) } else { (Bound::LOWER, pv) };
+            report(&SearchInfo { depth, multipv, score, bound, nodes: self.total_nodes(), tbhits: self.tbhits, time: self.start.elapsed(), pv });
+            if bound == Bound::UPPER {
+                beta = (alpha + beta) / 2;
+                alpha = (score - delta).max(-INFINITY);
+            } else {
+                beta = (score + delta).min(INFINITY);
+            }
+            delta *= 2;
+        }
+    }
+
+    // a ponder search turns into a timed one when the opponent plays the expected move
+    fn start_clock(&mut self) {
+        if self.deadline.is_none() && !self.ponder.load(Ordering::Relaxed) {
+            if let Some(budget) = self.budget {
+                self.clock = Instant::now();
+                self.deadline = Some(self.clock + budget);
+            }
+        }
+    }
+
+    fn total_nodes(&self) -> usize {
+        self.nodes + self.helper_nodes.load(Ordering::Relaxed)
+    }
+
+    fn check_time(&mut self) {
+        // checked at every node, so the same limit always gives the same search
+        if self.max_nodes.is_some_and(|nodes| self.nodes >= nodes) && !self.previous_pv.is_empty() {
+            self.stopped = true;
+        }
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            return;
+        }
+        if self.thread_id > 0 {
+            self.helper_nodes.fetch_add(CHECK_INTERVAL, Ordering::Relaxed);
+        }
+        // the first iteration always completes, so there is a move to play
+        if self.previous_pv.is_empty() {
+            return;
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+        self.start_clock();
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                self.stopped = true;
+            }
+        }
+    }
+
+    fn is_draw(&self, board: &Board) -> bool {
+        if board.halfmove_clock >= 100 {
+            return true;
+        }
+
+        // positions before the last capture or pawn move cannot repeat
+        let hash = *self.history.last().unwrap();
+        self.history
+            .iter()
+            .rev()
+            .take(board.halfmove_clock as usize + 1)
+            .skip(2)
+            .step_by(2)
+            .any(|previous| *previous == hash)
+    }
+
+    fn order_moves(&self, board: &Board, moves: &mut [Move], ply: usize, tt_move: Option<Move>) {
+        let pv_move = self.previous_pv.get(ply).copied();
+        moves.sort_by_key(|m| {
+            let score = if Some(*m) == pv_move {
+                1_000_000
+            } else if Some(*m) == tt_move {
+                500_000
+            } else if m.capture != Piece::EMPTY {
+                // most valuable victim, least valuable attacker
+                10_000 + 10 * piece_value(m.capture) - piece_value(board.pieces[m.from])
+            } else if m.promote_to != Piece::EMPTY {
+                5_000 + piece_value(m.promote_to)
+            } else {
+                self.quiet_history[m.from][m.to]
+            };
+            -score
+        });
+    }
+
+    // rewards the quiet move that caused a cutoff and punishes the ones tried before it
+    fn update_history(&mut self, m: Move, tried: &[Move], depth: usize) {
+        let bonus = (depth * depth) as isize;
+        for quiet in tried {
+            let score = &mut self.quiet_history[quiet.from][quiet.to];
+            *score = (*score - bonus).max(-HISTORY_MAX);
+        }
+
+        let score = &mut self.quiet_history[m.from][m.to];
+        *score += bonus;
+        if *score >= HISTORY_MAX {
+            self.quiet_history.iter_mut().flatten().for_each(|score| *score /= 2);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn alpha_beta(&mut self, board: &Board, depth: usize, ply: usize, mut alpha: isize, beta: isize, pv: &mut Vec<Move>, null_move: bool) -> isize {
+        self.nodes += 1;
+        self.check_time();
+
+        self.history.push(board.hash());
+        let score = self.alpha_beta_node(board, depth, ply, &mut alpha, beta, pv, null_move);
+        self.history.pop();
+
+        score
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn alpha_beta_node(&mut self, board: &Board, depth: usize, ply: usize, alpha: &mut isize, beta: isize, pv: &mut Vec<Move>, null_move: bool) -> isize {
+        if ply > 0 && self.is_draw(board) {
+            return 0;
+        }
+        if let Some(outcome) = board.variant_outcome() {
+            return outcome_score(outcome, ply);
+        }
+        // distance to mate tables give exact mate scores, so they are probed first
+        if ply > 0 {
+            if let Some(dtm) = self.dtm.as_ref().and_then(|tables| tables.probe(board)) {
+                self.tbhits += 1;
+                return match dtm {
+                    Dtm::WIN(plies) => MATE - (ply + plies) as isize,
+                    Dtm::LOSS(plies) => -MATE + (ply + plies) as isize,
+                    Dtm::DRAW => 0,
+                };
+            }
+        }
+        // tablebases assume a fresh 50-move counter, so only probe right after captures and pawn moves
+        if ply > 0 && board.halfmove_clock == 0 {
+            if let Some(wdl) = self.tablebase.as_ref().and_then(|tablebase| tablebase.probe_wdl(board)) {
+                self.tbhits += 1;
+                return match wdl {
+                    Wdl::WIN => TB_WIN - ply as isize,
+                    Wdl::LOSS => -TB_WIN + ply as isize,
+                    _ => 0,
+                };
+            }
+        }
+        // the position is already on the history
+        if depth == 0 || ply >= MAX_DEPTH {
+            return self.quiescence_node(board, ply, *alpha, beta);
+        }
+
+        // a search that leaves out a move must neither use nor overwrite what is known about the position
+        let excluded = self.excluded.take();
+        let hash = *self.history.last().unwrap();
+        let entry = if excluded.is_none() { self.tt.probe(hash) } else { None };
+        // nodes searched with an open window make up the principal variation, they are never cut short
+        let pv_node = beta - *alpha > 1;
+        if let Some(entry) = entry.filter(|entry| ply > 0 && !pv_node && entry.depth >= depth) {
+            let score = score_from_tt(entry.score, ply);
+            match entry.bound {
+                Bound::LOWER | Bound::EXACT if score >= beta => return score,
+                Bound::UPPER | Bound::EXACT if score <= *alpha => return score,
+                _ => {}
+            }
+        }
+
+        let in_check = board.in_check();
+        let eval = if in_check { -INFINITY } else { self.evaluate(board) };
+        // prune on the evaluation only while no mate is in sight
+        let selective = ply > 0 && !in_check && excluded.is_none() && beta.abs() < TB_WIN && alpha.abs() < TB_WIN;
+
+        if selective && !pv_node && self.pruning.reverse_futility && depth <= 6 && eval - REVERSE_FUTILITY_MARGIN * depth as isize >= beta {
+            return eval;
+        }
+
+        // if passing still beats beta, a real move will too, except in zugzwang and where captures are compulsory
+        if selective && !pv_node && self.pruning.null_move && null_move && depth >= 3 && eval >= beta && board.variant != Variant::ANTICHESS {
+            let pieces = non_pawn_pieces(board);
+            if pieces > 0 {
+                let reduction = if depth > 6 { 3 } else { 2 };
+                // repetitions do not reach back across a null move
+                let passed = Board { next: inverse_color(board.next), en_passant: -1, halfmove_clock: 0, ..*board };
+                let child_depth = depth.saturating_sub(reduction + 1);
+                self.line.push(None);
+                let score = -self.alpha_beta(&passed, child_depth, ply + 1, -beta, -beta + 1, &mut Vec::new(), false);
+                self.line.pop();
+                if self.stopped {
+                    return 0;
+                }
+
+                if score >= beta {
+                    // with few pieces left zugzwang is likely, so the cutoff is verified by a reduced search without passing
+                    if pieces > 2 {
+                        return beta;
+                    }
+                    let mut verify_alpha = beta - 1;
+                    let score = self.alpha_beta_node(board, child_depth.max(1), ply, &mut verify_alpha, beta, &mut Vec::new(), false);
+                    if self.stopped {
+                        return 0;
+                    }
+                    if score >= beta {
+                        return beta;
+                    }
+                }
+            }
+        }
+
+        let mut moves = board.generate_legal();
+        if moves.is_empty() {
+            // checkmate is worse the earlier it happens, stalemate is a draw
+            return if in_check { -MATE + ply as isize } else { 0 };
+        }
+        let tt_move = entry.and_then(|entry| moves.iter().copied().find(|m| entry.is_move(*m)));
+        self.order_moves(board, &mut moves, ply, tt_move);
+        if let Some(excluded) = excluded {
+            moves.retain(|m| *m != excluded);
+        }
+        if ply == 0 {
+            moves.retain(|m| !self.root_excluded.contains(m) && (self.root_moves.is_empty() || self.root_moves.contains(m)));
+        }
+
+        // the table move is singular if every other move falls well short of its score, then it is searched deeper
+        let mut singular = false;
+        if let (Some(m), Some(entry)) = (tt_move, entry) {
+            let score = score_from_tt(entry.score, ply);
+            if ply > 0 && depth >= SINGULAR_DEPTH && entry.depth + 3 >= depth && entry.bound != Bound::UPPER && score.abs() < TB_WIN {
+                let singular_beta = score - SINGULAR_MARGIN * depth as isize;
+                let mut singular_alpha = singular_beta - 1;
+                self.excluded = Some(m);
+                let score = self.alpha_beta_node(board, (depth - 1) / 2, ply, &mut singular_alpha, singular_beta, &mut Vec::new(), false);
+                if self.stopped {
+                    return 0;
+                }
+                singular = score < singular_beta;
+            }
+        }
+
+        let futile = selective && self.pruning.futility && depth <= 3
+            && eval + FUTILITY_MARGIN + FUTILITY_MARGIN_PER_PLY * depth as isize <= *alpha;
+        let late_moves = 3 + depth * depth;
+        let last_move = self.line.last().copied().flatten();
+
+        let alpha_start = *alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut quiets: Vec<Move> = Vec::new();
+        for (index, m) in moves.into_iter().enumerate() {
+            let child = board.apply(m);
+            let quiet = is_quiet(m);
+            let gives_check = child.in_check();
+            // moves that give check are never pruned or reduced
+            let tactical = !quiet || in_check || gives_check;
+
+            if index > 0 && !tactical && best > -TB_WIN {
+                if futile {
+                    continue;
+                }
+                if selective && self.pruning.late_move_pruning && depth <= 3 && quiets.len() >= late_moves {
+                    continue;
+                }
+            }
+
+            // checks, recaptures that even out a trade and singular moves are searched a ply deeper,
+            // while the branch has been extended by less than half the depth of the iteration
+            let recapture = m.capture != Piece::EMPTY
+                && last_move.is_some_and(|last| last.to == m.to && piece_value(last.capture) == piece_value(m.capture));
+            let extension = usize::from(self.extensions * 2 < self.root_depth && (gives_check || recapture || (singular && Some(m) == tt_move)));
+            let child_depth = depth - 1 + extension;
+
+            self.line.push(Some(m));
+            self.extensions += extension;
+            let mut child_pv = Vec::new();
+            let mut score;
+            if index == 0 {
+                score = -self.alpha_beta(&child, child_depth, ply + 1, -beta, -*alpha, &mut child_pv, true);
+            } else {
+                // later moves only have to be proven worse than the first, which a null window does cheaply
+                let mut reduction = 0;
+                if self.pruning.late_move_reductions && depth >= 3 && index >= 3 && !tactical {
+                    reduction = late_move_reduction(depth, index);
+                    if self.quiet_history[m.from][m.to] > 0 {
+                        reduction = reduction.saturating_sub(1);
+                    }
+                    reduction = reduction.min(child_depth - 1);
+                }
+                score = -self.alpha_beta(&child, child_depth - reduction, ply + 1, -*alpha - 1, -*alpha, &mut child_pv, true);
+                // a reduced search that beats alpha is repeated in full, a null window one that does with the full window
+                if score > *alpha && reduction > 0 {
+                    child_pv.clear();
+                    score = -self.alpha_beta(&child, child_depth, ply + 1, -*alpha - 1, -*alpha, &mut child_pv, true);
+                }
+                if score > *alpha && score < beta {
+                    child_pv.clear();
+                    score = -self.alpha_beta(&child, child_depth, ply + 1, -beta, -*alpha, &mut child_pv, true);
+                }
+            }
+            self.extensions -= extension;
+            self.line.pop();
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(m);
+                if score > *alpha {
+                    *alpha = score;
+                    pv.clear();
+                    pv.push(m);
+                    pv.extend(child_pv);
+                }
+                if score >= beta {
+                    if quiet {
+                        self.update_history(m, &quiets, depth);
+                    }
+                    break;
+                }
+            }
+            if quiet {
+                quiets.push(m);
+            }
+        }
+
+        if excluded.is_none() && (ply > 0 || (self.root_excluded.is_empty() && self.root_moves.is_empty())) {
+            let bound = if best >= beta {
+                Bound::LOWER
+            } else if best > alpha_start {
+                Bound::EXACT
+            } else {
+                Bound::UPPER
+            };
+            let best_move = if bound == Bound::UPPER { None } else { best_move };
+            self.tt.store(hash, depth, score_to_tt(best, ply), bound, best_move);
+        }
+
+        best
+    }
+
+    // a weaker player misjudges positions a little
+    fn evaluate(&mut self, board: &Board) -> isize {
+        let eval = board.evaluate(&mut self.pawns);
+        if self.skill.is_limited() {
+            eval + self.skill.noise(board.hash())
+        } else {
+            eval
+        }
+    }
+
+    // only captures and promotions are searched until the position is quiet, and all evasions
+    // when in check, so reduced searches still see the mates they run into
+    fn quiescence(&mut self, board: &Board, ply: usize, alpha: isize, beta: isize) -> isize {
+        self.nodes += 1;
+        self.check_time();
+
+        self.history.push(board.hash());
+        let score = self.quiescence_node(board, ply, alpha, beta);
+        self.history.pop();
+
+        score
+    }
+
+    fn quiescence_node(&mut self, board: &Board, ply: usize, mut alpha: isize, beta: isize) -> isize {
+        if ply >= MAX_DEPTH {
+            return self.evaluate(board);
+        }
+        if let Some(outcome) = board.variant_outcome() {
+            return outcome_score(outcome, ply);
+        }
+
+        let in_check = board.in_check();
+        // quiet evasions that give check back can go round in circles
+        if in_check && self.is_draw(board) {
+            return 0;
+        }
+        let mut moves = board.generate_legal();
+        if in_check && moves.is_empty() {
+            return -MATE + ply as isize;
+        }
+
+        let stand_pat = if in_check { -INFINITY } else { self.evaluate(board) };
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        if !in_check {
+            moves.retain(|m| !is_quiet(*m));
+        }
+        self.order_moves(board, &mut moves, usize::MAX, None);
+
+        let mut best = stand_pat;
+        for m in moves {
+            let score = -self.quiescence(&board.apply(m), ply + 1, -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                alpha = alpha.max(score);
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+
+        best
+    }
+}
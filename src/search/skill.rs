@@ -0,0 +1,95 @@
+use crate::random::Random;
+use crate::search::{SearchInfo, TB_WIN};
+
+pub const MAX_LEVEL: usize = 20;
+// the range `UCI_Elo` maps onto the skill levels, a rough guess at the strength of each end
+pub const MIN_ELO: usize = 800;
+pub const MAX_ELO: usize = 2600;
+
+// below full strength, at least this many lines are searched to choose from
+const LINES: usize = 4;
+
+/// A handicap for weaker play. Each step down the levels searches fewer nodes, adds more noise
+/// to the evaluation and is more willing to play a move that scored worse than the best.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Skill {
+    // 0 is the weakest, `MAX_LEVEL` full strength, in between for Elo ratings
+    level: f64,
+    seed: u64,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Skill::from_level(MAX_LEVEL)
+    }
+}
+
+impl Skill {
+    pub fn from_level(level: usize) -> Skill {
+        Skill { level: level.min(MAX_LEVEL) as f64, seed: 0 }
+    }
+
+    pub fn from_elo(elo: usize) -> Skill {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let level = (elo - MIN_ELO) as f64 / (MAX_ELO - MIN_ELO) as f64 * MAX_LEVEL as f64;
+        Skill { level, seed: 0 }
+    }
+
+    /// Varies the noise and the choice of moves from game to game.
+    pub fn with_seed(self, seed: u64) -> Skill {
+        Skill { seed, ..self }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.level < MAX_LEVEL as f64
+    }
+
+    // missing levels each halve the nodes searched
+    pub fn max_nodes(&self) -> Option<usize> {
+        self.is_limited().then(|| 2f64.powf(8.0 + self.level / 2.0) as usize)
+    }
+
+    pub fn lines(&self) -> usize {
+        if self.is_limited() { LINES } else { 1 }
+    }
+
+    // up to 8 centipawns either way per missing level, the same for a position throughout the game
+    pub fn noise(&self, hash: u64) -> isize {
+        let amplitude = ((MAX_LEVEL as f64 - self.level) * 8.0) as u64;
+        if amplitude == 0 {
+            return 0;
+        }
+        let mut random = Random::new(hash ^ self.seed);
+        random.below(2 * amplitude + 1) as isize - amplitude as isize
+    }
+
+    /// Picks one of the best lines of the position with the given hash, a line that scored worse
+    /// by the `temperature` is chosen e times less often than the best one.
+    pub fn pick(&self, hash: u64, lines: &[SearchInfo]) -> Option<SearchInfo> {
+        let best = lines.first()?;
+        if !self.is_limited() {
+            return Some(best.clone());
+        }
+
+        let temperature = 10.0 + (MAX_LEVEL as f64 - self.level) * 12.0;
+        let weights: Vec<f64> = lines
+            .iter()
+            .map(|line| {
+                // losses beyond ten pawns, mates included, all weigh the same
+                let loss = (best.score.clamp(-TB_WIN, TB_WIN) - line.score.clamp(-TB_WIN, TB_WIN)).min(1000);
+                (-(loss as f64) / temperature).exp()
+            })
+            .collect();
+
+        // not the stream the noise of the root position comes from
+        let mut random = Random::new(!hash ^ self.seed);
+        let mut choice = random.below(1 << 32) as f64 / (1u64 << 32) as f64 * weights.iter().sum::<f64>();
+        for (line, weight) in lines.iter().zip(weights) {
+            if choice < weight {
+                return Some(line.clone());
+            }
+            choice -= weight;
+        }
+        Some(best.clone())
+    }
+}
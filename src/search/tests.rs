@@ -0,0 +1,302 @@
+use std::time::Duration;
+use crate::board::fen::Fen;
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::piece::Piece;
+use crate::board::san::San;
+use crate::board::variant::Variant;
+use crate::board::zobrist::Zobrist;
+use crate::search::skill::{Skill, MAX_ELO, MAX_LEVEL, MIN_ELO};
+use crate::search::transposition::{Bound, TranspositionTable};
+use crate::search::{score_from_tt, score_to_tt, Limits, Pruning, Search, SearchInfo, INFINITY, MATE, MAX_DEPTH};
+use crate::Board;
+
+fn search(fen: &str, millis: u64) -> crate::search::SearchInfo {
+    let limits = Limits { movetime: Some(Duration::from_millis(millis)), ..Default::default() };
+    Search::new(Vec::new()).run(&Board::from_fen(fen), &limits, |_| {})
+}
+
+#[test]
+fn test_mate_in_one() {
+    let info = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2000);
+    assert_eq!(info.best_move().unwrap().to_string(), "a1a8");
+    assert_eq!(info.score, MATE - 1);
+    assert_eq!(info.mate_in(), Some(1));
+}
+
+#[test]
+fn test_mated() {
+    let info = search("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 500);
+    assert!(info.best_move().is_none());
+    assert_eq!(info.score, -MATE);
+}
+
+#[test]
+fn test_wins_hanging_queen() {
+    let info = search("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 1000);
+    assert_eq!(info.best_move().unwrap().to_string(), "d2d5");
+}
+
+#[test]
+fn test_stalemate_is_draw() {
+    let info = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 500);
+    assert!(info.best_move().is_none());
+    assert_eq!(info.score, 0);
+}
+
+#[test]
+fn test_atomic_explosion_wins() {
+    let board = Board { variant: Variant::ATOMIC, ..Board::from_fen("4k3/3p4/8/8/8/8/8/3QK3 w - - 0 1") };
+    let limits = Limits { movetime: Some(Duration::from_millis(500)), ..Default::default() };
+    let info = Search::new(Vec::new()).run(&board, &limits, |_| {});
+    assert_eq!(info.best_move().unwrap().to_string(), "d1d7");
+    assert_eq!(info.mate_in(), Some(1));
+}
+
+const NO_PRUNING: Pruning = Pruning {
+    null_move: false,
+    late_move_reductions: false,
+    reverse_futility: false,
+    futility: false,
+    late_move_pruning: false,
+};
+
+fn search_depth(fen: &str, depth: usize, pruning: Pruning) -> crate::search::SearchInfo {
+    let limits = Limits { depth: Some(depth), ..Default::default() };
+    let mut search = Search::new(Vec::new());
+    search.pruning = pruning;
+    search.run(&Board::from_fen(fen), &limits, |_| {})
+}
+
+#[test]
+fn test_pruning_keeps_quiet_mate() {
+    // 1. Ra6 is a quiet sacrifice, 1... bxa6 2. b7#
+    let fen = "kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1";
+    let switches = [
+        Pruning::default(),
+        NO_PRUNING,
+        Pruning { null_move: false, ..Default::default() },
+        Pruning { late_move_reductions: false, ..Default::default() },
+        Pruning { futility: false, reverse_futility: false, ..Default::default() },
+        Pruning { late_move_pruning: false, ..Default::default() },
+    ];
+    for pruning in switches {
+        let info = search_depth(fen, 5, pruning);
+        assert_eq!(info.best_move().unwrap().to_string(), "a1a6", "{:?}", pruning);
+        assert_eq!(info.mate_in(), Some(2), "{:?}", pruning);
+    }
+}
+
+#[test]
+fn test_pruning_saves_nodes() {
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+    let pruned = search_depth(fen, 5, Pruning::default());
+    let full = search_depth(fen, 5, NO_PRUNING);
+    assert_eq!(pruned.depth, 5);
+    assert!(pruned.nodes * 2 < full.nodes, "{} {}", pruned.nodes, full.nodes);
+}
+
+#[test]
+fn test_null_move_verification_in_zugzwang() {
+    // after 1. Rf1 black runs out of pawn moves and has to give up the rook
+    let info = search_depth("8/8/p1p5/1p5p/1P5p/8/PPP2K1p/4R1rk w - - 0 1", 8, Pruning::default());
+    assert_eq!(info.best_move().unwrap().to_string(), "e1f1");
+}
+
+#[test]
+fn test_quiescence_bounds() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/4R3/4K3 b - - 10 40");
+    // as deep as the search goes, even a check is left to the evaluation
+    let mut search = Search::new(Vec::new());
+    let eval = search.evaluate(&board);
+    assert_eq!(search.quiescence(&board, MAX_DEPTH, -INFINITY, INFINITY), eval);
+    // a check that has been evaded before is a repetition
+    let mut search = Search::new(vec![board.hash(), 0]);
+    assert_eq!(search.quiescence(&board, 2, -INFINITY, INFINITY), 0);
+    assert_eq!(search.history.len(), 2);
+}
+
+#[test]
+fn test_transposition_table() {
+    let tt = TranspositionTable::new(1);
+    assert_eq!(tt.probe(42), None);
+
+    let m = Move { from: 12, to: 4, promote_to: Piece::KNIGHT, capture: Piece::ROOK, ..Default::default() };
+    tt.store(42, 7, -MATE + 3, Bound::LOWER, Some(m));
+    let entry = tt.probe(42).unwrap();
+    assert_eq!((entry.depth, entry.score, entry.bound), (7, -MATE + 3, Bound::LOWER));
+    assert!(entry.is_move(m));
+    assert!(!entry.is_move(Move { promote_to: Piece::QUEEN, ..m }));
+    // another position in the same slot does not match
+    assert_eq!(tt.probe(42 + 65536), None);
+
+    // a later result without a move keeps the move of the earlier one
+    tt.store(42, 9, 15, Bound::UPPER, None);
+    assert!(tt.probe(42).unwrap().is_move(m));
+    tt.clear();
+    assert_eq!(tt.probe(42), None);
+
+    // mates are stored as the distance from the node
+    assert_eq!(score_from_tt(score_to_tt(MATE - 5, 3), 1), MATE - 3);
+    assert_eq!(score_from_tt(score_to_tt(-MATE + 5, 3), 1), -MATE + 3);
+    assert_eq!(score_from_tt(score_to_tt(120, 3), 1), 120);
+}
+
+#[test]
+fn test_check_extension() {
+    // 1. Qd8+ Bxd8 2. Re8# is seen from an iteration of two plies only because checks are extended
+    let board = Board::from_fen("r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1");
+    assert!(!board.in_check());
+    assert!(board.apply(board.parse_san("Qd8+").unwrap()).in_check());
+
+    let info = search_depth("r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1", 2, Pruning::default());
+    assert_eq!(info.best_move().unwrap().to_string(), "d5d8");
+    assert_eq!(info.mate_in(), Some(2));
+    // checks find the mate in three by the third ply, which does not answer a mate in one
+    let limits = Limits { mate: Some(1), ..Default::default() };
+    let board = Board::from_fen("r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1");
+    let info = Search::new(Vec::new()).run(&board, &limits, |_| {});
+    assert_eq!((info.depth, info.mate_in()), (4, Some(3)));
+}
+
+#[test]
+fn test_aspiration_window_fails_high() {
+    // the mate found in the fourth iteration lies far above the window around the previous score
+    let limits = Limits { depth: Some(4), ..Default::default() };
+    let mut reports = Vec::new();
+    let info = Search::new(Vec::new()).run(&Board::from_fen("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1"), &limits, |info| reports.push(info.clone()));
+    assert!(reports.iter().any(|report| report.depth == 4 && report.bound == Bound::LOWER));
+    assert_eq!(reports.last().unwrap().bound, Bound::EXACT);
+    assert_eq!(info.mate_in(), Some(2));
+    // checks find the mate in three by the third ply, which does not answer a mate in one
+    let limits = Limits { mate: Some(1), ..Default::default() };
+    let board = Board::from_fen("r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1");
+    let info = Search::new(Vec::new()).run(&board, &limits, |_| {});
+    assert_eq!((info.depth, info.mate_in()), (4, Some(3)));
+}
+
+#[test]
+fn test_lazy_smp() {
+    let limits = Limits { movetime: Some(Duration::from_millis(1000)), ..Default::default() };
+    let mut search = Search::new(Vec::new());
+    search.threads = 3;
+    let info = search.run(&Board::from_fen("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1"), &limits, |_| {});
+    assert_eq!(info.best_move().unwrap().to_string(), "a1a6");
+    assert_eq!(info.mate_in(), Some(2));
+    // checks find the mate in three by the third ply, which does not answer a mate in one
+    let limits = Limits { mate: Some(1), ..Default::default() };
+    let board = Board::from_fen("r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1");
+    let info = Search::new(Vec::new()).run(&board, &limits, |_| {});
+    assert_eq!((info.depth, info.mate_in()), (4, Some(3)));
+    // the helpers' nodes are counted as well
+    assert!(info.nodes > search.nodes);
+}
+
+#[test]
+fn test_multipv() {
+    // only two legal moves, so only two lines however many are asked for
+    let limits = Limits { depth: Some(3), ..Default::default() };
+    let mut search = Search::new(Vec::new());
+    search.multipv = 4;
+    let mut reports = Vec::new();
+    search.run(&Board::from_fen("7k/8/8/8/8/8/8/K6r w - - 0 1"), &limits, |info| reports.push(info.clone()));
+    let last: Vec<_> = reports.iter().filter(|info| info.depth == 3).collect();
+    assert_eq!(last.len(), 2);
+
+    let mut search = Search::new(Vec::new());
+    search.multipv = 3;
+    let mut reports = Vec::new();
+    let info = search.run(&Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1"), &limits, |info| reports.push(info.clone()));
+    let lines: Vec<_> = reports.iter().filter(|info| info.depth == 3 && info.bound == Bound::EXACT).collect();
+    assert_eq!(lines.iter().map(|info| info.multipv).collect::<Vec<_>>(), vec![1, 2, 3]);
+    // ranked by score, each with its own first move
+    assert_eq!(lines[0].best_move().unwrap().to_string(), "d2d5");
+    assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    assert!(lines[1].best_move() != lines[0].best_move() && lines[2].best_move() != lines[1].best_move());
+    assert_eq!(info.multipv, 1);
+    assert_eq!(info.best_move(), lines[0].best_move());
+}
+
+#[test]
+fn test_node_limit_is_deterministic() {
+    let board = Board::from_fen("r1bq1rk1/pp2bppp/2n2n2/3p4/3P4/2NB1N2/PP3PPP/R1BQ1RK1 w - - 0 10");
+    let limits = Limits { nodes: Some(20_000), ..Default::default() };
+    let runs: Vec<_> = (0..2)
+        .map(|_| {
+            let mut search = Search::new(Vec::new());
+            // helpers are left out of a search limited by nodes
+            search.threads = 2;
+            let info = search.run(&board, &limits, |_| {});
+            (search.nodes, info.depth, info.score, info.pv)
+        })
+        .collect();
+    assert_eq!(runs[0], runs[1]);
+    assert!((20_000..20_010).contains(&runs[0].0));
+}
+
+#[test]
+fn test_mate_limit() {
+    let limits = Limits { mate: Some(2), ..Default::default() };
+    let info = Search::new(Vec::new()).run(&Board::from_fen("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1"), &limits, |_| {});
+    assert_eq!(info.mate_in(), Some(2));
+    // checks find the mate in three by the third ply, which does not answer a mate in one
+    let limits = Limits { mate: Some(1), ..Default::default() };
+    let board = Board::from_fen("r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1");
+    let info = Search::new(Vec::new()).run(&board, &limits, |_| {});
+    assert_eq!((info.depth, info.mate_in()), (4, Some(3)));
+
+    // without a mate the search ends after a few plies
+    let limits = Limits { mate: Some(1), ..Default::default() };
+    let info = Search::new(Vec::new()).run(&Board::from_fen("r1bq1rk1/pp2bppp/2n2n2/3p4/3P4/2NB1N2/PP3PPP/R1BQ1RK1 w - - 0 10"), &limits, |_| {});
+    assert_eq!((info.depth, info.mate_in()), (4, None));
+}
+
+#[test]
+fn test_searchmoves() {
+    let board = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+    let moves: Vec<Move> = board.generate_legal().into_iter().filter(|m| m.from == 60).collect();
+    let limits = Limits { depth: Some(4), searchmoves: moves.clone(), ..Default::default() };
+    let info = Search::new(Vec::new()).run(&board, &limits, |_| {});
+    assert!(moves.contains(&info.best_move().unwrap()));
+    assert_eq!(info.depth, 4);
+}
+
+#[test]
+fn test_skill_levels_are_monotonic() {
+    assert_eq!(Skill::from_elo(MIN_ELO), Skill::from_level(0));
+    assert_eq!(Skill::from_elo(MAX_ELO), Skill::from_level(MAX_LEVEL));
+    assert!(!Skill::from_level(MAX_LEVEL).is_limited() && Skill::from_level(MAX_LEVEL).max_nodes().is_none());
+
+    let line = |score| SearchInfo { score, ..Default::default() };
+    let lines = [line(100), line(60), line(0), line(-300)];
+    let mut previous = (0, isize::MAX, 0);
+    for elo in (MIN_ELO..MAX_ELO).step_by(200) {
+        let skill = Skill::from_elo(elo);
+        let nodes = skill.max_nodes().unwrap();
+        let noise = (0..1000).map(|hash| skill.noise(hash).abs()).max().unwrap();
+        // how often the best of the lines is played over many games
+        let best = (0..500).filter(|seed| skill.with_seed(*seed).pick(0, &lines).unwrap().score == 100).count();
+        assert!(nodes > previous.0 && noise < previous.1 && best >= previous.2, "elo {}", elo);
+        previous = (nodes, noise, best);
+    }
+    assert!(previous.2 < 500);
+
+    // within a game the choice depends on the position
+    let skill = Skill::from_level(0).with_seed(1);
+    let pick = |hash| skill.pick(hash, &lines).unwrap().score;
+    assert_eq!(pick(7), pick(7));
+    assert!((0..100).any(|hash| pick(hash) != pick(0)));
+}
+
+#[test]
+fn test_weak_play_varies() {
+    // the pawns keep taking the queen from leading into a known win
+    let board = Board::from_fen("4k3/p7/8/3q4/8/8/3R3P/4K3 w - - 0 1");
+    let best_move = |skill: Skill| {
+        let mut search = Search::new(Vec::new());
+        search.skill = skill;
+        let limits = Limits { depth: Some(4), ..Default::default() };
+        search.run(&board, &limits, |_| {}).best_move().unwrap().to_string()
+    };
+    assert!((0..5).all(|seed| best_move(Skill::from_level(MAX_LEVEL).with_seed(seed)) == "d2d5"));
+    assert!((0..20).any(|seed| best_move(Skill::from_level(0).with_seed(seed)) != "d2d5"));
+}
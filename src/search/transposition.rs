@@ -0,0 +1,109 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::board::generator::Move;
+use crate::board::piece::Piece;
+
+/// Size of the table unless asked for another one.
+pub const DEFAULT_MEGABYTES: usize = 16;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Bound {
+    #[default]
+    EXACT,
+    LOWER,
+    UPPER,
+}
+
+/// What an earlier search found out about a position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub depth: usize,
+    pub score: isize,
+    pub bound: Bound,
+    // from, to, promotion and dropped piece, matched against the legal moves of the position
+    best_move: Option<(usize, usize, Piece, Piece)>,
+}
+
+impl Entry {
+    pub fn is_move(&self, m: Move) -> bool {
+        self.best_move == Some((m.from, m.to, m.promote_to, m.drop))
+    }
+}
+
+const PIECES: [Piece; 7] = [Piece::EMPTY, Piece::PAWN, Piece::KNIGHT, Piece::BISHOP, Piece::ROOK, Piece::QUEEN, Piece::KING];
+
+fn piece_code(piece: Piece) -> u64 {
+    PIECES.iter().position(|p| *p == piece).unwrap() as u64
+}
+
+// bit layout of an entry: move in 0..21, depth in 21..29, bound in 29..31, score in 32..64
+fn pack(entry: &Entry) -> u64 {
+    let m = match entry.best_move {
+        Some((from, to, promote_to, drop)) => {
+            1 | (from as u64) << 1 | (to as u64) << 7 | piece_code(promote_to) << 13 | piece_code(drop) << 17
+        }
+        None => 0,
+    };
+    let bound = match entry.bound {
+        Bound::EXACT => 0,
+        Bound::LOWER => 1,
+        Bound::UPPER => 2,
+    };
+    m | (entry.depth.min(255) as u64) << 21 | bound << 29 | (entry.score as i32 as u32 as u64) << 32
+}
+
+fn unpack(data: u64) -> Entry {
+    let best_move = (data & 1 == 1).then(|| {
+        let from = (data >> 1 & 63) as usize;
+        let to = (data >> 7 & 63) as usize;
+        (from, to, PIECES[(data >> 13 & 15) as usize], PIECES[(data >> 17 & 15) as usize])
+    });
+    let bound = match data >> 29 & 3 {
+        0 => Bound::EXACT,
+        1 => Bound::LOWER,
+        _ => Bound::UPPER,
+    };
+    Entry { depth: (data >> 21 & 255) as usize, score: (data >> 32) as u32 as i32 as isize, bound, best_move }
+}
+
+/// A hash table of search results, indexed by Zobrist hash. Each slot stores the hash XOR the
+/// data next to the data itself, so a slot torn by a concurrent write is recognized and ignored.
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let count = (megabytes * 1024 * 1024 / 16).max(1);
+        TranspositionTable { slots: (0..count).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect() }
+    }
+
+    fn slot(&self, hash: u64) -> &[AtomicU64; 2] {
+        &self.slots[(hash % self.slots.len() as u64) as usize]
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<Entry> {
+        let [key, data] = self.slot(hash);
+        let data = data.load(Ordering::Relaxed);
+        (key.load(Ordering::Relaxed) ^ data == hash && data != 0).then(|| unpack(data))
+    }
+
+    /// Stores a result, keeping the best move of an earlier search of the same position if this one has none.
+    pub fn store(&self, hash: u64, depth: usize, score: isize, bound: Bound, best_move: Option<Move>) {
+        let best_move = best_move
+            .map(|m| (m.from, m.to, m.promote_to, m.drop))
+            .or_else(|| self.probe(hash).and_then(|entry| entry.best_move));
+        let data = pack(&Entry { depth, score, bound, best_move });
+
+        let [key, slot] = self.slot(hash);
+        key.store(hash ^ data, Ordering::Relaxed);
+        slot.store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for [key, data] in &self.slots {
+            key.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+}
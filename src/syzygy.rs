@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Neg;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::piece::Piece;
+use crate::board::variant::Variant;
+use crate::{Board, Color};
+use table::{Lookup, Table, TablePiece};
+
+pub(crate) mod table;
+#[cfg(test)]
+mod tests;
+
+const WDL_SUFFIX: &str = ".rtbw";
+const DTZ_SUFFIX: &str = ".rtbz";
+
+// the order pieces are listed in table names
+pub(crate) const PIECE_LETTERS: [(Piece, char); 6] = [
+    (Piece::KING, 'K'),
+    (Piece::QUEEN, 'Q'),
+    (Piece::ROOK, 'R'),
+    (Piece::BISHOP, 'B'),
+    (Piece::KNIGHT, 'N'),
+    (Piece::PAWN, 'P'),
+];
+
+/// Win, draw or loss for the side to move. Cursed wins and blessed losses are won or lost
+/// without the 50-move rule.
+#[allow(clippy::upper_case_acronyms, non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    LOSS = -2,
+    BLESSED_LOSS = -1,
+    DRAW = 0,
+    CURSED_WIN = 1,
+    WIN = 2,
+}
+
+impl Wdl {
+    fn from_value(value: isize) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::LOSS),
+            -1 => Some(Wdl::BLESSED_LOSS),
+            0 => Some(Wdl::DRAW),
+            1 => Some(Wdl::CURSED_WIN),
+            2 => Some(Wdl::WIN),
+            _ => None,
+        }
+    }
+
+    fn signum(self) -> isize {
+        (self as isize).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_value(-(self as isize)).unwrap()
+    }
+}
+
+// the distance to zero of the move that led to a position right after a capture or pawn move
+fn dtz_before_zeroing(wdl: Wdl) -> isize {
+    match wdl {
+        Wdl::WIN => 1,
+        Wdl::CURSED_WIN => 101,
+        Wdl::BLESSED_LOSS => -101,
+        Wdl::LOSS => -1,
+        Wdl::DRAW => 0,
+    }
+}
+
+fn is_zeroing(board: &Board, m: Move) -> bool {
+    m.capture != Piece::EMPTY || board.pieces[m.from] == Piece::PAWN
+}
+
+pub(crate) fn piece_count(board: &Board) -> usize {
+    board.pieces.iter().filter(|piece| **piece != Piece::EMPTY).count()
+}
+
+// the material of both sides in table notation, like `KRP` and `KR`
+pub(crate) fn material(board: &Board) -> (String, String) {
+    let side = |color: Color| -> String {
+        PIECE_LETTERS
+            .iter()
+            .flat_map(|(piece, letter)| {
+                let count = (0..64).filter(|i| board.pieces[*i] == *piece && board.colors[*i] == color).count();
+                std::iter::repeat_n(*letter, count)
+            })
+            .collect()
+    };
+    (side(Color::WHITE), side(Color::BLACK))
+}
+
+/// Syzygy endgame tablebases, WDL tables (`.rtbw`) for wins, draws and losses and DTZ tables
+/// (`.rtbz`) for the distance to the next capture or pawn move.
+pub struct Tablebase {
+    // file name to path, tables are only read on first use
+    files: HashMap<String, PathBuf>,
+    tables: Mutex<HashMap<String, Option<Arc<Table>>>>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    /// Finds the tables in `path`, which may list several directories like the `PATH`
+    /// environment variable does.
+    pub fn open(path: &str) -> io::Result<Tablebase> {
+        let mut files = HashMap::new();
+        for directory in std::env::split_paths(path) {
+            for entry in fs::read_dir(directory)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.ends_with(WDL_SUFFIX) || name.ends_with(DTZ_SUFFIX) {
+                    files.insert(name, entry.path());
+                }
+            }
+        }
+
+        let max_pieces = files
+            .keys()
+            .filter(|name| name.ends_with(WDL_SUFFIX))
+            .map(|name| name.len() - WDL_SUFFIX.len() - 1)
+            .max()
+            .unwrap_or(0);
+
+        Ok(Tablebase { files, tables: Mutex::new(HashMap::new()), max_pieces })
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether a position can be probed at all, tables do not know about castling or variants.
+    pub fn covers(&self, board: &Board) -> bool {
+        let count = piece_count(board);
+        (count == 2 || count <= self.max_pieces) && !board.castling.iter().any(|right| *right)
+            && board.variant == Variant::STANDARD
+    }
+
+    // tables that cannot be read are remembered as missing
+    fn table(&self, file_name: &str) -> Option<Arc<Table>> {
+        let mut tables = self.tables.lock().unwrap();
+        if let Some(table) = tables.get(file_name) {
+            return table.clone();
+        }
+
+        let table = self.files.get(file_name).and_then(|path| {
+            let bytes = fs::read(path).ok()?;
+            let dtz = file_name.ends_with(DTZ_SUFFIX);
+            let name = &file_name[..file_name.len() - WDL_SUFFIX.len()];
+            Table::parse(bytes, name, dtz).ok().map(Arc::new)
+        });
+        tables.insert(file_name.to_string(), table.clone());
+        table
+    }
+
+    // The table for the material of a position, with the pieces as the table sees them and the
+    // side to move. Tables are stored with the stronger side as white, so positions where black
+    // is stronger are probed with colors swapped and the board flipped.
+    fn find_table(&self, board: &Board, suffix: &str) -> Option<(Arc<Table>, Vec<TablePiece>, usize)> {
+        let (white, black) = material(board);
+        let (table, flip) = match self.table(&format!("{}v{}{}", white, black, suffix)) {
+            Some(table) => (table, white == black && board.next == Color::BLACK),
+            None => (self.table(&format!("{}v{}{}", black, white, suffix))?, true),
+        };
+
+        let pieces = (0..64)
+            .filter(|i| board.pieces[*i] != Piece::EMPTY)
+            .map(|i| {
+                let kind = match board.pieces[i] {
+                    Piece::PAWN => 1,
+                    Piece::KNIGHT => 2,
+                    Piece::BISHOP => 3,
+                    Piece::ROOK => 4,
+                    Piece::QUEEN => 5,
+                    _ => 6,
+                };
+                let black = (board.colors[i] == Color::BLACK) != flip;
+                // tables number squares from a1, the board from a8
+                let square = if flip { i } else { i ^ 56 };
+                (kind | if black { 8 } else { 0 }, square)
+            })
+            .collect();
+        let side = (flip != (board.next == Color::BLACK)) as usize;
+
+        Some((table, pieces, side))
+    }
+
+    fn probe_wdl_table(&self, board: &Board) -> Option<Wdl> {
+        if piece_count(board) == 2 {
+            return Some(Wdl::DRAW);
+        }
+        let (table, pieces, side) = self.find_table(board, WDL_SUFFIX)?;
+        Wdl::from_value(table.wdl(&pieces, side)?)
+    }
+
+    fn probe_dtz_table(&self, board: &Board, wdl: Wdl) -> Option<Lookup> {
+        let (table, pieces, side) = self.find_table(board, DTZ_SUFFIX)?;
+        table.dtz(&pieces, side, wdl as isize)
+    }
+
+    // Tables store arbitrary values where a capture (or, for DTZ, a pawn move) is best, so those
+    // moves are searched first. The flag is set if such a zeroing move is best.
+    fn search(&self, board: &Board, pawn_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = board.generate_legal();
+        let mut best = Wdl::LOSS;
+        let mut searched = 0;
+        for m in &moves {
+            if m.capture == Piece::EMPTY && !(pawn_moves && board.pieces[m.from] == Piece::PAWN) {
+                continue;
+            }
+            searched += 1;
+
+            let (value, _) = self.search(&board.apply(*m), false)?;
+            let value = -value;
+            if value > best {
+                best = value;
+                if value == Wdl::WIN {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // the table cannot be trusted if all moves are zeroing moves, for example with en passant
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched { best } else { self.probe_wdl_table(board)? };
+        if best >= value {
+            return Some((best, best > Wdl::DRAW || all_searched));
+        }
+
+        Some((value, false))
+    }
+
+    /// The result of a position for the side to move, `None` if it is not in the tables.
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.search(board, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Plies to the next capture or pawn move with best play, positive if the side to move wins
+    /// and 0 for draws. Results that are spoiled by the 50-move rule are 100 plies further away.
+    pub fn probe_dtz(&self, board: &Board) -> Option<isize> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.dtz(board)
+    }
+
+    fn dtz(&self, board: &Board) -> Option<isize> {
+        let (wdl, zeroing) = self.search(board, true)?;
+        if wdl == Wdl::DRAW {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        match self.probe_dtz_table(board, wdl)? {
+            Lookup::Value(dtz) => {
+                let cursed = wdl == Wdl::CURSED_WIN || wdl == Wdl::BLESSED_LOSS;
+                Some((dtz + if cursed { 100 } else { 0 }) * wdl.signum())
+            }
+            Lookup::OtherSide => {
+                // the table stores the other side to move, so look one ply ahead
+                let mut best = isize::MAX;
+                for m in board.generate_legal() {
+                    let zeroing = is_zeroing(board, m);
+                    let after = board.apply(m);
+                    let mut dtz = if zeroing {
+                        -dtz_before_zeroing(self.search(&after, false)?.0)
+                    } else {
+                        -self.dtz(&after)?
+                    };
+
+                    if dtz == 1 && after.is_check(after.next) && after.generate_legal().is_empty() {
+                        best = 1;
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < best && dtz.signum() == wdl.signum() {
+                        best = dtz;
+                    }
+                }
+
+                Some(if best == isize::MAX { -1 } else { best })
+            }
+        }
+    }
+
+    /// The move that keeps the best result and makes the most progress towards it, with its
+    /// distance to zero. Winning moves are those with the smallest positive distance, losing
+    /// positions are dragged out as long as possible.
+    pub fn best_move(&self, board: &Board) -> Option<(Move, isize)> {
+        if !self.covers(board) {
+            return None;
+        }
+
+        let mut best: Option<(Move, isize)> = None;
+        for m in board.generate_legal() {
+            let after = board.apply(m);
+            let mut dtz = if after.halfmove_clock == 0 {
+                dtz_before_zeroing(-self.search(&after, false)?.0)
+            } else {
+                let dtz = -self.probe_dtz(&after)?;
+                dtz + dtz.signum()
+            };
+            if dtz == 2 && after.is_check(after.next) && after.generate_legal().is_empty() {
+                dtz = 1;
+            }
+
+            // wins by distance, then draws, then losses by distance
+            let rank = |dtz: isize| match dtz.signum() {
+                1 => (2, -dtz),
+                0 => (1, 0),
+                _ => (0, -dtz),
+            };
+            if best.is_none_or(|(_, best)| rank(dtz) > rank(best)) {
+                best = Some((m, dtz));
+            }
+        }
+
+        best
+    }
+}
@@ -0,0 +1,731 @@
+use std::io;
+use std::sync::OnceLock;
+
+// Squares in this file are numbered like the tables do, a1 = 0 and h8 = 63. Pieces are the
+// nibbles the tables store, 1 to 6 for pawn to king plus 8 for black.
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// flags of the file header
+const SPLIT: u8 = 1;
+const HAS_PAWNS: u8 = 2;
+
+// flags of a single subtable
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+const PAWN: u8 = 1;
+
+/// A piece as stored in the tables and its square.
+pub type TablePiece = (u8, usize);
+
+/// Result of a DTZ lookup, DTZ tables only store one side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lookup {
+    Value(isize),
+    OtherSide,
+}
+
+// index tables shared by all tables, see `indexes()`
+struct Indexes {
+    binomial: [[u64; 64]; 7],
+    // squares below the a1-h8 diagonal to 0..27
+    map_b1h1h7: [u64; 64],
+    // the a1-d1-d4 triangle to 0..9, diagonal squares last
+    map_a1d1d4: [u64; 64],
+    // the 462 legal placements of two kings with the first one in the triangle
+    map_kk: [[u64; 64]; 10],
+    // pawn squares to 0..47, the leading pawn is the one with the highest value
+    map_pawns: [u64; 64],
+    lead_pawn_idx: [[u64; 64]; 7],
+    lead_pawns_size: [[u64; 4]; 7],
+}
+
+fn file_of(square: usize) -> usize {
+    square & 7
+}
+
+fn rank_of(square: usize) -> usize {
+    square >> 3
+}
+
+// distance from the a1-h8 diagonal, negative below it
+fn off_diagonal(square: usize) -> isize {
+    rank_of(square) as isize - file_of(square) as isize
+}
+
+fn is_adjacent_or_same(a: usize, b: usize) -> bool {
+    rank_of(a).abs_diff(rank_of(b)) <= 1 && file_of(a).abs_diff(file_of(b)) <= 1
+}
+
+fn indexes() -> &'static Indexes {
+    static INDEXES: OnceLock<Indexes> = OnceLock::new();
+    INDEXES.get_or_init(|| {
+        let mut ix = Indexes {
+            binomial: [[0; 64]; 7],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; 7],
+            lead_pawns_size: [[0; 4]; 7],
+        };
+
+        for n in 0..64 {
+            for k in 0..7 {
+                ix.binomial[k][n] = match (k, n) {
+                    (0, _) => 1,
+                    (_, 0) => 0,
+                    _ => ix.binomial[k - 1][n - 1] + ix.binomial[k][n - 1],
+                };
+            }
+        }
+
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                ix.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        let triangle = [0, 1, 2, 3, 8, 9, 10, 11, 16, 17, 18, 19, 24, 25, 26, 27];
+        let mut code = 0;
+        for &square in triangle.iter().filter(|square| off_diagonal(**square) < 0) {
+            ix.map_a1d1d4[square] = code;
+            code += 1;
+        }
+        for &square in triangle.iter().filter(|square| off_diagonal(**square) == 0) {
+            ix.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for idx in 0..10 {
+            // b1 is mapped to 0, so the other squares mapped to 0 are outside the triangle
+            for first in (0..28).filter(|square| ix.map_a1d1d4[*square] == idx && (idx != 0 || *square == 1)) {
+                for second in 0..64 {
+                    if is_adjacent_or_same(first, second) || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        ix.map_kk[idx as usize][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            ix.map_kk[idx as usize][second] = code;
+            code += 1;
+        }
+
+        let mut available = 47;
+        for count in 1..7 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if count == 1 {
+                        ix.map_pawns[square] = available;
+                        ix.map_pawns[square ^ 7] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+                    ix.lead_pawn_idx[count][square] = idx;
+                    idx += ix.binomial[count - 1][ix.map_pawns[square] as usize];
+                }
+                ix.lead_pawns_size[count][file] = idx;
+            }
+        }
+
+        ix
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+struct PairsData {
+    flags: u8,
+    pieces: Vec<u8>,
+    group_len: Vec<usize>,
+    // one more than `group_len`, the last one is the size of the table
+    group_idx: Vec<u64>,
+    block_size: usize,
+    span: u64,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_lengths: usize,
+    block_length_size: usize,
+    blocks: usize,
+    // the stored value for single valued tables
+    min_sym_len: u8,
+    lowest_sym: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u32>,
+    btree: usize,
+    data: usize,
+    // byte offsets of the four DTZ value maps
+    map_idx: [usize; 4],
+}
+
+/// A WDL or DTZ table file, fully read into memory.
+pub struct Table {
+    bytes: Vec<u8>,
+    dtz: bool,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // the same material on both sides
+    symmetric: bool,
+    // pawns of the leading color first
+    pawn_count: [usize; 2],
+    // subtables by pawn file and side to move
+    subtables: Vec<Vec<PairsData>>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid tablebase: {}", message))
+}
+
+impl Table {
+    /// Parses a table, `name` is the material of the file name like `KRPvKR`.
+    pub fn parse(bytes: Vec<u8>, name: &str, dtz: bool) -> io::Result<Table> {
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if bytes.len() < 5 || bytes[0..4] != magic {
+            return Err(invalid("wrong magic"));
+        }
+
+        let Some((white, black)) = name.split_once('v') else { return Err(invalid("bad name")) };
+        let pieces = |side: &str, letter: char| side.chars().filter(|c| *c == letter).count();
+        let white_pawns = pieces(white, 'P');
+        let black_pawns = pieces(black, 'P');
+        let has_unique_pieces = [white, black].iter().any(|side| "QRBNP".chars().any(|letter| pieces(side, letter) == 1));
+        // the side with fewer pawns leads, that compresses better
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        let mut table = Table {
+            bytes: Vec::new(),
+            dtz,
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            symmetric: white == black,
+            pawn_count: if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] },
+            subtables: Vec::new(),
+        };
+        if table.piece_count > 7 {
+            return Err(invalid("too many pieces"));
+        }
+
+        let flags = bytes[4];
+        if (flags & HAS_PAWNS != 0) != table.has_pawns || (!dtz && (flags & SPLIT != 0) == table.symmetric) {
+            return Err(invalid("header does not match the material"));
+        }
+
+        table.bytes = bytes;
+        table.read_subtables()?;
+        Ok(table)
+    }
+
+    fn byte(&self, pos: usize) -> io::Result<u8> {
+        self.bytes.get(pos).copied().ok_or_else(|| invalid("unexpected end of file"))
+    }
+
+    fn u16_le(&self, pos: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes.get(pos..pos + 2)?.try_into().ok()?))
+    }
+
+    fn u32_le(&self, pos: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes.get(pos..pos + 4)?.try_into().ok()?))
+    }
+
+    fn u32_be(&self, pos: usize) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes.get(pos..pos + 4)?.try_into().ok()?))
+    }
+
+    fn u64_be(&self, pos: usize) -> Option<u64> {
+        Some(u64::from_be_bytes(self.bytes.get(pos..pos + 8)?.try_into().ok()?))
+    }
+
+    fn read_subtables(&mut self) -> io::Result<()> {
+        // symmetric tables and DTZ tables only store one side to move
+        let sides = if self.dtz || self.symmetric { 1 } else { 2 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+
+        let mut pos = 5;
+        for file in 0..files {
+            let first = self.byte(pos)?;
+            let second = if both_pawns { self.byte(pos + 1)? } else { 0xff };
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            pos += if both_pawns { 2 } else { 1 };
+
+            let mut subtables = vec![PairsData::default(); sides];
+            for _ in 0..self.piece_count {
+                let byte = self.byte(pos)?;
+                for (side, data) in subtables.iter_mut().enumerate() {
+                    data.pieces.push(if side == 0 { byte & 0xf } else { byte >> 4 });
+                }
+                pos += 1;
+            }
+            for (side, data) in subtables.iter_mut().enumerate() {
+                self.set_groups(data, order[side], file)?;
+            }
+            self.subtables.push(subtables);
+        }
+        pos += pos & 1;
+
+        let mut subtables = std::mem::take(&mut self.subtables);
+        for data in subtables.iter_mut().flatten() {
+            pos = self.set_sizes(data, pos)?;
+        }
+        if self.dtz {
+            pos = self.set_dtz_map(&mut subtables, pos)?;
+        }
+        for data in subtables.iter_mut().flatten() {
+            data.sparse_index = pos;
+            pos += data.sparse_index_size * 6;
+        }
+        for data in subtables.iter_mut().flatten() {
+            data.block_lengths = pos;
+            pos += data.block_length_size * 2;
+        }
+        if pos > self.bytes.len() {
+            return Err(invalid("unexpected end of file"));
+        }
+        for data in subtables.iter_mut().flatten().filter(|data| data.blocks > 0) {
+            pos = (pos + 0x3f) & !0x3f;
+            data.data = pos;
+            pos += data.blocks * data.block_size;
+            if pos > self.bytes.len() {
+                return Err(invalid("unexpected end of file"));
+            }
+        }
+
+        self.subtables = subtables;
+        Ok(())
+    }
+
+    // Groups pieces that are encoded together: the leading pawns, or up to three unique pieces
+    // (the two kings if there are none), then runs of identical pieces.
+    fn set_groups(&self, data: &mut PairsData, order: [u8; 2], file: usize) -> io::Result<()> {
+        let ix = indexes();
+        let mut first_len: isize = if self.has_pawns { 0 } else if self.has_unique_pieces { 3 } else { 2 };
+        data.group_len = vec![1];
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || data.pieces[i] == data.pieces[i - 1] {
+                *data.group_len.last_mut().unwrap() += 1;
+            } else {
+                data.group_len.push(1);
+            }
+        }
+
+        let groups = data.group_len.len();
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        if both_pawns && groups < 2 {
+            return Err(invalid("missing pawn group"));
+        }
+        data.group_idx = vec![0; groups + 1];
+
+        // the order in which groups are encoded is stored per table
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - data.group_len[0] - if both_pawns { data.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < groups || k == order[0] || k == order[1] {
+            if k == order[0] {
+                data.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    ix.lead_pawns_size[data.group_len[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                data.group_idx[1] = idx;
+                idx *= ix.binomial[data.group_len[1]][48 - data.group_len[0]];
+            } else if next < groups {
+                data.group_idx[next] = idx;
+                idx *= ix.binomial[data.group_len[next]][free_squares];
+                free_squares -= data.group_len[next];
+                next += 1;
+            }
+            k += 1;
+            if k > 15 {
+                return Err(invalid("bad group order"));
+            }
+        }
+        data.group_idx[groups] = idx;
+
+        Ok(())
+    }
+
+    fn set_sizes(&self, data: &mut PairsData, mut pos: usize) -> io::Result<usize> {
+        data.flags = self.byte(pos)?;
+        pos += 1;
+        if data.flags & SINGLE_VALUE != 0 {
+            data.min_sym_len = self.byte(pos)?;
+            return Ok(pos + 1);
+        }
+
+        let size = *data.group_idx.last().unwrap();
+        data.block_size = 1 << self.byte(pos)?;
+        data.span = 1 << self.byte(pos + 1)?;
+        data.sparse_index_size = size.div_ceil(data.span) as usize;
+        let padding = self.byte(pos + 2)? as usize;
+        data.blocks = self.u32_le(pos + 3).ok_or_else(|| invalid("unexpected end of file"))? as usize;
+        data.block_length_size = data.blocks + padding;
+        let max_sym_len = self.byte(pos + 7)?;
+        data.min_sym_len = self.byte(pos + 8)?;
+        pos += 9;
+        if max_sym_len < data.min_sym_len || data.min_sym_len == 0 {
+            return Err(invalid("bad symbol lengths"));
+        }
+
+        // canonical Huffman code, longer codes have lower values, so base64[len] decreases with
+        // the length and the length of a code is found by comparing against it
+        data.lowest_sym = pos;
+        let lengths = (max_sym_len - data.min_sym_len) as usize + 1;
+        let lowest = |i: usize| self.u16_le(pos + 2 * i).map(|sym| sym as u64).ok_or_else(|| invalid("unexpected end of file"));
+        data.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            data.base64[i] = (data.base64[i + 1] + lowest(i)?).wrapping_sub(lowest(i + 1)?) / 2;
+        }
+        for (i, base) in data.base64.iter_mut().enumerate() {
+            *base = base.checked_shl(64 - i as u32 - data.min_sym_len as u32).unwrap_or(0);
+        }
+        pos += lengths * 2;
+
+        // symbols are either values or pairs of symbols (recursive pairing)
+        let symbols = self.u16_le(pos).ok_or_else(|| invalid("unexpected end of file"))? as usize;
+        pos += 2;
+        data.btree = pos;
+        if pos + symbols * 3 > self.bytes.len() {
+            return Err(invalid("unexpected end of file"));
+        }
+        data.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                data.symlen[sym] = self.set_symlen(data, sym, &mut visited)?;
+            }
+        }
+
+        Ok(pos + symbols * 3 + (symbols & 1))
+    }
+
+    // number of values a symbol expands to, minus one
+    fn set_symlen(&self, data: &mut PairsData, sym: usize, visited: &mut [bool]) -> io::Result<u32> {
+        visited[sym] = true;
+        let (left, right) = self.children(data.btree, sym);
+        if right == 0xfff {
+            return Ok(0);
+        }
+        if left >= visited.len() || right >= visited.len() {
+            return Err(invalid("bad symbol tree"));
+        }
+
+        for child in [left, right] {
+            if !visited[child] {
+                data.symlen[child] = self.set_symlen(data, child, visited)?;
+            }
+        }
+        Ok(data.symlen[left] + data.symlen[right] + 1)
+    }
+
+    // two 12 bit symbols packed into three bytes
+    fn children(&self, btree: usize, sym: usize) -> (usize, usize) {
+        let lr = &self.bytes[btree + 3 * sym..btree + 3 * sym + 3];
+        let left = ((lr[1] as usize & 0xf) << 8) | lr[0] as usize;
+        let right = ((lr[2] as usize) << 4) | (lr[1] as usize >> 4);
+        (left, right)
+    }
+
+    fn set_dtz_map(&self, subtables: &mut [Vec<PairsData>], mut pos: usize) -> io::Result<usize> {
+        for data in subtables.iter_mut().map(|sides| &mut sides[0]) {
+            if data.flags & MAPPED == 0 {
+                continue;
+            }
+            if data.flags & WIDE != 0 {
+                pos += pos & 1;
+                for i in 0..4 {
+                    data.map_idx[i] = pos + 2;
+                    pos += 2 * self.u16_le(pos).ok_or_else(|| invalid("unexpected end of file"))? as usize + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    data.map_idx[i] = pos + 1;
+                    pos += self.byte(pos)? as usize + 1;
+                }
+            }
+        }
+
+        Ok(pos + (pos & 1))
+    }
+
+    // tables with pawns are split by the file of the leading pawn
+    fn lead_file(&self, pieces: &[TablePiece], side: usize) -> usize {
+        if !self.has_pawns {
+            return 0;
+        }
+
+        let sides = &self.subtables[0];
+        let lead = sides.get(side).unwrap_or(&sides[0]).pieces[0];
+        let ix = indexes();
+        pieces.iter()
+            .filter(|(piece, _)| *piece == lead)
+            .max_by_key(|(_, square)| ix.map_pawns[*square])
+            .map(|(_, square)| file_of(*square).min(7 - file_of(*square)))
+            .unwrap_or(0)
+    }
+
+    fn encode(&self, data: &PairsData, pieces: &[TablePiece]) -> Option<u64> {
+        let ix = indexes();
+        let count = pieces.len();
+        if count != self.piece_count {
+            return None;
+        }
+
+        let mut codes: Vec<u8> = Vec::with_capacity(count);
+        let mut squares: Vec<usize> = Vec::with_capacity(count);
+        let mut lead_pawns = 0;
+        if self.has_pawns {
+            let lead = data.pieces[0];
+            if lead & 7 != PAWN {
+                return None;
+            }
+            for (piece, square) in pieces.iter().filter(|(piece, _)| *piece == lead) {
+                codes.push(*piece);
+                squares.push(*square);
+            }
+            lead_pawns = squares.len();
+            let first = (0..lead_pawns).max_by_key(|i| ix.map_pawns[squares[*i]])?;
+            squares.swap(0, first);
+        }
+        for (piece, square) in pieces.iter().filter(|(piece, _)| !self.has_pawns || *piece != data.pieces[0]) {
+            codes.push(*piece);
+            squares.push(*square);
+        }
+
+        // same order as the pieces of the table
+        for i in lead_pawns..count.saturating_sub(1) {
+            if let Some(j) = (i + 1..count).find(|j| codes[*j] == data.pieces[i]) {
+                codes.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // mirror so the leading piece is on the queen side
+        if file_of(squares[0]) > 3 {
+            squares.iter_mut().for_each(|square| *square ^= 7);
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            if lead_pawns > 6 {
+                return None;
+            }
+            idx = ix.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|square| ix.map_pawns[*square]);
+            for (i, square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                idx += ix.binomial[i][ix.map_pawns[*square] as usize];
+            }
+        } else {
+            // then below the fifth rank and below the a1-h8 diagonal
+            if rank_of(squares[0]) > 3 {
+                squares.iter_mut().for_each(|square| *square ^= 56);
+            }
+            for i in 0..data.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    squares[i..].iter_mut().for_each(|square| *square = ((*square >> 3) | (*square << 3)) & 63);
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+                let adjust1 = (s1 > s0) as u64;
+                let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+                idx = if off_diagonal(s0) != 0 {
+                    (ix.map_a1d1d4[s0] * 63 + (s1 as u64 - adjust1)) * 62 + s2 as u64 - adjust2
+                } else if off_diagonal(s1) != 0 {
+                    (6 * 63 + rank_of(s0) as u64 * 28 + ix.map_b1h1h7[s1]) * 62 + s2 as u64 - adjust2
+                } else if off_diagonal(s2) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62
+                        + rank_of(s0) as u64 * 7 * 28
+                        + (rank_of(s1) as u64 - adjust1) * 28
+                        + ix.map_b1h1h7[s2]
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28
+                        + rank_of(s0) as u64 * 7 * 6
+                        + (rank_of(s1) as u64 - adjust1) * 6
+                        + (rank_of(s2) as u64 - adjust2)
+                };
+            } else {
+                idx = ix.map_kk[ix.map_a1d1d4[squares[0]] as usize][squares[1]];
+            }
+        }
+        idx *= data.group_idx[0];
+
+        // the other groups as combinations of the squares not taken by earlier groups
+        let mut start = data.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        for next in 1..data.group_len.len() {
+            let len = data.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|other| square > **other).count();
+                let skipped = if remaining_pawns { 8 } else { 0 };
+                n += ix.binomial[i + 1][(square - adjust).checked_sub(skipped)?];
+            }
+            remaining_pawns = false;
+            idx += n * data.group_idx[next];
+            start += len;
+        }
+
+        Some(idx)
+    }
+
+    /// The index of a position and the size of the subtable it is in, for `side` to move.
+    #[cfg(test)]
+    pub fn index(&self, pieces: &[TablePiece], side: usize) -> Option<(u64, u64)> {
+        let data = self.subtable(pieces, side)?;
+        Some((self.encode(data, pieces)?, *data.group_idx.last()?))
+    }
+
+    fn subtable(&self, pieces: &[TablePiece], side: usize) -> Option<&PairsData> {
+        let sides = &self.subtables[self.lead_file(pieces, side)];
+        sides.get(side).or(sides.first())
+    }
+
+    fn decompress(&self, data: &PairsData, idx: u64) -> Option<u16> {
+        if data.flags & SINGLE_VALUE != 0 {
+            return Some(data.min_sym_len as u16);
+        }
+
+        // the sparse index points into the middle of every span, walk to the block holding idx
+        let k = (idx / data.span) as usize;
+        if k >= data.sparse_index_size {
+            return None;
+        }
+        let entry = data.sparse_index + 6 * k;
+        let mut block = self.u32_le(entry)? as usize;
+        let mut offset = self.u16_le(entry + 4)? as i64 + (idx % data.span) as i64 - (data.span / 2) as i64;
+
+        let block_length = |block: usize| -> Option<i64> {
+            if block >= data.block_length_size {
+                return None;
+            }
+            self.u16_le(data.block_lengths + 2 * block).map(|length| length as i64)
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        let mut ptr = data.data + block * data.block_size;
+        let mut buffer = self.u64_be(ptr)?;
+        ptr += 8;
+        let mut buffer_size = 64;
+        let min_len = data.min_sym_len as u32;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buffer < *data.base64.get(len)? {
+                len += 1;
+            }
+            sym = ((buffer - data.base64[len]).checked_shr(64 - len as u32 - min_len).unwrap_or(0)) as usize;
+            sym += self.u16_le(data.lowest_sym + 2 * len)? as usize;
+            let expands = *data.symlen.get(sym)? as i64 + 1;
+            if offset < expands {
+                break;
+            }
+
+            offset -= expands;
+            let len = len as u32 + min_len;
+            buffer = buffer.checked_shl(len).unwrap_or(0);
+            buffer_size -= len;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (self.u32_be(ptr)? as u64) << (64 - buffer_size);
+                ptr += 4;
+            }
+        }
+
+        // descend the pairs until the single value at offset is reached
+        while data.symlen[sym] != 0 {
+            let (left, right) = self.children(data.btree, sym);
+            let left_values = *data.symlen.get(left)? as i64 + 1;
+            if offset < left_values {
+                sym = left;
+            } else {
+                offset -= left_values;
+                sym = right;
+            }
+            if sym >= data.symlen.len() {
+                return None;
+            }
+        }
+
+        Some(self.children(data.btree, sym).0 as u16)
+    }
+
+    /// -2 (loss) to 2 (win) for `side` to move, the value of positions where a capture is the
+    /// best move is undefined.
+    pub fn wdl(&self, pieces: &[TablePiece], side: usize) -> Option<isize> {
+        let data = self.subtable(pieces, side)?;
+        let value = self.decompress(data, self.encode(data, pieces)?)?;
+        Some(value as isize - 2)
+    }
+
+    /// Plies or moves to the next zeroing move, depending on the flags, for a position with
+    /// the given `wdl` value.
+    pub fn dtz(&self, pieces: &[TablePiece], side: usize, wdl: isize) -> Option<Lookup> {
+        let data = &self.subtables[self.lead_file(pieces, side)][0];
+        // symmetric tables without pawns look the same for both sides
+        let both_sides = self.symmetric && !self.has_pawns;
+        if !both_sides && (data.flags & STM) as usize != side {
+            return Some(Lookup::OtherSide);
+        }
+
+        let mut value = self.decompress(data, self.encode(data, pieces)?)? as isize;
+        if data.flags & MAPPED != 0 {
+            let map = data.map_idx[[1, 3, 0, 2, 0][(wdl + 2) as usize]];
+            value = if data.flags & WIDE != 0 {
+                self.u16_le(map + 2 * value as usize)? as isize
+            } else {
+                *self.bytes.get(map + value as usize)? as isize
+            };
+        }
+
+        let in_moves = match wdl {
+            2 => data.flags & WIN_PLIES == 0,
+            -2 => data.flags & LOSS_PLIES == 0,
+            _ => true,
+        };
+        if in_moves {
+            value *= 2;
+        }
+
+        Some(Lookup::Value(value + 1))
+    }
+}
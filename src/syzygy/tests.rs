@@ -0,0 +1,383 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::board::color::inverse_color;
+use crate::board::fen::Fen;
+use crate::board::generator::MoveGenerator;
+use crate::board::piece::Piece;
+use crate::search::{Limits, Search, TB_WIN};
+use crate::syzygy::table::{Lookup, Table};
+use crate::syzygy::{Tablebase, Wdl};
+use crate::eval::kpk;
+use crate::{Board, Color};
+
+// The three-piece tables in `tables/` are checked against positions whose results are known.
+// The other tests write small tables in the same format, with made-up values but every position
+// indexed like in real files.
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+const SINGLE_VALUE: u8 = 128;
+
+const KRK_SIZE: usize = 31332;
+const VALUES_PER_BLOCK: usize = 100;
+
+// white king, white rook and black king for both sides to move
+const KRK_PIECES: [u8; 3] = [0x66, 0x44, 0xee];
+const KPK_PIECES: [u8; 3] = [0x11, 0x66, 0xee];
+
+fn header(magic: [u8; 4], flags: u8, files: usize, pieces: &[u8]) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    bytes.push(flags);
+    for _ in 0..files {
+        // encode the groups in their natural order
+        bytes.push(0x00);
+        bytes.extend(pieces);
+    }
+    if bytes.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    bytes
+}
+
+// every subtable stores a single value
+fn single_value_table(magic: [u8; 4], flags: u8, files: usize, pieces: &[u8], values: &[(u8, u8)]) -> Vec<u8> {
+    let mut bytes = header(magic, flags, files, pieces);
+    for _ in 0..files {
+        for (flags, value) in values {
+            bytes.extend([SINGLE_VALUE | flags, *value]);
+        }
+    }
+    bytes
+}
+
+// KRvK with `index % 5` stored for white to move, each value as a three bit code, and a single
+// value for black to move
+fn compressed_krk_table(black_value: u8) -> Vec<u8> {
+    let blocks = KRK_SIZE.div_ceil(VALUES_PER_BLOCK);
+    let mut bytes = header(WDL_MAGIC, 1, 1, &KRK_PIECES);
+
+    // flags, 64 byte blocks, a sparse entry every 64 values and no padding
+    bytes.extend([0, 6, 6, 0]);
+    bytes.extend((blocks as u32).to_le_bytes());
+    // all codes are three bits long, starting at symbol 0
+    bytes.extend([3, 3]);
+    bytes.extend(0u16.to_le_bytes());
+    // five symbols that each stand for their own value
+    bytes.extend(5u16.to_le_bytes());
+    for value in 0..5 {
+        bytes.extend([value, 0xf0, 0xff]);
+    }
+    bytes.push(0);
+
+    bytes.extend([SINGLE_VALUE, black_value]);
+
+    for k in 0..KRK_SIZE.div_ceil(64) {
+        let middle = k * 64 + 32;
+        bytes.extend(((middle / VALUES_PER_BLOCK) as u32).to_le_bytes());
+        bytes.extend(((middle % VALUES_PER_BLOCK) as u16).to_le_bytes());
+    }
+    for block in 0..blocks {
+        let values = VALUES_PER_BLOCK.min(KRK_SIZE - block * VALUES_PER_BLOCK);
+        bytes.extend(((values - 1) as u16).to_le_bytes());
+    }
+
+    bytes.resize(bytes.len().div_ceil(64) * 64, 0);
+    for block in 0..blocks {
+        let mut data = [0u8; 64];
+        for i in 0..VALUES_PER_BLOCK.min(KRK_SIZE - block * VALUES_PER_BLOCK) {
+            let value = (block * VALUES_PER_BLOCK + i) % 5;
+            for bit in 0..3 {
+                if value >> (2 - bit) & 1 != 0 {
+                    let pos = 3 * i + bit;
+                    data[pos / 8] |= 0x80 >> (pos % 8);
+                }
+            }
+        }
+        bytes.extend(data);
+    }
+
+    bytes
+}
+
+// the eight symmetries of the board, squares from a1
+fn transform(square: usize, symmetry: usize) -> usize {
+    let mut square = square;
+    if symmetry & 1 != 0 {
+        square ^= 7;
+    }
+    if symmetry & 2 != 0 {
+        square ^= 56;
+    }
+    if symmetry & 4 != 0 {
+        square = ((square >> 3) | (square << 3)) & 63;
+    }
+    square
+}
+
+#[test]
+fn test_index_without_pawns() {
+    let table = Table::parse(compressed_krk_table(2), "KRvK", false).unwrap();
+
+    let mut indexes = HashSet::new();
+    let mut classes = HashSet::new();
+    for king in 0..64 {
+        for rook in (0..64).filter(|rook| *rook != king) {
+            for other in (0..64).filter(|other| *other != king && *other != rook) {
+                let (idx, size) = table.index(&[(6, king), (4, rook), (14, other)], 0).unwrap();
+                assert!(idx < size);
+                assert_eq!(size, KRK_SIZE as u64);
+
+                // symmetric positions share their index
+                let symmetric: Vec<(usize, usize, usize)> = (0..8)
+                    .map(|symmetry| (transform(king, symmetry), transform(rook, symmetry), transform(other, symmetry)))
+                    .collect();
+                if (king + rook + other) % 7 == 0 {
+                    for (k, r, o) in &symmetric {
+                        assert_eq!(table.index(&[(14, *o), (6, *k), (4, *r)], 0).unwrap().0, idx);
+                    }
+                }
+
+                indexes.insert(idx);
+                classes.insert(*symmetric.iter().min().unwrap());
+            }
+        }
+    }
+
+    // and different positions never do
+    assert_eq!(indexes.len(), classes.len());
+}
+
+#[test]
+fn test_index_with_pawns() {
+    let bytes = single_value_table(WDL_MAGIC, 3, 4, &KPK_PIECES, &[(0, 4), (0, 2)]);
+    let table = Table::parse(bytes, "KPvK", false).unwrap();
+
+    let mut indexes = HashSet::new();
+    let mut positions = 0;
+    for pawn in 8..56 {
+        for king in (0..64).filter(|king| *king != pawn) {
+            for other in (0..64).filter(|other| *other != king && *other != pawn) {
+                let (idx, size) = table.index(&[(1, pawn), (6, king), (14, other)], 1).unwrap();
+                assert!(idx < size);
+                let mirrored = table.index(&[(1, pawn ^ 7), (6, king ^ 7), (14, other ^ 7)], 1).unwrap();
+                assert_eq!(mirrored.0, idx);
+
+                // tables with pawns are split by the file of the leading pawn
+                let file = (pawn % 8).min(7 - pawn % 8);
+                indexes.insert((file, idx));
+                positions += 1;
+            }
+        }
+    }
+
+    assert_eq!(indexes.len(), positions / 2);
+}
+
+#[test]
+fn test_decompress() {
+    let table = Table::parse(compressed_krk_table(0), "KRvK", false).unwrap();
+
+    for (i, (king, rook, other)) in [(4, 0, 60), (0, 63, 18), (27, 36, 9), (7, 56, 63), (12, 13, 55)].into_iter().enumerate() {
+        let pieces = [(6, king), (4, rook), (14, other)];
+        let (idx, _) = table.index(&pieces, 0).unwrap();
+        assert_eq!(table.wdl(&pieces, 0), Some((idx % 5) as isize - 2), "position {}", i);
+        assert_eq!(table.wdl(&pieces, 1), Some(-2));
+    }
+
+    for idx in (0..KRK_SIZE).step_by(97) {
+        let square = |n: usize| n % 64;
+        let pieces = [(6, square(idx)), (4, square(idx / 64 + 1)), (14, square(idx / 7 + 3))];
+        if pieces[0].1 == pieces[1].1 || pieces[1].1 == pieces[2].1 || pieces[0].1 == pieces[2].1 {
+            continue;
+        }
+        let (idx, _) = table.index(&pieces, 0).unwrap();
+        assert_eq!(table.wdl(&pieces, 0), Some((idx % 5) as isize - 2));
+    }
+}
+
+#[test]
+fn test_invalid_table() {
+    assert!(Table::parse(vec![0; 16], "KRvK", false).is_err());
+    // the header says there are pawns
+    assert!(Table::parse(single_value_table(WDL_MAGIC, 3, 1, &KRK_PIECES, &[(0, 4), (0, 0)]), "KRvK", false).is_err());
+    // truncated
+    assert!(Table::parse(compressed_krk_table(0)[..100].to_vec(), "KRvK", false).is_err());
+}
+
+#[test]
+fn test_dtz_sides() {
+    // stored for white to move in moves, so a win is counted twice
+    let bytes = single_value_table(DTZ_MAGIC, 0, 1, &KRK_PIECES, &[(0, 5)]);
+    let table = Table::parse(bytes, "KRvK", true).unwrap();
+    let pieces = [(6, 0), (4, 7), (14, 58)];
+    assert_eq!(table.dtz(&pieces, 0, 2), Some(Lookup::Value(11)));
+    assert_eq!(table.dtz(&pieces, 1, -2), Some(Lookup::OtherSide));
+}
+
+// KRvK where white to move always wins and black to move always loses
+fn krk_tablebase(name: &str) -> Tablebase {
+    let directory: PathBuf = std::env::temp_dir().join(name);
+    std::fs::create_dir_all(&directory).unwrap();
+    let wdl = single_value_table(WDL_MAGIC, 1, 1, &KRK_PIECES, &[(0, 4), (0, 0)]);
+    std::fs::write(directory.join("KRvK.rtbw"), wdl).unwrap();
+    let dtz = single_value_table(DTZ_MAGIC, 0, 1, &KRK_PIECES, &[(0, 5)]);
+    std::fs::write(directory.join("KRvK.rtbz"), dtz).unwrap();
+
+    Tablebase::open(directory.to_str().unwrap()).unwrap()
+}
+
+#[test]
+fn test_probe_wdl() {
+    let tablebase = krk_tablebase("rusty-syzygy-wdl");
+    assert_eq!(tablebase.len(), 2);
+
+    let probe = |fen: &str| tablebase.probe_wdl(&Board::from_fen(fen));
+    assert_eq!(probe("2k5/8/8/8/8/8/8/K6R w - - 0 1"), Some(Wdl::WIN));
+    assert_eq!(probe("2k5/8/8/8/8/8/8/K6R b - - 0 1"), Some(Wdl::LOSS));
+    // black is stronger, so the board is flipped
+    assert_eq!(probe("k6r/8/8/8/8/8/8/2K5 b - - 0 1"), Some(Wdl::WIN));
+    assert_eq!(probe("k6r/8/8/8/8/8/8/2K5 w - - 0 1"), Some(Wdl::LOSS));
+    // capturing the rook draws, whatever the table says
+    assert_eq!(probe("8/8/8/8/8/8/6kR/K7 b - - 0 1"), Some(Wdl::DRAW));
+    assert_eq!(probe("8/8/8/8/8/2k5/8/K7 w - - 0 1"), Some(Wdl::DRAW));
+
+    assert_eq!(probe("2k5/8/8/8/8/8/8/K6Q w - - 0 1"), None);
+    assert_eq!(probe("2k5/8/8/8/8/8/8/4K2R w K - 0 1"), None);
+}
+
+#[test]
+fn test_probe_dtz() {
+    let tablebase = krk_tablebase("rusty-syzygy-dtz");
+    let probe = |fen: &str| tablebase.probe_dtz(&Board::from_fen(fen));
+
+    assert_eq!(probe("2k5/8/8/8/8/8/8/K6R w - - 0 1"), Some(11));
+    // black to move is not stored, one ply further for the best defence
+    assert_eq!(probe("2k5/8/8/8/8/8/8/K6R b - - 0 1"), Some(-12));
+    assert_eq!(probe("8/8/8/8/8/8/6kR/K7 b - - 0 1"), Some(0));
+
+    let (m, dtz) = tablebase.best_move(&Board::from_fen("2k5/8/8/8/8/8/8/K6R w - - 0 1")).unwrap();
+    assert_eq!(dtz, 13);
+    assert_ne!(m.to_string(), "h1h7");
+}
+
+#[test]
+fn test_search_cutoff() {
+    let mut search = Search::new(Vec::new());
+    search.tablebase = Some(Arc::new(krk_tablebase("rusty-syzygy-search")));
+    let limits = Limits { movetime: Some(Duration::from_millis(300)), ..Default::default() };
+    let info = search.run(&Board::from_fen("2k5/8/8/8/8/8/3n4/K2R4 w - - 0 1"), &limits, |_| {});
+
+    assert_eq!(info.best_move().unwrap().to_string(), "d1d2");
+    assert_eq!(info.score, TB_WIN - 1);
+    assert!(info.tbhits > 0);
+}
+
+fn real_tablebase() -> Tablebase {
+    Tablebase::open(concat!(env!("CARGO_MANIFEST_DIR"), "/src/syzygy/tables")).unwrap()
+}
+
+// the FEN of a position with a white king and piece and a black king, squares from a8
+fn fen(white_king: usize, piece: (char, usize), black_king: usize, next: char) -> String {
+    let mut squares = ['1'; 64];
+    squares[white_king] = 'K';
+    squares[piece.1] = piece.0;
+    squares[black_king] = 'k';
+    let rows: Vec<String> = squares.chunks(8).map(|row| row.iter().collect()).collect();
+    format!("{} {} - - 0 1", rows.join("/"), next)
+}
+
+// kings apart and the side that moved not in check
+fn is_legal(board: &Board) -> bool {
+    let kings = [Color::WHITE, Color::BLACK].map(|color| board.king_position(color) as usize);
+    let apart = (kings[0] / 8).abs_diff(kings[1] / 8) > 1 || (kings[0] % 8).abs_diff(kings[1] % 8) > 1;
+    apart && !board.is_check(inverse_color(board.next))
+}
+
+#[test]
+#[ignore = "needs KQvK, KRvK and KPvK in src/syzygy/tables"]
+fn test_real_tables() {
+    let tablebase = real_tablebase();
+    let wdl = |fen: &str| tablebase.probe_wdl(&Board::from_fen(fen));
+    let dtz = |fen: &str| tablebase.probe_dtz(&Board::from_fen(fen));
+    let best = |fen: &str| tablebase.best_move(&Board::from_fen(fen)).map(|(m, dtz)| (m.to_string(), dtz));
+
+    // mate in one
+    assert_eq!(wdl("k7/8/1K6/8/8/8/8/1Q6 w - - 0 1"), Some(Wdl::WIN));
+    assert_eq!(dtz("k7/8/1K6/8/8/8/8/1Q6 w - - 0 1"), Some(1));
+    assert_eq!(best("k7/8/1K6/8/8/8/8/1Q6 w - - 0 1"), Some(("b1b7".to_string(), 1)));
+    assert_eq!(wdl("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Some(Wdl::WIN));
+    assert_eq!(dtz("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Some(1));
+    assert_eq!(best("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Some(("h1h8".to_string(), 1)));
+    assert_eq!(wdl("k7/8/1K6/8/8/8/8/7R b - - 0 1"), Some(Wdl::LOSS));
+    assert!(dtz("k7/8/1K6/8/8/8/8/7R b - - 0 1").unwrap() < 0);
+
+    // the king takes the rook
+    assert_eq!(wdl("8/8/8/8/8/2k5/1R6/7K b - - 0 1"), Some(Wdl::DRAW));
+    assert_eq!(dtz("8/8/8/8/8/2k5/1R6/7K b - - 0 1"), Some(0));
+    assert_eq!(best("8/8/8/8/8/2k5/1R6/7K b - - 0 1").unwrap().0, "c3b2");
+
+    // stalemate, and with white to move the king steps aside and the pawn queens two moves later
+    assert_eq!(wdl("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(Wdl::DRAW));
+    assert_eq!(wdl("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1"), Some(Wdl::WIN));
+    assert!(dtz("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1").unwrap() > 0);
+    assert!(["e6d6", "e6f6"].contains(&best("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1").unwrap().0.as_str()));
+    // the opposition
+    assert_eq!(wdl("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"), Some(Wdl::WIN));
+    assert_eq!(wdl("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1"), Some(Wdl::DRAW));
+}
+
+#[test]
+#[ignore = "needs KQvK and KRvK in src/syzygy/tables"]
+fn test_real_tables_agree_with_search() {
+    // the result of a position is the best result of its moves, a capture of the last piece draws
+    let tablebase = real_tablebase();
+    for piece in ['Q', 'R'] {
+        for index in (0..64 * 64 * 64).step_by(101) {
+            let (white_king, square, black_king) = (index / 4096, index / 64 % 64, index % 64);
+            if white_king == square || square == black_king || white_king == black_king {
+                continue;
+            }
+            for next in ['w', 'b'] {
+                let board = Board::from_fen(&fen(white_king, (piece, square), black_king, next));
+                if !is_legal(&board) {
+                    continue;
+                }
+                let moves = board.generate_legal();
+                let expected = if moves.is_empty() {
+                    if board.in_check() { Wdl::LOSS } else { Wdl::DRAW }
+                } else {
+                    moves
+                        .into_iter()
+                        .map(|m| board.apply(m))
+                        .map(|after| if after.pieces.iter().filter(|piece| **piece != Piece::EMPTY).count() == 2 { Wdl::DRAW } else { -tablebase.probe_wdl(&after).unwrap() })
+                        .max()
+                        .unwrap()
+                };
+                assert_eq!(tablebase.probe_wdl(&board), Some(expected), "{}", board.to_fen());
+            }
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs KPvK in src/syzygy/tables"]
+fn test_real_tables_agree_with_bitbase() {
+    let tablebase = real_tablebase();
+    for pawn in (8..56).step_by(3) {
+        for white_king in (0..64).filter(|king| *king != pawn) {
+            for black_king in (0..64).filter(|king| *king != pawn && *king != white_king) {
+                for (next, color) in [('w', Color::WHITE), ('b', Color::BLACK)] {
+                    let board = Board::from_fen(&fen(white_king, ('P', pawn), black_king, next));
+                    if !is_legal(&board) {
+                        continue;
+                    }
+                    let won = kpk::probe(Color::WHITE, white_king, pawn, black_king, color);
+                    let expected = if won { if color == Color::WHITE { Wdl::WIN } else { Wdl::LOSS } } else { Wdl::DRAW };
+                    assert_eq!(tablebase.probe_wdl(&board), Some(expected), "{}", board.to_fen());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::board::fen::Fen;
+use crate::board::generator::{Move, MoveGenerator};
+use crate::board::variant::{Variant, VARIANTS};
+use crate::board::zobrist::Zobrist;
+use crate::book::Book;
+use crate::dtm;
+use crate::random::Random;
+use crate::search::transposition::{Bound, TranspositionTable, DEFAULT_MEGABYTES};
+use crate::search::skill::{Skill, MAX_ELO, MAX_LEVEL, MIN_ELO};
+use crate::search::{Limits, Pruning, Search, SearchInfo, MAX_MULTIPV, MAX_THREADS};
+use crate::syzygy::Tablebase;
+use crate::Board;
+
+#[cfg(test)]
+mod tests;
+
+const DEFAULT_MOVETIME: Duration = Duration::from_secs(5);
+
+const GO_KEYWORDS: [&str; 12] =
+    ["searchmoves", "ponder", "wtime", "btime", "winc", "binc", "movestogo", "depth", "nodes", "mate", "movetime", "infinite"];
+
+// shared by the command loop and the search thread
+type Output = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Finds the legal move written in UCI long algebraic notation (`e2e4`, `e7e8q`).
+pub fn parse_move(board: &Board, notation: &str) -> Option<Move> {
+    board.generate_legal().into_iter().find(|m| m.to_string() == notation)
+}
+
+pub fn format_info(info: &SearchInfo) -> String {
+    let mut score = match info.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    match info.bound {
+        Bound::LOWER => score.push_str(" lowerbound"),
+        Bound::UPPER => score.push_str(" upperbound"),
+        Bound::EXACT => {}
+    }
+    let millis = info.time.as_millis();
+    let nps = (info.nodes as u128 * 1000).checked_div(millis).unwrap_or(0);
+    let pv: Vec<String> = info.pv.iter().map(|m| m.to_string()).collect();
+
+    format!(
+        "info depth {} multipv {} score {} nodes {} nps {} tbhits {} time {} pv {}",
+        info.depth, info.multipv, score, info.nodes, nps, info.tbhits, millis, pv.join(" ")
+    )
+}
+
+pub(crate) struct Uci {
+    board: Board,
+    // hashes of the positions before `board`, for repetition detection
+    history: Vec<u64>,
+    own_book: bool,
+    best_book_move: bool,
+    chess960: bool,
+    variant: Variant,
+    book: Option<Book>,
+    tablebase: Option<Arc<Tablebase>>,
+    dtm: Option<Arc<dtm::Tablebase>>,
+    pruning: Pruning,
+    threads: usize,
+    multipv: usize,
+    // `UCI_Elo` replaces `Skill Level` while strength is limited
+    limit_strength: bool,
+    elo: usize,
+    skill_level: usize,
+    // drawn for each game, so weaker play treats a position the same way throughout it
+    skill_seed: u64,
+    // kept between moves, so the next search starts from what this one found
+    tt: Arc<TranspositionTable>,
+    random: Random,
+    out: Output,
+    // the search running in the background, with the flags to stop it and to end pondering
+    searching: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+    ponder: Arc<AtomicBool>,
+}
+
+impl Default for Uci {
+    fn default() -> Self {
+        Uci::with_output(Box::new(io::stdout()))
+    }
+}
+
+impl Uci {
+    pub(crate) fn with_output(out: Box<dyn Write + Send>) -> Self {
+        let mut random = Random::from_time();
+        Uci {
+            board: Board { ..Default::default() },
+            history: Vec::new(),
+            own_book: false,
+            best_book_move: false,
+            chess960: false,
+            variant: Variant::STANDARD,
+            book: None,
+            tablebase: None,
+            dtm: None,
+            pruning: Pruning::default(),
+            threads: 1,
+            multipv: 1,
+            limit_strength: false,
+            elo: MAX_ELO,
+            skill_level: MAX_LEVEL,
+            skill_seed: random.next_u64(),
+            tt: Arc::new(TranspositionTable::new(DEFAULT_MEGABYTES)),
+            random,
+            out: Arc::new(Mutex::new(out)),
+            searching: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Handles a single command, returns `false` once the engine should quit. Searches run in
+    /// the background, so the engine keeps answering while it thinks.
+    pub(crate) fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        let command = tokens.next();
+        match command {
+            // the best move is written before the command returns
+            Some("stop") => self.stop_search(),
+            Some("ponderhit") => self.ponder.store(false, Ordering::Relaxed),
+            // GUIs are expected to stop the search first, but do not have to
+            Some("go" | "position" | "ucinewgame" | "setoption" | "quit") => self.stop_search(),
+            _ => {}
+        }
+
+        let output = self.out.clone();
+        let mut out = output.lock().unwrap();
+        let out = &mut *out;
+        let result = match command {
+            Some("uci") => self.uci(out),
+            Some("isready") => writeln!(out, "readyok"),
+            Some("ucinewgame") => {
+                self.board = Board { chess960: self.chess960, ..self.start_position() };
+                self.history.clear();
+                self.tt.clear();
+                self.skill_seed = self.random.next_u64();
+                Ok(())
+            }
+            Some("setoption") => self.set_option(line, out),
+            Some("position") => self.position(&tokens.collect::<Vec<&str>>(), out),
+            Some("go") => self.go(&tokens.collect::<Vec<&str>>(), out),
+            Some("quit") => return false,
+            // unknown commands are ignored
+            _ => Ok(()),
+        };
+
+        result.and_then(|_| out.flush()).is_ok()
+    }
+
+    /// Waits until the search in the background is done.
+    pub(crate) fn wait(&mut self) {
+        if let Some(searching) = self.searching.take() {
+            searching.join().unwrap();
+        }
+    }
+
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    fn uci<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "id name rusty {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(out, "id author eliias")?;
+        writeln!(out, "option name OwnBook type check default false")?;
+        writeln!(out, "option name BookFile type string default <empty>")?;
+        writeln!(out, "option name Best Book Move type check default false")?;
+        writeln!(out, "option name UCI_Chess960 type check default false")?;
+        let variants: Vec<String> = VARIANTS.iter().map(|variant| format!("var {}", variant)).collect();
+        writeln!(out, "option name UCI_Variant type combo default {} {}", Variant::STANDARD, variants.join(" "))?;
+        writeln!(out, "option name SyzygyPath type string default <empty>")?;
+        writeln!(out, "option name DtmPath type string default <empty>")?;
+        writeln!(out, "option name Threads type spin default 1 min 1 max {}", MAX_THREADS)?;
+        writeln!(out, "option name MultiPV type spin default 1 min 1 max {}", MAX_MULTIPV)?;
+        writeln!(out, "option name NullMove type check default true")?;
+        writeln!(out, "option name LMR type check default true")?;
+        writeln!(out, "option name ReverseFutility type check default true")?;
+        writeln!(out, "option name Futility type check default true")?;
+        writeln!(out, "option name LateMovePruning type check default true")?;
+        writeln!(out, "option name UCI_LimitStrength type check default false")?;
+        writeln!(out, "option name UCI_Elo type spin default {} min {} max {}", MAX_ELO, MIN_ELO, MAX_ELO)?;
+        writeln!(out, "option name Skill Level type spin default {} min 0 max {}", MAX_LEVEL, MAX_LEVEL)?;
+        writeln!(out, "uciok")
+    }
+
+    fn set_option<W: Write>(&mut self, line: &str, out: &mut W) -> io::Result<()> {
+        // option names may contain spaces, so split at the keywords instead of at whitespace
+        let Some((_, rest)) = line.split_once("name") else { return Ok(()) };
+        let (name, value) = match rest.split_once(" value") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (rest.trim(), ""),
+        };
+
+        match name.to_lowercase().as_str() {
+            "ownbook" => self.own_book = value == "true",
+            "best book move" => self.best_book_move = value == "true",
+            "threads" => match value.parse::<usize>() {
+                Ok(threads) if (1..=MAX_THREADS).contains(&threads) => self.threads = threads,
+                _ => writeln!(out, "info string invalid thread count {}", value)?,
+            },
+            "multipv" => match value.parse::<usize>() {
+                Ok(lines) if (1..=MAX_MULTIPV).contains(&lines) => self.multipv = lines,
+                _ => writeln!(out, "info string invalid number of lines {}", value)?,
+            },
+            "uci_limitstrength" => self.limit_strength = value == "true",
+            "uci_elo" => match value.parse::<usize>() {
+                Ok(elo) if (MIN_ELO..=MAX_ELO).contains(&elo) => self.elo = elo,
+                _ => writeln!(out, "info string invalid elo {}", value)?,
+            },
+            "skill level" => match value.parse::<usize>() {
+                Ok(level) if level <= MAX_LEVEL => self.skill_level = level,
+                _ => writeln!(out, "info string invalid skill level {}", value)?,
+            },
+            "nullmove" => self.pruning.null_move = value == "true",
+            "lmr" => self.pruning.late_move_reductions = value == "true",
+            "reversefutility" => self.pruning.reverse_futility = value == "true",
+            "futility" => self.pruning.futility = value == "true",
+            "latemovepruning" => self.pruning.late_move_pruning = value == "true",
+            "uci_chess960" => {
+                self.chess960 = value == "true";
+                self.board.chess960 = self.chess960;
+            }
+            "uci_variant" => match value.parse::<Variant>() {
+                Ok(variant) => {
+                    self.variant = variant;
+                    self.board = Board { chess960: self.chess960, ..self.start_position() };
+                    self.history.clear();
+                }
+                Err(()) => writeln!(out, "info string unknown variant {}", value)?,
+            },
+            "bookfile" => {
+                self.book = None;
+                if !value.is_empty() && value != "<empty>" {
+                    match Book::open(value) {
+                        Ok(book) => {
+                            writeln!(out, "info string loaded {} book entries from {}", book.len(), value)?;
+                            self.book = Some(book);
+                        }
+                        Err(e) => writeln!(out, "info string cannot load book {}: {}", value, e)?,
+                    }
+                }
+            }
+            "syzygypath" => {
+                self.tablebase = None;
+                if !value.is_empty() && value != "<empty>" {
+                    match Tablebase::open(value) {
+                        Ok(tablebase) => {
+                            writeln!(out, "info string found {} tablebase files in {}", tablebase.len(), value)?;
+                            self.tablebase = Some(Arc::new(tablebase));
+                        }
+                        Err(e) => writeln!(out, "info string cannot open tablebases {}: {}", value, e)?,
+                    }
+                }
+            }
+            "dtmpath" => {
+                self.dtm = None;
+                if !value.is_empty() && value != "<empty>" {
+                    match dtm::Tablebase::open(value) {
+                        Ok(tables) => {
+                            writeln!(out, "info string loaded {} dtm tables from {}", tables.len(), value)?;
+                            self.dtm = Some(Arc::new(tables));
+                        }
+                        Err(e) => writeln!(out, "info string cannot load dtm tables {}: {}", value, e)?,
+                    }
+                }
+            }
+            _ => writeln!(out, "info string unknown option {}", name)?,
+        }
+
+        Ok(())
+    }
+
+    fn position<W: Write>(&mut self, tokens: &[&str], out: &mut W) -> io::Result<()> {
+        let moves_at = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+        let board = match tokens.first() {
+            Some(&"startpos") => self.start_position(),
+            Some(&"fen") => match Board::try_from_fen(&tokens[1..moves_at].join(" ")) {
+                Ok(board) => board,
+                Err(e) => return writeln!(out, "info string {}", e),
+            },
+            _ => return writeln!(out, "info string invalid position command"),
+        };
+
+        // castling is written as the king taking its rook in Chess960, and FENs of variants
+        // without their own fields are played by the rules `UCI_Variant` selected
+        let variant = if self.variant == Variant::STANDARD { board.variant } else { self.variant };
+        let mut board = Board { chess960: board.chess960 || self.chess960, variant, ..board };
+        // the position only changes once all moves turned out to be legal
+        let mut history = Vec::new();
+        for notation in tokens.iter().skip(moves_at + 1) {
+            match parse_move(&board, notation) {
+                Some(m) => {
+                    history.push(board.hash());
+                    board = board.apply(m);
+                }
+                None => return writeln!(out, "info string illegal move {}", notation),
+            }
+        }
+
+        self.board = board;
+        self.history = history;
+        Ok(())
+    }
+
+    fn start_position(&self) -> Board {
+        Board { variant: self.variant, ..Board::from_fen(self.variant.start_fen()) }
+    }
+
+    // books only hold positions of standard chess
+    fn book_move(&mut self) -> Option<Move> {
+        let book = self.book.as_ref().filter(|_| self.own_book && self.board.variant == Variant::STANDARD)?;
+        if self.best_book_move {
+            book.best_move(&self.board)
+        } else {
+            book.weighted_move(&self.board, &mut self.random)
+        }
+    }
+
+    fn go<W: Write>(&mut self, tokens: &[&str], out: &mut W) -> io::Result<()> {
+        let infinite = tokens.contains(&"infinite");
+        let ponder = tokens.contains(&"ponder");
+
+        let mut limits = Limits::default();
+        for pair in tokens.windows(2) {
+            let millis = pair[1].parse::<u64>().ok().map(Duration::from_millis);
+            match pair[0] {
+                "movetime" => limits.movetime = millis,
+                "wtime" => limits.wtime = millis,
+                "btime" => limits.btime = millis,
+                "winc" => limits.winc = millis,
+                "binc" => limits.binc = millis,
+                "movestogo" => limits.movestogo = pair[1].parse().ok(),
+                "depth" => limits.depth = pair[1].parse().ok(),
+                "nodes" => limits.nodes = pair[1].parse().ok(),
+                "mate" => limits.mate = pair[1].parse().ok(),
+                _ => {}
+            }
+        }
+        // the moves run up to the next keyword, illegal ones are left out
+        if let Some(at) = tokens.iter().position(|token| *token == "searchmoves") {
+            limits.searchmoves = tokens[at + 1..]
+                .iter()
+                .take_while(|token| !GO_KEYWORDS.contains(token))
+                .filter_map(|notation| parse_move(&self.board, notation))
+                .collect();
+        }
+
+        // book and tablebase moves are played at once, which analysis, pondering and a restricted search must not do
+        let limited = limits.depth.is_some() || limits.nodes.is_some() || limits.mate.is_some();
+        if !infinite && !ponder && !limited && limits.searchmoves.is_empty() {
+            if let Some(m) = self.book_move() {
+                writeln!(out, "info string book move")?;
+                return writeln!(out, "bestmove {}", m);
+            }
+            if let Some((m, dtz)) = self.tablebase.as_ref().and_then(|tablebase| tablebase.best_move(&self.board)) {
+                writeln!(out, "info string tablebase move dtz {}", dtz)?;
+                return writeln!(out, "bestmove {}", m);
+            }
+        }
+
+        // without a clock or another limit, think for a fixed time per move
+        if !infinite && !limited && limits.time_budget(self.board.next).is_none() {
+            limits.movetime = Some(DEFAULT_MOVETIME);
+        }
+
+        self.stop = Arc::new(AtomicBool::new(false));
+        self.ponder = Arc::new(AtomicBool::new(ponder));
+        let mut search = Search::new(self.history.clone());
+        search.tablebase = self.tablebase.clone();
+        search.dtm = self.dtm.clone();
+        search.pruning = self.pruning;
+        search.tt = self.tt.clone();
+        search.threads = self.threads;
+        search.multipv = self.multipv;
+        let skill = if self.limit_strength { Skill::from_elo(self.elo) } else { Skill::from_level(self.skill_level) };
+        search.skill = skill.with_seed(self.skill_seed);
+        search.stop = self.stop.clone();
+        search.ponder = self.ponder.clone();
+
+        let board = self.board;
+        let output = self.out.clone();
+        let (stop, ponder) = (self.stop.clone(), self.ponder.clone());
+        self.searching = Some(thread::spawn(move || {
+            let result = search.run(&board, &limits, |info| {
+                let mut out = output.lock().unwrap();
+                // there is nobody to tell if the output is gone
+                let _ = writeln!(out, "{}", format_info(info)).and_then(|_| out.flush());
+            });
+
+            // in infinite analysis and while pondering, the best move waits for stop or ponderhit
+            while (infinite || ponder.load(Ordering::Relaxed)) && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            let mut out = output.lock().unwrap();
+            let _ = match (result.best_move(), result.pv.get(1)) {
+                (Some(m), Some(reply)) => writeln!(out, "bestmove {} ponder {}", m, reply),
+                (Some(m), None) => writeln!(out, "bestmove {}", m),
+                (None, _) => writeln!(out, "bestmove 0000"),
+            };
+            let _ = out.flush();
+        }));
+
+        Ok(())
+    }
+}
+
+pub(crate) fn run() {
+    let mut uci = Uci::default();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !uci.handle(&line) {
+            break;
+        }
+    }
+    uci.stop_search();
+}
@@ -0,0 +1,290 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::board::fen::Fen;
+use crate::search::transposition::Bound;
+use crate::search::SearchInfo;
+use crate::uci::{format_info, parse_move, Uci};
+use crate::Board;
+
+// collects what the engine writes, from the command loop and the search thread alike
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn engine() -> (Uci, Output) {
+    let out = Output::default();
+    (Uci::with_output(Box::new(out.clone())), out)
+}
+
+// runs the commands, waits for the search to finish and returns the lines written since the last call
+fn run(uci: &mut Uci, out: &Output, commands: &[&str]) -> Vec<String> {
+    for command in commands {
+        assert!(uci.handle(command));
+    }
+    uci.wait();
+    let bytes = std::mem::take(&mut *out.0.lock().unwrap());
+    String::from_utf8(bytes).unwrap().lines().map(String::from).collect()
+}
+
+#[test]
+fn test_handshake() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["uci", "isready"]);
+    assert!(lines[0].starts_with("id name rusty"));
+    assert!(lines.contains(&"option name OwnBook type check default false".to_string()));
+    assert!(lines.contains(&"option name BookFile type string default <empty>".to_string()));
+    assert!(lines.contains(&"option name UCI_Chess960 type check default false".to_string()));
+    assert!(lines.contains(&"option name UCI_Variant type combo default chess var chess var crazyhouse var atomic \
+        var 3check var kingofthehill var racingkings var antichess var horde".to_string()));
+    assert!(lines.contains(&"option name SyzygyPath type string default <empty>".to_string()));
+    assert!(lines.contains(&"option name DtmPath type string default <empty>".to_string()));
+    assert!(lines.contains(&"option name Threads type spin default 1 min 1 max 64".to_string()));
+    assert!(lines.contains(&"option name MultiPV type spin default 1 min 1 max 256".to_string()));
+    assert!(lines.contains(&"option name NullMove type check default true".to_string()));
+    assert!(lines.contains(&"option name LateMovePruning type check default true".to_string()));
+    assert!(lines.contains(&"option name UCI_LimitStrength type check default false".to_string()));
+    assert!(lines.contains(&"option name UCI_Elo type spin default 2600 min 800 max 2600".to_string()));
+    assert!(lines.contains(&"option name Skill Level type spin default 20 min 0 max 20".to_string()));
+    assert_eq!(lines[lines.len() - 2], "uciok");
+    assert_eq!(lines[lines.len() - 1], "readyok");
+
+    assert!(!uci.handle("quit"));
+}
+
+#[test]
+fn test_position_and_go() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1 moves g1f1 g8h8", "go movetime 500"]);
+    assert!(lines.iter().any(|line| line.contains(" score mate 1 ")));
+    assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+}
+
+#[test]
+fn test_format_bounds() {
+    let info = SearchInfo { depth: 5, multipv: 1, score: 30, bound: Bound::LOWER, ..Default::default() };
+    assert!(format_info(&info).starts_with("info depth 5 multipv 1 score cp 30 lowerbound nodes 0"));
+    let info = SearchInfo { bound: Bound::UPPER, ..info };
+    assert!(format_info(&info).contains(" score cp 30 upperbound "));
+}
+
+#[test]
+fn test_illegal_position_move() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["position startpos moves e2e5"]);
+    assert_eq!(lines, vec!["info string illegal move e2e5"]);
+
+    // the position from before the command stays, not the moves up to the illegal one
+    run(&mut uci, &out, &["position startpos moves d2d4"]);
+    let (board, history) = (uci.board, uci.history.clone());
+    let lines = run(&mut uci, &out, &["position startpos moves e2e4 e7e5 e1e3"]);
+    assert_eq!(lines, vec!["info string illegal move e1e3"]);
+    assert!(uci.board == board && uci.history == history);
+}
+
+#[test]
+fn test_chess960_castling() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &[
+        "setoption name UCI_Chess960 value true",
+        "position fen 1r2k3/8/8/8/8/8/8/R5KR w HA - 0 1 moves g1a1",
+    ]);
+    assert!(lines.is_empty());
+    assert_eq!(uci.board.to_fen(), "1r2k3/8/8/8/8/8/8/2KR3R b - - 1 1");
+
+    // the classical start position castles king takes rook as well
+    run(&mut uci, &out, &["position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1h1"]);
+    assert_eq!(uci.board.to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+}
+
+#[test]
+fn test_variant() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["setoption name UCI_Variant value racingkings", "position startpos moves h2h3"]);
+    assert!(lines.is_empty());
+    assert_eq!(uci.board.to_fen(), "8/8/8/8/8/7K/krbnNBR1/qrbnNBRQ b - - 1 1");
+
+    // the FEN has no pockets, but the variant is set
+    run(&mut uci, &out, &["setoption name UCI_Variant value crazyhouse", "position fen 4k3/8/8/8/8/8/8/4K3 w - - 0 1 moves e1d1"]);
+    assert_eq!(uci.board.to_fen(), "4k3/8/8/8/8/8/8/3K4[] b - - 1 1");
+
+    let lines = run(&mut uci, &out, &["setoption name UCI_Variant value kingofthehill", "position fen 8/8/8/8/3K4/8/8/4k3 b - - 0 1", "go movetime 100"]);
+    assert_eq!(lines.last().unwrap(), "bestmove 0000");
+
+    let lines = run(&mut uci, &out, &["setoption name UCI_Variant value shogi"]);
+    assert_eq!(lines, vec!["info string unknown variant shogi"]);
+}
+
+#[test]
+fn test_pruning_options() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["setoption name NullMove value false", "setoption name LMR value false", "setoption name Futility value false"]);
+    assert!(lines.is_empty());
+    assert!(!uci.pruning.null_move && !uci.pruning.late_move_reductions && !uci.pruning.futility);
+    assert!(uci.pruning.reverse_futility && uci.pruning.late_move_pruning);
+}
+
+#[test]
+fn test_threads() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["setoption name Threads value 4", "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "go movetime 300"]);
+    assert_eq!(uci.threads, 4);
+    assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+
+    let lines = run(&mut uci, &out, &["setoption name Threads value 0"]);
+    assert_eq!(lines, vec!["info string invalid thread count 0"]);
+    assert_eq!(uci.threads, 4);
+}
+
+#[test]
+fn test_multipv() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["setoption name MultiPV value 3", "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "go movetime 500"]);
+    assert!(lines.iter().any(|line| line.contains(" multipv 1 score mate 1 ") && line.ends_with(" pv a1a8")));
+    assert!(lines.iter().any(|line| line.contains(" multipv 3 ")));
+    assert!(!lines.iter().any(|line| line.contains(" multipv 4 ")));
+    assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+}
+
+#[test]
+fn test_go_limits() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["position startpos", "go depth 2"]);
+    assert!(lines.iter().any(|line| line.starts_with("info depth 2 ")));
+    assert!(!lines.iter().any(|line| line.starts_with("info depth 3 ")));
+
+    let lines = run(&mut uci, &out, &["go depth 3 searchmoves a2a3 h2h3 e2e5 movestogo 20"]);
+    let bestmove = lines.last().unwrap();
+    assert!(bestmove.starts_with("bestmove a2a3") || bestmove.starts_with("bestmove h2h3"), "{}", bestmove);
+
+    // the same number of nodes from a fresh start gives the same search
+    let first = run(&mut uci, &out, &["ucinewgame", "position startpos moves e2e4", "go nodes 5000"]);
+    let second = run(&mut uci, &out, &["ucinewgame", "position startpos moves e2e4", "go nodes 5000"]);
+    let pv = |lines: &[String]| lines[lines.len() - 2].split(" pv ").nth(1).unwrap().to_string();
+    assert_eq!(pv(&first), pv(&second));
+    assert_eq!(first.last(), second.last());
+}
+
+fn written(out: &Output) -> String {
+    String::from_utf8(out.0.lock().unwrap().clone()).unwrap()
+}
+
+// waits for the engine to write the text, so the tests follow the protocol rather than the clock
+fn wait_for(out: &Output, text: &str) {
+    let start = Instant::now();
+    while !written(out).contains(text) {
+        assert!(start.elapsed() < Duration::from_secs(60), "no {:?} in {}", text, written(out));
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+// where the first line starting with the prefix is, to check the order of the messages
+fn position(lines: &[String], prefix: &str) -> usize {
+    lines.iter().position(|line| line.starts_with(prefix)).unwrap_or_else(|| panic!("no {:?} in {:?}", prefix, lines))
+}
+
+#[test]
+fn test_infinite_and_stop() {
+    let (mut uci, out) = engine();
+    // the mate is found at once, but the best move waits for stop
+    assert!(uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"));
+    assert!(uci.handle("go infinite"));
+    wait_for(&out, " score mate 1 ");
+    // the engine keeps answering while it searches
+    assert!(uci.handle("isready"));
+    wait_for(&out, "readyok");
+
+    let before = written(&out).lines().count();
+    let lines = run(&mut uci, &out, &["stop"]);
+    assert!(!lines[..before].iter().any(|line| line.starts_with("bestmove")));
+    assert!(position(&lines, "info depth 1 ") < position(&lines, "readyok"));
+    assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+}
+
+#[test]
+fn test_ponder() {
+    let (mut uci, out) = engine();
+    assert!(uci.handle("position startpos moves e2e4"));
+    // about 70ms to think once the clock runs, which it does not while pondering
+    assert!(uci.handle("go ponder wtime 2000 btime 2000"));
+    wait_for(&out, "info depth 2 ");
+    assert!(uci.handle("isready"));
+    wait_for(&out, "readyok");
+
+    let before = written(&out).lines().count();
+    let lines = run(&mut uci, &out, &["ponderhit"]);
+    // nothing is decided while pondering, the best move only comes after the hit
+    assert!(!lines[..before].iter().any(|line| line.starts_with("bestmove")));
+    assert_eq!(lines.iter().filter(|line| line.starts_with("bestmove")).count(), 1);
+    let bestmove: Vec<&str> = lines.last().unwrap().split(' ').collect();
+    assert_eq!((bestmove.len(), bestmove[0], bestmove[2]), (4, "bestmove", "ponder"));
+
+    // stopping a ponder search still gives a best move
+    assert!(uci.handle("go ponder wtime 2000 btime 2000"));
+    wait_for(&out, "info depth 1 ");
+    let lines = run(&mut uci, &out, &["stop"]);
+    assert!(position(&lines, "info depth 1 ") < position(&lines, "bestmove "));
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+fn test_book_file() {
+    let path = std::env::temp_dir().join("rusty-uci-test.bin");
+    let key: u64 = 0x463b96181691fc9c;
+    let mut bytes = key.to_be_bytes().to_vec();
+    bytes.extend((3u16 | 3 << 3 | 3 << 6 | 1 << 9).to_be_bytes());
+    bytes.extend(1u16.to_be_bytes());
+    bytes.extend(0u32.to_be_bytes());
+    std::fs::write(&path, bytes).unwrap();
+
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &[
+        &format!("setoption name BookFile value {}", path.display()),
+        "setoption name OwnBook value true",
+        "position startpos",
+        "go wtime 1000 btime 1000",
+    ]);
+    assert_eq!(lines[0], format!("info string loaded 1 book entries from {}", path.display()));
+    assert_eq!(lines.last().unwrap(), "bestmove d2d4");
+    // a search limited by depth, nodes or mate is meant to search
+    for go in ["go depth 1", "go nodes 500", "go mate 1"] {
+        let lines = run(&mut uci, &out, &[go]);
+        assert!(!lines.contains(&"info string book move".to_string()), "{}", go);
+        assert!(lines.iter().any(|line| line.starts_with("info depth ")), "{}", go);
+    }
+
+    let lines = run(&mut uci, &out, &["setoption name BookFile value /does/not/exist.bin"]);
+    assert!(lines[0].starts_with("info string cannot load book"));
+}
+
+#[test]
+fn test_strength_options() {
+    let (mut uci, out) = engine();
+    let lines = run(&mut uci, &out, &["setoption name UCI_Elo value 3500", "setoption name Skill Level value 21"]);
+    assert_eq!(lines, ["info string invalid elo 3500", "info string invalid skill level 21"]);
+
+    // the weakest level searches a few hundred nodes, and reports only the line asked for
+    let lines = run(&mut uci, &out, &["setoption name UCI_LimitStrength value true", "setoption name UCI_Elo value 800", "position startpos", "go movetime 5000"]);
+    let infos: Vec<&String> = lines.iter().filter(|line| line.starts_with("info depth")).collect();
+    assert!(!infos.is_empty());
+    assert!(infos.iter().all(|line| line.contains(" multipv 1 ")));
+    let bestmove = lines.last().unwrap().split_whitespace().nth(1).unwrap().to_string();
+    assert!(parse_move(&Board::default(), &bestmove).is_some());
+
+    // weaker play is randomized once per game, not for every move
+    let seed = uci.skill_seed;
+    run(&mut uci, &out, &["position startpos moves e2e4 e7e5", "go movetime 5000"]);
+    assert_eq!(uci.skill_seed, seed);
+    run(&mut uci, &out, &["ucinewgame"]);
+    assert_ne!(uci.skill_seed, seed);
+}